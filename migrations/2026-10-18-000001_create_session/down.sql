@@ -0,0 +1,2 @@
+DROP INDEX "session_user_id_idx";
+DROP TABLE "session";
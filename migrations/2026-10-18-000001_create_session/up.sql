@@ -0,0 +1,11 @@
+CREATE TABLE "session"
+(
+    "id" UUID PRIMARY KEY NOT NULL,
+    "user_id" UUID NOT NULL,
+    "created_at" TIMESTAMP NOT NULL,
+    "expires_at" TIMESTAMP NOT NULL,
+    "revoked" BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY("user_id") REFERENCES "user"("id")
+);
+
+CREATE INDEX "session_user_id_idx" ON "session"("user_id");
@@ -0,0 +1,10 @@
+CREATE TABLE "password_reset"
+(
+    "hash" VARCHAR PRIMARY KEY NOT NULL,
+    "user_id" UUID NOT NULL,
+    "created_by" UUID NOT NULL,
+    "expires_at" TIMESTAMP NOT NULL,
+    "used" BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY("user_id") REFERENCES "user"("id"),
+    FOREIGN KEY("created_by") REFERENCES "user"("id")
+);
@@ -0,0 +1 @@
+ALTER TABLE "user" ADD COLUMN "is_active" BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,12 @@
+CREATE TABLE "audit_event"
+(
+    "id" UUID PRIMARY KEY NOT NULL,
+    "created_at" TIMESTAMP NOT NULL,
+    "kind" VARCHAR(64) NOT NULL,
+    "actor_id" UUID,
+    "ip" VARCHAR(64),
+    "detail" JSONB NOT NULL DEFAULT '{}',
+    FOREIGN KEY("actor_id") REFERENCES "user"("id")
+);
+
+CREATE INDEX "audit_event_created_at_idx" ON "audit_event"("created_at");
@@ -0,0 +1,6 @@
+DROP INDEX "recovery_code_user_id_idx";
+DROP TABLE "recovery_code";
+
+ALTER TABLE "user" DROP COLUMN "totp_last_step";
+ALTER TABLE "user" DROP COLUMN "totp_enabled";
+ALTER TABLE "user" DROP COLUMN "totp_secret";
@@ -0,0 +1,13 @@
+ALTER TABLE "user" ADD COLUMN "totp_secret" VARCHAR;
+ALTER TABLE "user" ADD COLUMN "totp_enabled" BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE "user" ADD COLUMN "totp_last_step" BIGINT;
+
+CREATE TABLE "recovery_code"
+(
+    "hash" VARCHAR PRIMARY KEY NOT NULL,
+    "user_id" UUID NOT NULL,
+    "used" BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY("user_id") REFERENCES "user"("id")
+);
+
+CREATE INDEX "recovery_code_user_id_idx" ON "recovery_code"("user_id");
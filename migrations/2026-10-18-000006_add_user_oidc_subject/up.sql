@@ -0,0 +1 @@
+ALTER TABLE "user" ADD COLUMN "oidc_subject" VARCHAR UNIQUE;
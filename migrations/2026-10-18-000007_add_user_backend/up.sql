@@ -0,0 +1 @@
+ALTER TABLE "user" ADD COLUMN "backend" VARCHAR(16) NOT NULL DEFAULT 'local';
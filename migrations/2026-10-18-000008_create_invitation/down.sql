@@ -0,0 +1,2 @@
+DROP TABLE "invitation";
+DROP TABLE "project_member";
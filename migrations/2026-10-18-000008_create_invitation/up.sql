@@ -0,0 +1,23 @@
+CREATE TABLE "project_member"
+(
+    "project_id" UUID NOT NULL,
+    "user_id" UUID NOT NULL,
+    "role" VARCHAR(16) NOT NULL,
+    FOREIGN KEY("project_id") REFERENCES "project"("id"),
+    FOREIGN KEY("user_id") REFERENCES "user"("id"),
+    PRIMARY KEY("project_id", "user_id")
+);
+
+CREATE TABLE "invitation"
+(
+    "hash" VARCHAR PRIMARY KEY NOT NULL,
+    "created_by" UUID NOT NULL,
+    "created_at" TIMESTAMP NOT NULL,
+    "expires_at" TIMESTAMP NOT NULL,
+    "project_id" UUID,
+    "role" VARCHAR(16),
+    "used_by" UUID,
+    FOREIGN KEY("created_by") REFERENCES "user"("id"),
+    FOREIGN KEY("project_id") REFERENCES "project"("id"),
+    FOREIGN KEY("used_by") REFERENCES "user"("id")
+);
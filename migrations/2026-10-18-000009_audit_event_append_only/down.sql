@@ -0,0 +1,5 @@
+DROP TRIGGER "audit_event_append_only" ON "audit_event";
+DROP FUNCTION "audit_event_reject_change";
+
+DROP INDEX "audit_event_actor_id_idx";
+DROP INDEX "audit_event_kind_idx";
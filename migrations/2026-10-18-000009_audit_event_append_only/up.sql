@@ -0,0 +1,13 @@
+CREATE INDEX "audit_event_kind_idx" ON "audit_event"("kind", "created_at");
+CREATE INDEX "audit_event_actor_id_idx" ON "audit_event"("actor_id", "created_at");
+
+-- The audit log is append-only.
+CREATE FUNCTION "audit_event_reject_change"() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_event is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER "audit_event_append_only"
+    BEFORE UPDATE OR DELETE ON "audit_event"
+    FOR EACH STATEMENT EXECUTE FUNCTION "audit_event_reject_change"();
@@ -0,0 +1,14 @@
+ALTER TABLE "invitation" DROP CONSTRAINT "invitation_project_id_fkey",
+    ADD FOREIGN KEY("project_id") REFERENCES "project"("id");
+ALTER TABLE "project_member" DROP CONSTRAINT "project_member_project_id_fkey",
+    ADD FOREIGN KEY("project_id") REFERENCES "project"("id");
+ALTER TABLE "record" DROP CONSTRAINT "record_commit_id_fkey",
+    ADD FOREIGN KEY("commit_id") REFERENCES "commit"("id");
+ALTER TABLE "commit" DROP CONSTRAINT "commit_unit_id_fkey",
+    ADD FOREIGN KEY("unit_id") REFERENCES "unit"("id");
+ALTER TABLE "source" DROP CONSTRAINT "source_unit_id_fkey",
+    ADD FOREIGN KEY("unit_id") REFERENCES "unit"("id");
+ALTER TABLE "unit" DROP CONSTRAINT "unit_project_id_fkey",
+    ADD FOREIGN KEY("project_id") REFERENCES "project"("id");
+
+ALTER TABLE "project" DROP COLUMN "is_archived";
@@ -0,0 +1,15 @@
+ALTER TABLE "project" ADD COLUMN "is_archived" BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Deleting a project removes everything it owns.
+ALTER TABLE "unit" DROP CONSTRAINT "unit_project_id_fkey",
+    ADD FOREIGN KEY("project_id") REFERENCES "project"("id") ON DELETE CASCADE;
+ALTER TABLE "source" DROP CONSTRAINT "source_unit_id_fkey",
+    ADD FOREIGN KEY("unit_id") REFERENCES "unit"("id") ON DELETE CASCADE;
+ALTER TABLE "commit" DROP CONSTRAINT "commit_unit_id_fkey",
+    ADD FOREIGN KEY("unit_id") REFERENCES "unit"("id") ON DELETE CASCADE;
+ALTER TABLE "record" DROP CONSTRAINT "record_commit_id_fkey",
+    ADD FOREIGN KEY("commit_id") REFERENCES "commit"("id") ON DELETE CASCADE;
+ALTER TABLE "project_member" DROP CONSTRAINT "project_member_project_id_fkey",
+    ADD FOREIGN KEY("project_id") REFERENCES "project"("id") ON DELETE CASCADE;
+ALTER TABLE "invitation" DROP CONSTRAINT "invitation_project_id_fkey",
+    ADD FOREIGN KEY("project_id") REFERENCES "project"("id") ON DELETE CASCADE;
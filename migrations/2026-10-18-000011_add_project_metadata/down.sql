@@ -0,0 +1,6 @@
+ALTER TABLE "project"
+    DROP COLUMN "settings",
+    DROP COLUMN "qa_profile",
+    DROP COLUMN "description",
+    DROP COLUMN "target_language_list",
+    DROP COLUMN "source_language";
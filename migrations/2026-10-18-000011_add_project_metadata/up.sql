@@ -0,0 +1,6 @@
+ALTER TABLE "project"
+    ADD COLUMN "source_language" VARCHAR(35),
+    ADD COLUMN "target_language_list" VARCHAR(35)[] NOT NULL DEFAULT '{}',
+    ADD COLUMN "description" VARCHAR NOT NULL DEFAULT '',
+    ADD COLUMN "qa_profile" VARCHAR(64),
+    ADD COLUMN "settings" JSONB NOT NULL DEFAULT '{}';
@@ -0,0 +1,30 @@
+DROP TRIGGER on_insert_commit ON "commit";
+DROP FUNCTION update_unit_head;
+
+ALTER TABLE "unit" ADD COLUMN "commit_id" UUID;
+
+-- Only the most recent head of each unit survives.
+UPDATE "unit"
+SET "commit_id" = (
+    SELECT "commit"."id"
+    FROM "unit_head" JOIN "commit" ON "commit"."id" = "unit_head"."commit_id"
+    WHERE "unit_head"."unit_id" = "unit"."id"
+    ORDER BY "commit"."created_at" DESC
+    LIMIT 1);
+
+DROP TABLE "unit_head";
+
+CREATE FUNCTION update_latest_commit() RETURNS TRIGGER AS $on_insert_commit$
+    BEGIN
+        UPDATE "unit" SET "commit_id" = NEW."id" WHERE "id" = NEW."unit_id";
+        RETURN NULL;
+    END;
+$on_insert_commit$ LANGUAGE plpgsql;
+
+CREATE TRIGGER on_insert_commit AFTER INSERT
+    ON "commit"
+    FOR EACH ROW EXECUTE FUNCTION update_latest_commit();
+
+DROP INDEX "commit_unit_id_language_idx";
+
+ALTER TABLE "commit" DROP COLUMN "language";
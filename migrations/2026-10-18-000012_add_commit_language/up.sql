@@ -0,0 +1,45 @@
+ALTER TABLE "commit" ADD COLUMN "language" VARCHAR(35);
+
+-- Existing commits are taken to be in the project's first target language.
+UPDATE "commit"
+SET "language" = COALESCE(
+    (SELECT "project"."target_language_list"[1]
+     FROM "unit" JOIN "project" ON "project"."id" = "unit"."project_id"
+     WHERE "unit"."id" = "commit"."unit_id"),
+    'und');
+
+ALTER TABLE "commit" ALTER COLUMN "language" SET NOT NULL;
+
+CREATE INDEX "commit_unit_id_language_idx" ON "commit"("unit_id", "language", "created_at");
+
+CREATE TABLE "unit_head"
+(
+    "unit_id" UUID NOT NULL,
+    "language" VARCHAR(35) NOT NULL,
+    "commit_id" UUID NOT NULL,
+    FOREIGN KEY("unit_id") REFERENCES "unit"("id") ON DELETE CASCADE,
+    FOREIGN KEY("commit_id") REFERENCES "commit"("id") ON DELETE CASCADE,
+    PRIMARY KEY("unit_id", "language")
+);
+
+INSERT INTO "unit_head"("unit_id", "language", "commit_id")
+SELECT "unit"."id", "commit"."language", "commit"."id"
+FROM "unit" JOIN "commit" ON "commit"."id" = "unit"."commit_id";
+
+DROP TRIGGER on_insert_commit ON "commit";
+DROP FUNCTION update_latest_commit;
+
+ALTER TABLE "unit" DROP COLUMN "commit_id";
+
+CREATE FUNCTION update_unit_head() RETURNS TRIGGER AS $on_insert_commit$
+    BEGIN
+        INSERT INTO "unit_head"("unit_id", "language", "commit_id")
+        VALUES (NEW."unit_id", NEW."language", NEW."id")
+        ON CONFLICT ("unit_id", "language") DO UPDATE SET "commit_id" = EXCLUDED."commit_id";
+        RETURN NULL;
+    END;
+$on_insert_commit$ LANGUAGE plpgsql;
+
+CREATE TRIGGER on_insert_commit AFTER INSERT
+    ON "commit"
+    FOR EACH ROW EXECUTE FUNCTION update_unit_head();
@@ -0,0 +1,12 @@
+ALTER TABLE "record" DROP COLUMN "needs_retranslation";
+
+ALTER TABLE "commit" DROP COLUMN "source_version";
+
+DELETE FROM "source"
+USING "unit"
+WHERE "unit"."id" = "source"."unit_id" AND "source"."version" <> "unit"."source_version";
+ALTER TABLE "source" DROP CONSTRAINT "source_pkey",
+    ADD PRIMARY KEY("unit_id", "sq");
+ALTER TABLE "source" DROP COLUMN "version";
+
+ALTER TABLE "unit" DROP COLUMN "source_version";
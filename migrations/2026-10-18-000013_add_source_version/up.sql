@@ -0,0 +1,11 @@
+-- Source lists are versioned, and commits refer to the version their
+-- records were written against.
+ALTER TABLE "unit" ADD COLUMN "source_version" INTEGER NOT NULL DEFAULT 1;
+
+ALTER TABLE "source" ADD COLUMN "version" INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE "source" DROP CONSTRAINT "source_pkey",
+    ADD PRIMARY KEY("unit_id", "version", "sq");
+
+ALTER TABLE "commit" ADD COLUMN "source_version" INTEGER NOT NULL DEFAULT 1;
+
+ALTER TABLE "record" ADD COLUMN "needs_retranslation" BOOLEAN NOT NULL DEFAULT FALSE;
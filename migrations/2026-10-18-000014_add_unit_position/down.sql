@@ -0,0 +1,3 @@
+DROP INDEX "unit_project_id_position_idx";
+
+ALTER TABLE "unit" DROP COLUMN "position";
@@ -0,0 +1,9 @@
+ALTER TABLE "unit" ADD COLUMN "position" INTEGER NOT NULL DEFAULT 0;
+
+UPDATE "unit"
+SET "position" = "ordered"."position"
+FROM (SELECT "id", ROW_NUMBER() OVER (PARTITION BY "project_id" ORDER BY "title", "id") - 1 AS "position"
+      FROM "unit") AS "ordered"
+WHERE "unit"."id" = "ordered"."id";
+
+CREATE INDEX "unit_project_id_position_idx" ON "unit"("project_id", "position");
@@ -0,0 +1,5 @@
+DROP INDEX "unit_folder_id_idx";
+
+ALTER TABLE "unit" DROP COLUMN "folder_id";
+
+DROP TABLE "folder";
@@ -0,0 +1,19 @@
+CREATE TABLE "folder"
+(
+    "id" UUID PRIMARY KEY NOT NULL,
+    "project_id" UUID NOT NULL,
+    "parent_id" UUID,
+    "name" VARCHAR(256) NOT NULL,
+    "position" INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY("project_id") REFERENCES "project"("id") ON DELETE CASCADE,
+    UNIQUE("id", "project_id"),
+    -- A parent folder belongs to the same project.
+    FOREIGN KEY("parent_id", "project_id") REFERENCES "folder"("id", "project_id")
+);
+
+CREATE INDEX "folder_project_id_parent_id_idx" ON "folder"("project_id", "parent_id");
+
+ALTER TABLE "unit" ADD COLUMN "folder_id" UUID,
+    ADD FOREIGN KEY("folder_id", "project_id") REFERENCES "folder"("id", "project_id");
+
+CREATE INDEX "unit_folder_id_idx" ON "unit"("folder_id");
@@ -0,0 +1,3 @@
+DROP FUNCTION "word_count"(TEXT);
+
+ALTER TABLE "record" DROP COLUMN "is_reviewed";
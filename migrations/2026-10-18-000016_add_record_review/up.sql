@@ -0,0 +1,6 @@
+ALTER TABLE "record" ADD COLUMN "is_reviewed" BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Words are runs of non-space characters.
+CREATE FUNCTION "word_count"(TEXT) RETURNS INTEGER AS $$
+    SELECT COUNT(*)::INTEGER FROM regexp_matches($1, '\S+', 'g')
+$$ LANGUAGE SQL IMMUTABLE STRICT PARALLEL SAFE;
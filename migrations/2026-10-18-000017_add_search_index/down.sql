@@ -0,0 +1,7 @@
+DROP INDEX "record_content_fts_idx";
+DROP INDEX "source_meta_fts_idx";
+DROP INDEX "source_content_fts_idx";
+
+DROP INDEX "record_content_trgm_idx";
+DROP INDEX "source_meta_trgm_idx";
+DROP INDEX "source_content_trgm_idx";
@@ -0,0 +1,12 @@
+CREATE EXTENSION IF NOT EXISTS "pg_trgm";
+
+-- Substring and regular expression search.
+CREATE INDEX "source_content_trgm_idx" ON "source" USING GIN ("content" gin_trgm_ops);
+CREATE INDEX "source_meta_trgm_idx" ON "source" USING GIN ("meta" gin_trgm_ops);
+CREATE INDEX "record_content_trgm_idx" ON "record" USING GIN ("content" gin_trgm_ops);
+
+-- Full-text search. The simple configuration does not stem, so it suits any
+-- language.
+CREATE INDEX "source_content_fts_idx" ON "source" USING GIN (to_tsvector('simple', "content"));
+CREATE INDEX "source_meta_fts_idx" ON "source" USING GIN (to_tsvector('simple', "meta"));
+CREATE INDEX "record_content_fts_idx" ON "record" USING GIN (to_tsvector('simple', "content"));
@@ -0,0 +1,11 @@
+DROP INDEX "source_meta_fts_idx";
+DROP INDEX "source_meta_trgm_idx";
+DROP INDEX "source_meta_idx";
+
+DROP FUNCTION "meta_text"(JSONB);
+
+ALTER TABLE "source" ALTER COLUMN "meta" DROP DEFAULT,
+    ALTER COLUMN "meta" TYPE VARCHAR USING CASE "meta" WHEN '{}' THEN '' ELSE "meta"::TEXT END;
+
+CREATE INDEX "source_meta_trgm_idx" ON "source" USING GIN ("meta" gin_trgm_ops);
+CREATE INDEX "source_meta_fts_idx" ON "source" USING GIN (to_tsvector('simple', "meta"));
@@ -0,0 +1,33 @@
+-- Keeps JSON objects, and moves any other text under the context key.
+CREATE FUNCTION "convert_source_meta"(TEXT) RETURNS JSONB AS $$
+BEGIN
+    IF $1 = '' THEN
+        RETURN '{}';
+    END IF;
+    IF jsonb_typeof($1::JSONB) = 'object' THEN
+        RETURN $1::JSONB;
+    END IF;
+    RETURN jsonb_build_object('context', $1);
+EXCEPTION WHEN invalid_text_representation THEN
+    RETURN jsonb_build_object('context', $1);
+END;
+$$ LANGUAGE plpgsql IMMUTABLE STRICT;
+
+DROP INDEX "source_meta_fts_idx";
+DROP INDEX "source_meta_trgm_idx";
+
+ALTER TABLE "source" ALTER COLUMN "meta" TYPE JSONB USING convert_source_meta("meta"),
+    ALTER COLUMN "meta" SET DEFAULT '{}';
+
+DROP FUNCTION "convert_source_meta"(TEXT);
+
+-- Finds lines by well-known keys, such as the speaker.
+CREATE INDEX "source_meta_idx" ON "source" USING GIN ("meta" jsonb_path_ops);
+
+-- The values of the top-level keys, for search.
+CREATE FUNCTION "meta_text"(JSONB) RETURNS TEXT AS $$
+    SELECT COALESCE(string_agg("value", ' '), '') FROM jsonb_each_text($1)
+$$ LANGUAGE SQL IMMUTABLE STRICT PARALLEL SAFE;
+
+CREATE INDEX "source_meta_trgm_idx" ON "source" USING GIN (meta_text("meta") gin_trgm_ops);
+CREATE INDEX "source_meta_fts_idx" ON "source" USING GIN (to_tsvector('simple', meta_text("meta")));
@@ -0,0 +1,15 @@
+-- Machine translations of source lines, kept apart from the records people
+-- commit until someone accepts them.
+CREATE TABLE "suggestion"
+(
+    "unit_id" UUID NOT NULL,
+    "source_version" INTEGER NOT NULL,
+    "sq" INTEGER NOT NULL,
+    "language" VARCHAR(35) NOT NULL,
+    "content" VARCHAR NOT NULL,
+    "provider" VARCHAR(64) NOT NULL,
+    "created_at" TIMESTAMP NOT NULL,
+    FOREIGN KEY("unit_id", "source_version", "sq")
+        REFERENCES "source"("unit_id", "version", "sq") ON DELETE CASCADE,
+    PRIMARY KEY("unit_id", "language", "source_version", "sq")
+);
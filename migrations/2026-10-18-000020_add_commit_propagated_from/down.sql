@@ -0,0 +1 @@
+ALTER TABLE "commit" DROP COLUMN "propagated_from_id";
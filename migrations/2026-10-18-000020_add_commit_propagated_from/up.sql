@@ -0,0 +1,3 @@
+-- Marks commits copying a translation to repetitions of its source line.
+-- Not a foreign key, so the mark stays after the original unit is deleted.
+ALTER TABLE "commit" ADD COLUMN "propagated_from_id" UUID;
@@ -0,0 +1,18 @@
+-- Soft locks telling other editors who is working on some lines of a unit,
+-- or the whole unit if no range is set. Expired locks are ignored.
+CREATE TABLE "line_lock"
+(
+    "id" UUID PRIMARY KEY NOT NULL,
+    "unit_id" UUID NOT NULL,
+    "language" VARCHAR(35) NOT NULL,
+    "first_sq" INTEGER,
+    "last_sq" INTEGER,
+    "holder_id" UUID NOT NULL,
+    "created_at" TIMESTAMP NOT NULL,
+    "expires_at" TIMESTAMP NOT NULL,
+    FOREIGN KEY("unit_id") REFERENCES "unit"("id") ON DELETE CASCADE,
+    FOREIGN KEY("holder_id") REFERENCES "user"("id") ON DELETE CASCADE,
+    CHECK(("first_sq" IS NULL) = ("last_sq" IS NULL) AND "first_sq" <= "last_sq")
+);
+
+CREATE INDEX "line_lock_unit_id_language_idx" ON "line_lock"("unit_id", "language");
@@ -0,0 +1,10 @@
+INSERT INTO "record"("commit_id", "sq", "content", "needs_retranslation", "is_reviewed")
+SELECT "resolved".*
+FROM "commit", "commit_record"("commit"."id") AS "resolved"
+WHERE "commit"."base_id" IS NOT NULL
+ON CONFLICT DO NOTHING;
+
+DROP FUNCTION "commit_record";
+
+ALTER TABLE "commit" DROP COLUMN "delta_depth";
+ALTER TABLE "commit" DROP COLUMN "base_id";
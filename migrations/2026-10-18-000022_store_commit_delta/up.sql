@@ -0,0 +1,70 @@
+-- A commit stores only the records that differ from its base, the latest
+-- commit it was made on. Commits without a base store every record. At most
+-- 32 deltas follow each other before a commit stores every record again.
+ALTER TABLE "commit" ADD COLUMN "base_id" UUID REFERENCES "commit"("id");
+ALTER TABLE "commit" ADD COLUMN "delta_depth" INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX "commit_base_id_idx" ON "commit"("base_id");
+
+-- The records of a commit, resolved along its bases.
+CREATE FUNCTION "commit_record"(UUID)
+RETURNS TABLE (
+    "commit_id" UUID,
+    "sq" INTEGER,
+    "content" VARCHAR,
+    "needs_retranslation" BOOLEAN,
+    "is_reviewed" BOOLEAN
+) AS $$
+    WITH RECURSIVE "chain"("id", "base_id", "distance") AS (
+        SELECT "commit"."id", "commit"."base_id", 0
+        FROM "commit" WHERE "commit"."id" = $1
+        UNION ALL
+        SELECT "commit"."id", "commit"."base_id", "chain"."distance" + 1
+        FROM "commit" INNER JOIN "chain" ON "commit"."id" = "chain"."base_id"
+    )
+    SELECT DISTINCT ON ("record"."sq") $1, "record"."sq", "record"."content",
+        "record"."needs_retranslation", "record"."is_reviewed"
+    FROM "chain" INNER JOIN "record" ON "record"."commit_id" = "chain"."id"
+    ORDER BY "record"."sq", "chain"."distance"
+$$ LANGUAGE SQL STABLE STRICT;
+
+-- Existing commits follow the one before them on the same source version.
+UPDATE "commit" SET "base_id" = "delta"."base_id", "delta_depth" = "delta"."delta_depth"
+FROM (
+    SELECT "id",
+        "previous_id" AS "base_id",
+        "position" % 33 AS "delta_depth"
+    FROM (
+        SELECT "id",
+            LAG("id") OVER "history" AS "previous_id",
+            ROW_NUMBER() OVER "history" - 1 AS "position"
+        FROM "commit"
+        WINDOW "history" AS (
+            PARTITION BY "unit_id", "language", "source_version"
+            ORDER BY "created_at", "id"
+        )
+    ) AS "ordered"
+) AS "delta"
+WHERE "commit"."id" = "delta"."id" AND "delta"."delta_depth" > 0;
+
+-- A delta cannot drop lines, so commits dropping some of their base keep
+-- every record.
+UPDATE "commit" SET "base_id" = NULL, "delta_depth" = 0
+WHERE "base_id" IS NOT NULL AND EXISTS (
+    SELECT FROM "record" AS "base"
+    WHERE "base"."commit_id" = "commit"."base_id" AND NOT EXISTS (
+        SELECT FROM "record"
+        WHERE "record"."commit_id" = "commit"."id" AND "record"."sq" = "base"."sq"
+    )
+);
+
+-- Every commit still stores all of its records, so each delta is compared
+-- with its base as it was.
+DELETE FROM "record"
+USING "commit", "record" AS "base"
+WHERE "record"."commit_id" = "commit"."id"
+    AND "base"."commit_id" = "commit"."base_id"
+    AND "base"."sq" = "record"."sq"
+    AND "base"."content" = "record"."content"
+    AND "base"."needs_retranslation" = "record"."needs_retranslation"
+    AND "base"."is_reviewed" = "record"."is_reviewed";
@@ -0,0 +1 @@
+DROP FUNCTION "commit_record_list";
@@ -0,0 +1,22 @@
+-- The records of many commits, resolved along their bases in one pass so
+-- that listing heads does not walk each chain on its own.
+CREATE FUNCTION "commit_record_list"(UUID[])
+RETURNS TABLE (
+    "commit_id" UUID,
+    "sq" INTEGER,
+    "content" VARCHAR,
+    "needs_retranslation" BOOLEAN,
+    "is_reviewed" BOOLEAN
+) AS $$
+    WITH RECURSIVE "chain"("head_id", "id", "base_id", "distance") AS (
+        SELECT "commit"."id", "commit"."id", "commit"."base_id", 0
+        FROM "commit" WHERE "commit"."id" = ANY($1)
+        UNION ALL
+        SELECT "chain"."head_id", "commit"."id", "commit"."base_id", "chain"."distance" + 1
+        FROM "commit" INNER JOIN "chain" ON "commit"."id" = "chain"."base_id"
+    )
+    SELECT DISTINCT ON ("chain"."head_id", "record"."sq") "chain"."head_id", "record"."sq",
+        "record"."content", "record"."needs_retranslation", "record"."is_reviewed"
+    FROM "chain" INNER JOIN "record" ON "record"."commit_id" = "chain"."id"
+    ORDER BY "chain"."head_id", "record"."sq", "chain"."distance"
+$$ LANGUAGE SQL STABLE STRICT;
@@ -0,0 +1,153 @@
+use std::net::SocketAddr;
+
+use axum::extract::{FromRef, Query, State};
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::{routing, Json, Router};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::api::ServiceError;
+use crate::auth::{AuthRwLock, Claim};
+use crate::repo;
+
+const AUDIT_EVENT_LIST_LIMIT: i64 = 100;
+
+pub fn build_router<S>() -> Router<S>
+where
+    S: Send + Sync + Clone + 'static,
+    AuthRwLock: FromRef<S>,
+    repo::Repo: FromRef<S>,
+{
+    Router::new()
+        .route("/", routing::get(get_list))
+        .route("/export", routing::get(export))
+}
+
+/// Appends an event to the audit log. Failing to record is logged rather than
+/// failing the request, which has already taken effect.
+pub fn record(
+    repo: &repo::Repo,
+    kind: &str,
+    actor_id: Option<Uuid>,
+    address: Option<SocketAddr>,
+    detail: serde_json::Value,
+) {
+    let audit_event = repo::AuditEvent {
+        id: Uuid::new_v4(),
+        created_at: Utc::now().naive_utc(),
+        kind: String::from(kind),
+        actor_id,
+        ip: address.map(|t| t.ip().to_string()),
+        detail,
+    };
+    if let Err(error) = repo.add_audit_event(audit_event) {
+        tracing::warn!("Failed to record audit event {}: {}", kind, error);
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AuditEvent {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub kind: String,
+    pub actor_id: Option<Uuid>,
+    pub ip: Option<String>,
+    pub detail: serde_json::Value,
+}
+
+impl From<repo::AuditEvent> for AuditEvent {
+    fn from(audit_event: repo::AuditEvent) -> Self {
+        AuditEvent {
+            id: audit_event.id,
+            created_at: audit_event.created_at.and_utc(),
+            kind: audit_event.kind,
+            actor_id: audit_event.actor_id,
+            ip: audit_event.ip,
+            detail: audit_event.detail,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct FilterQuery {
+    pub kind: Option<String>,
+    pub actor_id: Option<Uuid>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+impl FilterQuery {
+    fn to_filter(&self) -> repo::AuditEventFilter {
+        repo::AuditEventFilter {
+            kind: self.kind.clone(),
+            actor_id: self.actor_id,
+            since: self.since.map(|t| t.naive_utc()),
+            until: self.until.map(|t| t.naive_utc()),
+        }
+    }
+}
+
+async fn get_list(
+    State(repo): State<repo::Repo>,
+    claim: Claim,
+    Query(query): Query<FilterQuery>,
+) -> Result<Json<Vec<AuditEvent>>, ServiceError> {
+    claim.require_admin()?;
+
+    let audit_event_list = repo.get_audit_event(
+        query.to_filter(),
+        query.offset.unwrap_or(0).max(0),
+        Some(
+            query
+                .limit
+                .unwrap_or(AUDIT_EVENT_LIST_LIMIT)
+                .clamp(0, AUDIT_EVENT_LIST_LIMIT),
+        ),
+    )?;
+
+    Ok(Json(
+        audit_event_list
+            .into_iter()
+            .map(AuditEvent::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// All matching events as JSON lines, ignoring pagination unless given.
+async fn export(
+    State(repo): State<repo::Repo>,
+    claim: Claim,
+    Query(query): Query<FilterQuery>,
+) -> Result<impl IntoResponse, ServiceError> {
+    claim.require_admin()?;
+
+    let audit_event_list = repo.get_audit_event(
+        query.to_filter(),
+        query.offset.unwrap_or(0).max(0),
+        query.limit.map(|t| t.max(0)),
+    )?;
+
+    let mut body = String::new();
+    for audit_event in audit_event_list {
+        body += &serde_json::to_string(&AuditEvent::from(audit_event))
+            .map_err(|_| repo::Error::DataError)?;
+        body.push('\n');
+    }
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"audit.jsonl\"",
+            ),
+        ],
+        body,
+    ))
+}
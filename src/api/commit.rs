@@ -1,10 +1,18 @@
-use axum::extract::{FromRef, Json, Query, State};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRef, Json, Query, State};
+use axum::http::StatusCode;
 use axum::{routing, Router};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use uuid::Uuid;
 
-use crate::api::ServiceError;
+use crate::api::length_limit::{LengthLimit, Violation};
+use crate::api::propagation::{PropagatedCommit, Propagation};
+use crate::api::source_meta::SourceMeta;
+use crate::api::{audit, language, lock, ServiceError};
 use crate::auth::{AuthRwLock, Claim};
 use crate::repo;
 
@@ -18,12 +26,22 @@ where
         .route("/", routing::get(get_list).post(add))
         .route("/by-id", routing::get(get_by_id))
         .route("/record", routing::get(get_record_list))
+        .route("/head", routing::get(get_head))
+        .route("/head/record", routing::get(get_head_record_list))
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 struct UnitIdQuery {
     pub unit_id: Uuid,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct HeadQuery {
+    pub unit_id: Uuid,
+    pub language: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -32,22 +50,39 @@ struct Commit {
     pub id: Uuid,
     pub created_by: Uuid,
     pub created_at: DateTime<Utc>,
+    pub language: String,
+    pub source_version: i32,
+    /// Set on commits filling repetitions of the lines of another.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub propagated_from_id: Option<Uuid>,
+}
+
+impl From<repo::Commit> for Commit {
+    fn from(commit: repo::Commit) -> Self {
+        Commit {
+            id: commit.id,
+            created_by: commit.editor_id,
+            created_at: commit.created_at.and_utc(),
+            language: commit.language,
+            source_version: commit.source_version,
+            propagated_from_id: commit.propagated_from_id,
+        }
+    }
 }
 
 async fn get_list(
     State(repo): State<repo::Repo>,
     Query(query): Query<UnitIdQuery>,
 ) -> Result<Json<Vec<Commit>>, ServiceError> {
-    let commit_list = repo.get_commit_by_unit_id(query.unit_id)?;
+    let commit_list = repo.get_commit_by_unit_id(
+        query.unit_id,
+        query.language.map(language::normalize_filter),
+    )?;
 
     Ok(Json(
         commit_list
             .into_iter()
-            .map(|t| Commit {
-                id: t.id,
-                created_by: t.editor_id,
-                created_at: t.created_at.and_utc(),
-            })
+            .map(Commit::from)
             .collect::<Vec<_>>(),
     ))
 }
@@ -64,11 +99,17 @@ async fn get_by_id(
 ) -> Result<Json<Commit>, ServiceError> {
     let commit = repo.get_commit_by_id(query.id)?;
 
-    Ok(Json(Commit {
-        id: commit.id,
-        created_by: commit.editor_id,
-        created_at: commit.created_at.and_utc(),
-    }))
+    Ok(Json(Commit::from(commit)))
+}
+
+/// The latest commit of a unit in a language.
+async fn get_head(
+    State(repo): State<repo::Repo>,
+    Query(query): Query<HeadQuery>,
+) -> Result<Json<Commit>, ServiceError> {
+    let head = repo.get_unit_head(query.unit_id, language::normalize_filter(query.language))?;
+
+    Ok(Json(Commit::from(repo.get_commit_by_id(head.commit_id)?)))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -76,6 +117,11 @@ async fn get_by_id(
 struct Record {
     pub sq: i32,
     pub content: String,
+    /// Set on lines carried over from before their source changed.
+    #[serde(default)]
+    pub needs_retranslation: bool,
+    #[serde(default)]
+    pub is_reviewed: bool,
 }
 
 async fn get_record_list(
@@ -90,6 +136,29 @@ async fn get_record_list(
             .map(|t| Record {
                 sq: t.sq,
                 content: t.content,
+                needs_retranslation: t.needs_retranslation,
+                is_reviewed: t.is_reviewed,
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// The records of the latest commit of a unit in a language.
+async fn get_head_record_list(
+    State(repo): State<repo::Repo>,
+    Query(query): Query<HeadQuery>,
+) -> Result<Json<Vec<Record>>, ServiceError> {
+    let head = repo.get_unit_head(query.unit_id, language::normalize_filter(query.language))?;
+    let record_list = repo.get_record_by_commit_id(head.commit_id)?;
+
+    Ok(Json(
+        record_list
+            .into_iter()
+            .map(|t| Record {
+                sq: t.sq,
+                content: t.content,
+                needs_retranslation: t.needs_retranslation,
+                is_reviewed: t.is_reviewed,
             })
             .collect::<Vec<_>>(),
     ))
@@ -99,34 +168,245 @@ async fn get_record_list(
 #[serde(rename_all = "camelCase")]
 struct NewCommit {
     pub unit_id: Uuid,
+    pub language: String,
+    /// The source version the records were written against, the current
+    /// one if not given.
+    pub source_version: Option<i32>,
+    /// If given, the records are only the lines changed on top of this
+    /// commit, which must still be the latest in the language. Otherwise
+    /// they are every line.
+    pub parent_id: Option<Uuid>,
     pub record_list: Vec<Record>,
+    /// Rejects the commit if any line goes over its length limits.
+    #[serde(default)]
+    pub strict: bool,
+    /// Fills repetitions of the lines translated in other commits.
+    pub propagate: Option<Propagation>,
+    /// Changes lines locked by other editors.
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitResult {
+    /// Not set if the commit was rejected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    pub violation_list: Vec<Violation>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub propagated_list: Vec<PropagatedCommit>,
 }
 
+/// Takes either every line or the lines changed on top of the latest commit.
+/// Refuses to change lines locked by other editors, including the lines
+/// propagation fills, unless forced. Reports
+/// the lines going over the length limits of the project or their meta,
+/// committing them anyway unless `strict` is set. Repetitions filled by
+/// propagation are committed along with it, or not at all.
 async fn add(
     claim: Claim,
     State(repo): State<repo::Repo>,
-    Json(new_commit): Json<NewCommit>,
-) -> Result<Json<Uuid>, ServiceError> {
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    Json(mut new_commit): Json<NewCommit>,
+) -> Result<(StatusCode, Json<CommitResult>), ServiceError> {
     let user_id = claim.id;
 
+    let unit = repo.get_unit_by_id(new_commit.unit_id)?;
+    claim.require_member(&repo, unit.project_id)?;
+    let project = super::project::require_writable(&repo, unit.project_id)?;
+    let language = super::project::check_target_language(&project, &new_commit.language)?;
+
+    let (head_id, head_record_list) = match repo.get_unit_head(unit.id, language.clone()) {
+        Ok(head) => (
+            Some(head.commit_id),
+            repo.get_record_by_commit_id(head.commit_id)?,
+        ),
+        Err(repo::Error::NotFound) => (None, Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+    if new_commit.parent_id.is_some() && new_commit.parent_id != head_id {
+        return Err(repo::Error::Conflict.into());
+    }
+
+    let head_map = head_record_list
+        .iter()
+        .map(|t| (t.sq, t.content.as_str()))
+        .collect::<HashMap<_, _>>();
+    let mut changed_sq_list = new_commit
+        .record_list
+        .iter()
+        .filter(|t| head_map.get(&t.sq).copied().unwrap_or("") != t.content)
+        .map(|t| t.sq)
+        .collect::<Vec<_>>();
+    if new_commit.parent_id.is_none() {
+        changed_sq_list.extend(
+            head_record_list
+                .iter()
+                .filter(|t| !t.content.is_empty())
+                .filter(|t| {
+                    !new_commit
+                        .record_list
+                        .iter()
+                        .any(|record| record.sq == t.sq)
+                })
+                .map(|t| t.sq),
+        );
+    }
+    lock::check_change(
+        &repo,
+        &claim,
+        address,
+        &language,
+        &[(unit.id, changed_sq_list)],
+        new_commit.force,
+    )?;
+
+    // Only reviewers mark lines as reviewed. Other editors keep the marks of
+    // lines they leave as they were.
+    if new_commit.record_list.iter().any(|t| t.is_reviewed)
+        && claim.require_reviewer(&repo, unit.project_id).is_err()
+    {
+        let reviewed_map = head_record_list
+            .iter()
+            .filter(|t| t.is_reviewed)
+            .map(|t| (t.sq, t.content.as_str()))
+            .collect::<HashMap<_, _>>();
+        for record in new_commit.record_list.iter_mut() {
+            record.is_reviewed &= reviewed_map.get(&record.sq) == Some(&record.content.as_str());
+        }
+    }
+
+    let source_version = new_commit.source_version.unwrap_or(unit.source_version);
+    let project_limit = LengthLimit::from_settings(&project.settings);
+    let limit_map = repo
+        .get_source_by_unit_id_version(unit.id, source_version)?
+        .into_iter()
+        .map(|t| {
+            let line_limit = SourceMeta::parse(&t.meta).length_limit();
+            (t.sq, project_limit.merge(line_limit))
+        })
+        .collect::<HashMap<_, _>>();
+    let mut violation_list = Vec::new();
+    for record in new_commit.record_list.iter() {
+        if let Some(limit) = limit_map.get(&record.sq) {
+            violation_list.extend(limit.find_violation(record.sq, &record.content));
+        }
+    }
+    if new_commit.strict && !violation_list.is_empty() {
+        return Ok((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(CommitResult {
+                id: None,
+                violation_list,
+                propagated_list: Vec::new(),
+            }),
+        ));
+    }
+
     let commit_id = Uuid::new_v4();
     let commit = repo::Commit {
         id: commit_id,
         unit_id: new_commit.unit_id,
         created_at: Utc::now().naive_utc(),
         editor_id: user_id,
+        language,
+        source_version,
+        propagated_from_id: None,
     };
-    let record_list = new_commit
+    // Lines sent again as they were still need retranslating if their source
+    // changed, only edited lines count as retranslated.
+    let retranslation_map = head_record_list
+        .iter()
+        .filter(|t| t.needs_retranslation)
+        .map(|t| (t.sq, t.content.as_str()))
+        .collect::<HashMap<_, _>>();
+    let mut record_list = new_commit
         .record_list
         .into_iter()
         .map(|t| repo::Record {
-            commit_id: commit_id,
+            commit_id,
             sq: t.sq,
+            needs_retranslation: retranslation_map.get(&t.sq) == Some(&t.content.as_str()),
             content: t.content,
+            is_reviewed: t.is_reviewed,
         })
         .collect::<Vec<_>>();
+    if new_commit.parent_id.is_some() {
+        let sq_set = record_list.iter().map(|t| t.sq).collect::<HashSet<_>>();
+        record_list.extend(
+            head_record_list
+                .iter()
+                .filter(|t| !sq_set.contains(&t.sq))
+                .map(|t| repo::Record {
+                    commit_id,
+                    ..t.clone()
+                }),
+        );
+        record_list.sort_by_key(|t| t.sq);
+    }
 
-    repo.add_commit(commit, record_list)?;
+    let Some(propagation) = new_commit.propagate else {
+        match new_commit.parent_id {
+            Some(parent_id) => repo.add_commit_list(vec![repo::ChildCommit {
+                parent_id: Some(parent_id),
+                commit,
+                record_list,
+            }])?,
+            None => repo.add_commit(commit, record_list)?,
+        }
 
-    Ok(Json(commit_id))
+        return Ok((
+            StatusCode::OK,
+            Json(CommitResult {
+                id: Some(commit_id),
+                violation_list,
+                propagated_list: Vec::new(),
+            }),
+        ));
+    };
+
+    let (propagated_commit_list, propagated_list) =
+        propagation.build_commit_list(&repo, &unit, &commit, &record_list, &head_record_list)?;
+    lock::check_change(
+        &repo,
+        &claim,
+        address,
+        &commit.language,
+        &propagated_list
+            .iter()
+            .map(|t| (t.unit_id, t.sq_list.clone()))
+            .collect::<Vec<_>>(),
+        new_commit.force,
+    )?;
+    let mut child_commit_list = vec![repo::ChildCommit {
+        parent_id: head_id,
+        commit,
+        record_list,
+    }];
+    child_commit_list.extend(propagated_commit_list);
+    repo.add_commit_list(child_commit_list)?;
+
+    if !propagated_list.is_empty() {
+        audit::record(
+            &repo,
+            "commit.propagate",
+            Some(user_id),
+            Some(address),
+            json!({
+                "commitId": commit_id,
+                "unitCount": propagated_list.len(),
+                "lineCount": propagated_list.iter().map(|t| t.sq_list.len()).sum::<usize>(),
+            }),
+        );
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(CommitResult {
+            id: Some(commit_id),
+            violation_list,
+            propagated_list,
+        }),
+    ))
 }
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRef, Json, Query, State};
+use axum::http::StatusCode;
+use axum::{routing, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::api::progress::{self, Progress, ProjectProgress};
+use crate::api::{audit, ServiceError};
+use crate::auth::{AuthRwLock, Claim};
+use crate::repo;
+
+pub fn build_router<S>() -> Router<S>
+where
+    S: Send + Sync + Clone + 'static,
+    AuthRwLock: FromRef<S>,
+    repo::Repo: FromRef<S>,
+{
+    Router::new()
+        .route("/", routing::get(get_list).post(add))
+        .route("/tree", routing::get(get_tree))
+        .route("/rename", routing::post(rename))
+        .route("/move", routing::post(move_to_folder))
+        .route("/delete", routing::post(delete))
+}
+
+/// Checks that a folder exists in the project.
+pub(super) fn require_folder_in(
+    repo: &repo::Repo,
+    folder_id: Uuid,
+    project_id: Uuid,
+) -> Result<repo::Folder, ServiceError> {
+    let folder = repo.get_folder_by_id(folder_id)?;
+    if folder.project_id != project_id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The folder belongs to another project",
+        )
+            .into());
+    }
+
+    Ok(folder)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ProjectIdQuery {
+    pub project_id: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Folder {
+    pub id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub name: String,
+    pub position: i32,
+}
+
+async fn get_list(
+    State(repo): State<repo::Repo>,
+    Query(query): Query<ProjectIdQuery>,
+) -> Result<Json<Vec<Folder>>, ServiceError> {
+    Ok(Json(
+        repo.get_folder_by_project_id(query.project_id)?
+            .into_iter()
+            .map(|t| Folder {
+                id: t.id,
+                parent_id: t.parent_id,
+                name: t.name,
+                position: t.position,
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UnitNode {
+    pub id: Uuid,
+    pub title: String,
+    pub position: i32,
+    pub progress: Progress,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FolderNode {
+    pub id: Uuid,
+    pub name: String,
+    pub position: i32,
+    pub progress: Progress,
+    pub folder_list: Vec<FolderNode>,
+    pub unit_list: Vec<UnitNode>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Tree {
+    pub progress: Progress,
+    /// The folders and units at the project root.
+    pub folder_list: Vec<FolderNode>,
+    pub unit_list: Vec<UnitNode>,
+}
+
+struct TreeBuilder {
+    folder_map: HashMap<Option<Uuid>, Vec<repo::Folder>>,
+    unit_map: HashMap<Option<Uuid>, Vec<repo::Unit>>,
+    progress: ProjectProgress,
+}
+
+impl TreeBuilder {
+    fn build_folder_list(&mut self, parent_id: Option<Uuid>) -> Vec<FolderNode> {
+        self.folder_map
+            .remove(&parent_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|t| FolderNode {
+                id: t.id,
+                name: t.name,
+                position: t.position,
+                progress: self.progress.folder_map.remove(&t.id).unwrap_or_default(),
+                folder_list: self.build_folder_list(Some(t.id)),
+                unit_list: self.build_unit_list(Some(t.id)),
+            })
+            .collect::<Vec<_>>()
+    }
+
+    fn build_unit_list(&mut self, folder_id: Option<Uuid>) -> Vec<UnitNode> {
+        self.unit_map
+            .remove(&folder_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|t| UnitNode {
+                id: t.id,
+                title: t.title,
+                position: t.position,
+                progress: self.progress.unit_map.remove(&t.id).unwrap_or_default(),
+            })
+            .collect::<Vec<_>>()
+    }
+}
+
+async fn get_tree(
+    State(repo): State<repo::Repo>,
+    Query(query): Query<ProjectIdQuery>,
+) -> Result<Json<Tree>, ServiceError> {
+    let folder_list = repo.get_folder_by_project_id(query.project_id)?;
+    let progress = progress::get_project_progress(&repo, query.project_id, &folder_list)?;
+
+    let mut builder = TreeBuilder {
+        folder_map: HashMap::new(),
+        unit_map: HashMap::new(),
+        progress,
+    };
+    for folder in folder_list {
+        builder
+            .folder_map
+            .entry(folder.parent_id)
+            .or_default()
+            .push(folder);
+    }
+    for unit in repo.get_unit_by_project_id(query.project_id)? {
+        builder
+            .unit_map
+            .entry(unit.folder_id)
+            .or_default()
+            .push(unit);
+    }
+
+    Ok(Json(Tree {
+        progress: builder.progress.total.clone(),
+        folder_list: builder.build_folder_list(None),
+        unit_list: builder.build_unit_list(None),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NewFolder {
+    pub project_id: Uuid,
+    /// The project root if not given.
+    pub parent_id: Option<Uuid>,
+    pub name: String,
+}
+
+async fn add(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(new_folder): Json<NewFolder>,
+) -> Result<Json<Uuid>, ServiceError> {
+    claim.require_member(&repo, new_folder.project_id)?;
+    super::project::require_writable(&repo, new_folder.project_id)?;
+    if let Some(parent_id) = new_folder.parent_id {
+        require_folder_in(&repo, parent_id, new_folder.project_id)?;
+    }
+
+    let folder_id = Uuid::new_v4();
+    repo.add_folder(repo::Folder {
+        id: folder_id,
+        project_id: new_folder.project_id,
+        parent_id: new_folder.parent_id,
+        name: new_folder.name.clone(),
+        position: 0,
+    })?;
+
+    audit::record(
+        &repo,
+        "folder.create",
+        Some(claim.id),
+        Some(address),
+        json!({
+            "folderId": folder_id,
+            "projectId": new_folder.project_id,
+            "parentId": new_folder.parent_id,
+            "name": new_folder.name,
+        }),
+    );
+
+    Ok(Json(folder_id))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RenameFolder {
+    pub id: Uuid,
+    pub name: String,
+}
+
+async fn rename(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<RenameFolder>,
+) -> Result<StatusCode, ServiceError> {
+    let folder = repo.get_folder_by_id(request.id)?;
+    claim.require_member(&repo, folder.project_id)?;
+    super::project::require_writable(&repo, folder.project_id)?;
+
+    repo.update_folder_name(folder.id, request.name.clone())?;
+
+    audit::record(
+        &repo,
+        "folder.rename",
+        Some(claim.id),
+        Some(address),
+        json!({ "folderId": folder.id, "name": request.name }),
+    );
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MoveFolder {
+    pub id: Uuid,
+    /// The project root if not given.
+    pub parent_id: Option<Uuid>,
+}
+
+async fn move_to_folder(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<MoveFolder>,
+) -> Result<StatusCode, ServiceError> {
+    let folder = repo.get_folder_by_id(request.id)?;
+    claim.require_member(&repo, folder.project_id)?;
+    super::project::require_writable(&repo, folder.project_id)?;
+    if let Some(parent_id) = request.parent_id {
+        require_folder_in(&repo, parent_id, folder.project_id)?;
+    }
+
+    repo.move_folder(folder.id, request.parent_id)
+        .map_err(|error| match error {
+            repo::Error::Conflict => (
+                StatusCode::CONFLICT,
+                "A folder cannot be moved into itself or its descendants",
+            )
+                .into(),
+            _ => ServiceError::from(error),
+        })?;
+
+    audit::record(
+        &repo,
+        "folder.move",
+        Some(claim.id),
+        Some(address),
+        json!({
+            "folderId": folder.id,
+            "fromParentId": folder.parent_id,
+            "toParentId": request.parent_id,
+        }),
+    );
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteFolder {
+    pub id: Uuid,
+}
+
+async fn delete(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<DeleteFolder>,
+) -> Result<StatusCode, ServiceError> {
+    let folder = repo.get_folder_by_id(request.id)?;
+    claim.require_owner(&repo, folder.project_id)?;
+    super::project::require_writable(&repo, folder.project_id)?;
+
+    repo.delete_folder(folder.id).map_err(|error| match error {
+        repo::Error::Conflict => (StatusCode::CONFLICT, "The folder is not empty").into(),
+        _ => ServiceError::from(error),
+    })?;
+
+    audit::record(
+        &repo,
+        "folder.delete",
+        Some(claim.id),
+        Some(address),
+        json!({
+            "folderId": folder.id,
+            "projectId": folder.project_id,
+            "name": folder.name,
+        }),
+    );
+
+    Ok(StatusCode::OK)
+}
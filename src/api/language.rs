@@ -0,0 +1,185 @@
+/// ISO 639-1 codes, the primary subtags accepted in two letter form.
+const ISO_639_1_LIST: &[&str] = &[
+    "aa", "ab", "ae", "af", "ak", "am", "an", "ar", "as", "av", "ay", "az", "ba", "be", "bg", "bi",
+    "bm", "bn", "bo", "br", "bs", "ca", "ce", "ch", "co", "cr", "cs", "cu", "cv", "cy", "da", "de",
+    "dv", "dz", "ee", "el", "en", "eo", "es", "et", "eu", "fa", "ff", "fi", "fj", "fo", "fr", "fy",
+    "ga", "gd", "gl", "gn", "gu", "gv", "ha", "he", "hi", "ho", "hr", "ht", "hu", "hy", "hz", "ia",
+    "id", "ie", "ig", "ii", "ik", "io", "is", "it", "iu", "ja", "jv", "ka", "kg", "ki", "kj", "kk",
+    "kl", "km", "kn", "ko", "kr", "ks", "ku", "kv", "kw", "ky", "la", "lb", "lg", "li", "ln", "lo",
+    "lt", "lu", "lv", "mg", "mh", "mi", "mk", "ml", "mn", "mr", "ms", "mt", "my", "na", "nb", "nd",
+    "ne", "ng", "nl", "nn", "no", "nr", "nv", "ny", "oc", "oj", "om", "or", "os", "pa", "pi", "pl",
+    "ps", "pt", "qu", "rm", "rn", "ro", "ru", "rw", "sa", "sc", "sd", "se", "sg", "si", "sk", "sl",
+    "sm", "sn", "so", "sq", "sr", "ss", "st", "su", "sv", "sw", "ta", "te", "tg", "th", "ti", "tk",
+    "tl", "tn", "to", "tr", "ts", "tt", "tw", "ty", "ug", "uk", "ur", "uz", "ve", "vi", "vo", "wa",
+    "wo", "xh", "yi", "yo", "za", "zh", "zu",
+];
+
+const MAX_TAG_LENGTH: usize = 35;
+
+fn is_alpha(s: &str, range: std::ops::RangeInclusive<usize>) -> bool {
+    range.contains(&s.len()) && s.bytes().all(|c| c.is_ascii_alphabetic())
+}
+
+fn is_alphanumeric(s: &str, range: std::ops::RangeInclusive<usize>) -> bool {
+    range.contains(&s.len()) && s.bytes().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn is_digit(s: &str, len: usize) -> bool {
+    s.len() == len && s.bytes().all(|c| c.is_ascii_digit())
+}
+
+/// Checks a BCP 47 language tag and returns it in canonical case, e.g.
+/// `zh-hant-tw` becomes `zh-Hant-TW`. Two letter primary subtags must be
+/// ISO 639-1 codes; grandfathered tags are not accepted.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    if tag.is_empty() || tag.len() > MAX_TAG_LENGTH {
+        return None;
+    }
+
+    let mut subtag_list = tag.split('-').map(str::to_ascii_lowercase).peekable();
+    let mut output = Vec::new();
+
+    let language = subtag_list.next()?;
+    match language.len() {
+        2 if ISO_639_1_LIST.contains(&language.as_str()) => {}
+        3 | 5..=8 if is_alpha(&language, 3..=8) => {}
+        // Private use only, e.g. `x-klingon`.
+        1 if language == "x" => {}
+        _ => return None,
+    }
+    let private_use = language == "x";
+    output.push(language);
+
+    if !private_use {
+        // Extended language subtags.
+        for _ in 0..3 {
+            match subtag_list.peek() {
+                Some(t) if is_alpha(t, 3..=3) && output[0].len() <= 3 => {
+                    output.push(subtag_list.next()?);
+                }
+                _ => break,
+            }
+        }
+
+        if let Some(script) = subtag_list.next_if(|t| is_alpha(t, 4..=4)) {
+            output.push(script[..1].to_ascii_uppercase() + &script[1..]);
+        }
+
+        if let Some(region) = subtag_list.next_if(|t| is_alpha(t, 2..=2) || is_digit(t, 3)) {
+            output.push(region.to_ascii_uppercase());
+        }
+
+        while let Some(variant) = subtag_list.next_if(|t| {
+            is_alphanumeric(t, 5..=8)
+                || (t.len() == 4 && t.as_bytes()[0].is_ascii_digit() && is_alphanumeric(t, 4..=4))
+        }) {
+            if output.contains(&variant) {
+                return None;
+            }
+            output.push(variant);
+        }
+
+        let mut singleton_list = Vec::new();
+        while let Some(singleton) = subtag_list.next_if(|t| t.len() == 1 && t != "x") {
+            if !is_alphanumeric(&singleton, 1..=1) || singleton_list.contains(&singleton) {
+                return None;
+            }
+            singleton_list.push(singleton.clone());
+            output.push(singleton);
+
+            let mut count = 0;
+            while let Some(extension) = subtag_list.next_if(|t| is_alphanumeric(t, 2..=8)) {
+                output.push(extension);
+                count += 1;
+            }
+            if count == 0 {
+                return None;
+            }
+        }
+
+        if let Some(x) = subtag_list.next_if(|t| t == "x") {
+            output.push(x);
+        } else if subtag_list.peek().is_some() {
+            return None;
+        }
+    }
+
+    // Private use subtags after `x`.
+    if subtag_list.peek().is_some() || output.last().is_some_and(|t| t == "x") {
+        let mut count = 0;
+        for private in subtag_list {
+            if !is_alphanumeric(&private, 1..=8) {
+                return None;
+            }
+            output.push(private);
+            count += 1;
+        }
+        if count == 0 {
+            return None;
+        }
+    }
+
+    Some(output.join("-"))
+}
+
+/// The canonical form of `tag` if it is valid, to match stored tags with.
+pub fn normalize_filter(tag: String) -> String {
+    normalize_tag(&tag).unwrap_or(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_tag_folds_case() {
+        for (tag, expected) in [
+            ("EN", "en"),
+            ("EN-us", "en-US"),
+            ("zh-hant-tw", "zh-Hant-TW"),
+            ("SR-LATN", "sr-Latn"),
+            ("es-419", "es-419"),
+            ("en-latn-gb-OXENDICT", "en-Latn-GB-oxendict"),
+            ("de-CH-1996", "de-CH-1996"),
+            ("zh-YUE-hk", "zh-yue-HK"),
+            ("ast", "ast"),
+            ("en-US-u-CA-gregory", "en-US-u-ca-gregory"),
+            ("en-x-Twain", "en-x-twain"),
+            ("X-Klingon", "x-klingon"),
+        ] {
+            assert_eq!(normalize_tag(tag).as_deref(), Some(expected), "{}", tag);
+        }
+    }
+
+    #[test]
+    fn normalize_tag_rejects_invalid_subtags() {
+        for tag in [
+            "",
+            "-",
+            "e",
+            "qq",
+            "en_US",
+            "en-",
+            "en--US",
+            "en-US-",
+            "englishlanguage",
+            "en-U$",
+            "de-1996-1996",
+            "en-a-bbb-a-ccc",
+            "en-a",
+            "en-x",
+            "x",
+            "i-klingon",
+            "en-US-x-waytoolongsubtag",
+            "en-abcdefghijklmnopqrstuvwxyzabcdefgh",
+        ] {
+            assert_eq!(normalize_tag(tag), None, "{}", tag);
+        }
+    }
+
+    #[test]
+    fn normalize_filter_keeps_invalid_tags() {
+        assert_eq!(normalize_filter(String::from("DE-de")), "de-DE");
+        assert_eq!(normalize_filter(String::from("en_US")), "en_US");
+    }
+}
@@ -0,0 +1,269 @@
+use axum::http::StatusCode;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api::ServiceError;
+
+/// The key of the project settings holding the limits of every line.
+pub const SETTINGS_KEY: &str = "lengthLimit";
+
+/// Limits on the translations of a line. A project sets them for every
+/// line, and the meta of a line overrides them key by key.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LengthLimit {
+    /// The most characters.
+    pub max_length: Option<u32>,
+    /// The most bytes once encoded in `encoding`.
+    pub max_byte_length: Option<u32>,
+    /// A WHATWG encoding label, UTF-8 if not given.
+    pub encoding: Option<String>,
+    /// The most lines, counted by line breaks.
+    pub max_line_count: Option<u32>,
+}
+
+impl LengthLimit {
+    /// Reads the limits of a project out of its settings, which are valid.
+    pub fn from_settings(settings: &Value) -> LengthLimit {
+        settings
+            .get(SETTINGS_KEY)
+            .and_then(|t| LengthLimit::deserialize(t).ok())
+            .unwrap_or_default()
+    }
+
+    /// Takes each limit of `other` set, keeping the rest.
+    pub fn merge(&self, other: LengthLimit) -> LengthLimit {
+        LengthLimit {
+            max_length: other.max_length.or(self.max_length),
+            max_byte_length: other.max_byte_length.or(self.max_byte_length),
+            encoding: other.encoding.or_else(|| self.encoding.clone()),
+            max_line_count: other.max_line_count.or(self.max_line_count),
+        }
+    }
+
+    /// Checks the encoding label.
+    pub fn check(&self) -> Result<(), ServiceError> {
+        match self.encoding.as_deref() {
+            Some(label) if find_encoding(label).is_none() => {
+                Err((StatusCode::BAD_REQUEST, "The encoding is unknown").into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Lists the limits a translation goes over.
+    pub fn find_violation(&self, sq: i32, content: &str) -> Vec<Violation> {
+        let mut violation_list = Vec::new();
+        let mut push = |kind, limit, actual: usize| {
+            if actual > limit as usize {
+                violation_list.push(Violation {
+                    sq,
+                    kind,
+                    limit: Some(limit),
+                    actual: Some(actual as u32),
+                });
+            }
+        };
+
+        if let Some(max_length) = self.max_length {
+            push(ViolationKind::Length, max_length, content.chars().count());
+        }
+        if let Some(max_line_count) = self.max_line_count {
+            push(
+                ViolationKind::LineCount,
+                max_line_count,
+                content.split('\n').count(),
+            );
+        }
+        if let Some(max_byte_length) = self.max_byte_length {
+            let encoding = self
+                .encoding
+                .as_deref()
+                .and_then(find_encoding)
+                .unwrap_or(encoding_rs::UTF_8);
+            match encoded_length(content, encoding) {
+                Some(length) => push(ViolationKind::ByteLength, max_byte_length, length),
+                None => violation_list.push(Violation {
+                    sq,
+                    kind: ViolationKind::Encoding,
+                    limit: None,
+                    actual: None,
+                }),
+            }
+        }
+
+        violation_list
+    }
+}
+
+fn find_encoding(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label_no_replacement(label.as_bytes())
+}
+
+/// The byte length of the content in the encoding, `None` if some character
+/// has no mapping into it.
+fn encoded_length(content: &str, encoding: &'static Encoding) -> Option<usize> {
+    // Encoders of encoding_rs only write UTF-16 as UTF-8.
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        return Some(content.encode_utf16().count() * 2);
+    }
+
+    let (bytes, _, had_errors) = encoding.encode(content);
+    (!had_errors).then_some(bytes.len())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ViolationKind {
+    Length,
+    ByteLength,
+    LineCount,
+    /// Some character cannot be written in the encoding.
+    Encoding,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Violation {
+    pub sq: i32,
+    pub kind: ViolationKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn kind_list(violation_list: &[Violation]) -> Vec<ViolationKind> {
+        violation_list.iter().map(|t| t.kind).collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn reads_limits_from_settings() {
+        let length_limit = LengthLimit::from_settings(&json!({
+            "lengthLimit": { "maxLength": 10, "encoding": "shift_jis" },
+            "theme": "dark",
+        }));
+        assert_eq!(length_limit.max_length, Some(10));
+        assert_eq!(length_limit.encoding.as_deref(), Some("shift_jis"));
+        assert_eq!(length_limit.max_line_count, None);
+
+        for settings in [json!({}), json!({ "lengthLimit": { "maxLength": "ten" } })] {
+            let length_limit = LengthLimit::from_settings(&settings);
+            assert_eq!(length_limit.max_length, None);
+            assert_eq!(length_limit.encoding, None);
+        }
+    }
+
+    #[test]
+    fn lets_the_meta_override_the_project() {
+        let project_limit = LengthLimit {
+            max_length: Some(10),
+            max_byte_length: Some(20),
+            encoding: Some(String::from("shift_jis")),
+            max_line_count: None,
+        };
+        let length_limit = project_limit.merge(LengthLimit {
+            max_length: Some(4),
+            encoding: Some(String::from("utf-16le")),
+            max_line_count: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(length_limit.max_length, Some(4));
+        assert_eq!(length_limit.max_byte_length, Some(20));
+        assert_eq!(length_limit.encoding.as_deref(), Some("utf-16le"));
+        assert_eq!(length_limit.max_line_count, Some(1));
+
+        let length_limit = project_limit.merge(LengthLimit::default());
+        assert_eq!(length_limit.max_length, Some(10));
+        assert_eq!(length_limit.encoding.as_deref(), Some("shift_jis"));
+    }
+
+    #[test]
+    fn checks_encoding_labels() {
+        for label in ["utf-8", "Shift_JIS", "sjis", "utf-16le", "windows-1252"] {
+            let length_limit = LengthLimit {
+                encoding: Some(String::from(label)),
+                ..Default::default()
+            };
+            assert!(length_limit.check().is_ok(), "{}", label);
+        }
+
+        // The replacement encoding is only a label for refusing input.
+        for label in ["klingon", "", "replacement", "iso-2022-kr"] {
+            let length_limit = LengthLimit {
+                encoding: Some(String::from(label)),
+                ..Default::default()
+            };
+            assert!(length_limit.check().is_err(), "{}", label);
+        }
+    }
+
+    #[test]
+    fn counts_encoded_bytes() {
+        let shift_jis = find_encoding("shift_jis").unwrap();
+        assert_eq!(encoded_length("abc", shift_jis), Some(3));
+        assert_eq!(encoded_length("こんにちは", shift_jis), Some(10));
+        assert_eq!(encoded_length("ｱｲｳ", shift_jis), Some(3));
+        assert_eq!(encoded_length("😀", shift_jis), None);
+
+        assert_eq!(encoded_length("こんにちは", encoding_rs::UTF_8), Some(15));
+        assert_eq!(encoded_length("é😀", encoding_rs::UTF_8), Some(6));
+        assert_eq!(encoded_length("é😀", UTF_16LE), Some(6));
+        assert_eq!(encoded_length("é😀", UTF_16BE), Some(6));
+    }
+
+    #[test]
+    fn finds_violations() {
+        let length_limit = LengthLimit {
+            max_length: Some(5),
+            max_byte_length: Some(8),
+            encoding: Some(String::from("shift_jis")),
+            max_line_count: Some(2),
+        };
+
+        // Characters are counted, not bytes.
+        assert_eq!(
+            kind_list(&length_limit.find_violation(1, "こんにちは")),
+            vec![ViolationKind::ByteLength]
+        );
+        assert!(length_limit.find_violation(1, "こんにち").is_empty());
+        assert!(length_limit.find_violation(1, "").is_empty());
+
+        let violation_list = length_limit.find_violation(7, "a\nb\nc");
+        assert_eq!(kind_list(&violation_list), vec![ViolationKind::LineCount]);
+        assert_eq!(violation_list[0].sq, 7);
+        assert_eq!(violation_list[0].limit, Some(2));
+        assert_eq!(violation_list[0].actual, Some(3));
+
+        assert_eq!(
+            kind_list(&length_limit.find_violation(1, "Hello, world")),
+            vec![ViolationKind::Length, ViolationKind::ByteLength]
+        );
+        let violation_list = length_limit.find_violation(1, "😀");
+        assert_eq!(kind_list(&violation_list), vec![ViolationKind::Encoding]);
+        assert_eq!(violation_list[0].limit, None);
+    }
+
+    #[test]
+    fn falls_back_to_utf_8() {
+        for encoding in [None, Some(String::from("klingon"))] {
+            let length_limit = LengthLimit {
+                max_byte_length: Some(6),
+                encoding,
+                ..Default::default()
+            };
+            assert!(length_limit.find_violation(1, "ええ").is_empty());
+            assert_eq!(
+                kind_list(&length_limit.find_violation(1, "えええ")),
+                vec![ViolationKind::ByteLength]
+            );
+        }
+    }
+}
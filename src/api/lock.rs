@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRef, Query, State};
+use axum::http::StatusCode;
+use axum::{routing, Json, Router};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::api::{audit, language, ServiceError};
+use crate::auth::{AuthRwLock, Claim};
+use crate::repo;
+
+/// Seconds a lock lasts without a heartbeat, unless asked otherwise.
+const DEFAULT_LOCK_TTL: i32 = 120;
+const MIN_LOCK_TTL: i32 = 10;
+const MAX_LOCK_TTL: i32 = 600;
+
+pub fn build_router<S>() -> Router<S>
+where
+    S: Send + Sync + Clone + 'static,
+    AuthRwLock: FromRef<S>,
+    repo::Repo: FromRef<S>,
+{
+    Router::new()
+        .route("/", routing::get(get_list).post(acquire_lock))
+        .route("/heartbeat", routing::post(heartbeat_lock))
+        .route("/release", routing::post(release_lock))
+        .route("/break", routing::post(break_lock))
+}
+
+fn expires_at(ttl: Option<i32>) -> NaiveDateTime {
+    let ttl = ttl
+        .unwrap_or(DEFAULT_LOCK_TTL)
+        .clamp(MIN_LOCK_TTL, MAX_LOCK_TTL);
+
+    Utc::now().naive_utc() + Duration::try_seconds(ttl as i64).unwrap_or_default()
+}
+
+fn locked_by(repo: &repo::Repo, line_lock: &repo::LineLock) -> ServiceError {
+    match repo.get_user_by_id(line_lock.holder_id) {
+        Ok(user) => (
+            StatusCode::CONFLICT,
+            format!("The lines are locked by {}", user.name).as_str(),
+        )
+            .into(),
+        Err(error) => error.into(),
+    }
+}
+
+/// Locks the lines from `first_sq` to `last_sq`, or the whole unit if they
+/// are not given. Locks of the same holder may overlap.
+pub fn acquire(
+    repo: &repo::Repo,
+    claim: &Claim,
+    unit_id: Uuid,
+    language: &str,
+    first_sq: Option<i32>,
+    last_sq: Option<i32>,
+    ttl: Option<i32>,
+) -> Result<repo::LineLock, ServiceError> {
+    let unit = repo.get_unit_by_id(unit_id)?;
+    claim.require_member(repo, unit.project_id)?;
+    let project = super::project::require_writable(repo, unit.project_id)?;
+    let language = super::project::check_target_language(&project, language)?;
+
+    match (first_sq, last_sq) {
+        (Some(first_sq), Some(last_sq)) if first_sq <= last_sq => {}
+        (None, None) => {}
+        _ => return Err((StatusCode::BAD_REQUEST, "The line range is invalid").into()),
+    }
+
+    let line_lock = repo::LineLock {
+        id: Uuid::new_v4(),
+        unit_id,
+        language: language.clone(),
+        first_sq,
+        last_sq,
+        holder_id: claim.id,
+        created_at: Utc::now().naive_utc(),
+        expires_at: expires_at(ttl),
+    };
+    match repo.add_line_lock(line_lock.clone()) {
+        Ok(()) => Ok(line_lock),
+        Err(repo::Error::Conflict) => Err(repo
+            .get_line_lock_by_unit_id(unit_id, Some(language))?
+            .iter()
+            .find(|t| t.holder_id != claim.id && t.overlaps(&line_lock))
+            .map_or_else(|| repo::Error::Conflict.into(), |t| locked_by(repo, t))),
+        Err(error) => Err(error.into()),
+    }
+}
+
+fn require_holder(
+    repo: &repo::Repo,
+    claim: &Claim,
+    id: Uuid,
+) -> Result<repo::LineLock, ServiceError> {
+    let line_lock = repo.get_line_lock_by_id(id)?;
+    if line_lock.holder_id != claim.id {
+        return Err((StatusCode::CONFLICT, "The lock is held by another editor").into());
+    }
+
+    Ok(line_lock)
+}
+
+/// Keeps a lock of the caller from expiring.
+pub fn heartbeat(
+    repo: &repo::Repo,
+    claim: &Claim,
+    id: Uuid,
+    ttl: Option<i32>,
+) -> Result<repo::LineLock, ServiceError> {
+    let mut line_lock = require_holder(repo, claim, id)?;
+    line_lock.expires_at = expires_at(ttl);
+    repo.update_line_lock_expires_at(id, line_lock.expires_at)?;
+
+    Ok(line_lock)
+}
+
+pub fn release(repo: &repo::Repo, claim: &Claim, id: Uuid) -> Result<(), ServiceError> {
+    require_holder(repo, claim, id)?;
+    repo.delete_line_lock(id)?;
+
+    Ok(())
+}
+
+/// Removes the lock of any holder. Admins only.
+pub fn force_release(
+    repo: &repo::Repo,
+    claim: &Claim,
+    address: Option<SocketAddr>,
+    id: Uuid,
+) -> Result<(), ServiceError> {
+    claim.require_admin()?;
+    let line_lock = repo.get_line_lock_by_id(id)?;
+    repo.delete_line_lock(id)?;
+
+    audit::record(
+        repo,
+        "lock.break",
+        Some(claim.id),
+        address,
+        json!({
+            "lockId": id,
+            "unitId": line_lock.unit_id,
+            "language": line_lock.language,
+            "holderId": line_lock.holder_id,
+        }),
+    );
+
+    Ok(())
+}
+
+/// Refuses changes to lines of other holders' locks, given as the lines
+/// changed in each unit, unless `force` is set. Overrides are recorded.
+pub fn check_change(
+    repo: &repo::Repo,
+    claim: &Claim,
+    address: SocketAddr,
+    language: &str,
+    change_list: &[(Uuid, Vec<i32>)],
+    force: bool,
+) -> Result<(), ServiceError> {
+    let unit_id_list = change_list.iter().map(|t| t.0).collect::<Vec<_>>();
+    let mut conflict_map: HashMap<Uuid, Vec<repo::LineLock>> = HashMap::new();
+    for line_lock in repo.get_line_lock_by_unit_id_list(&unit_id_list, String::from(language))? {
+        if line_lock.holder_id != claim.id
+            && change_list.iter().any(|(unit_id, sq_list)| {
+                *unit_id == line_lock.unit_id && sq_list.iter().any(|&sq| line_lock.covers(sq))
+            })
+        {
+            conflict_map
+                .entry(line_lock.unit_id)
+                .or_default()
+                .push(line_lock);
+        }
+    }
+
+    for (unit_id, _) in change_list {
+        let Some(conflict_list) = conflict_map.get(unit_id) else {
+            continue;
+        };
+        if !force {
+            return Err(locked_by(repo, &conflict_list[0]));
+        }
+
+        audit::record(
+            repo,
+            "lock.override",
+            Some(claim.id),
+            Some(address),
+            json!({
+                "unitId": unit_id,
+                "language": language,
+                "lockIdList": conflict_list.iter().map(|t| t.id).collect::<Vec<_>>(),
+            }),
+        );
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct LockQuery {
+    pub unit_id: Uuid,
+    /// Every language if not given.
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LineLock {
+    pub id: Uuid,
+    pub unit_id: Uuid,
+    pub language: String,
+    /// Not set on locks of the whole unit.
+    pub first_sq: Option<i32>,
+    pub last_sq: Option<i32>,
+    pub holder_id: Uuid,
+    pub holder_name: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl LineLock {
+    fn new(repo: &repo::Repo, line_lock: repo::LineLock) -> Result<Self, repo::Error> {
+        Ok(LineLock {
+            id: line_lock.id,
+            unit_id: line_lock.unit_id,
+            language: line_lock.language,
+            first_sq: line_lock.first_sq,
+            last_sq: line_lock.last_sq,
+            holder_id: line_lock.holder_id,
+            holder_name: repo.get_user_by_id(line_lock.holder_id)?.name,
+            created_at: line_lock.created_at.and_utc(),
+            expires_at: line_lock.expires_at.and_utc(),
+        })
+    }
+}
+
+/// The unexpired locks on a unit, showing who is editing it.
+async fn get_list(
+    State(repo): State<repo::Repo>,
+    Query(query): Query<LockQuery>,
+) -> Result<Json<Vec<LineLock>>, ServiceError> {
+    Ok(Json(
+        repo.get_line_lock_by_unit_id(
+            query.unit_id,
+            query.language.map(language::normalize_filter),
+        )?
+        .into_iter()
+        .map(|t| LineLock::new(&repo, t))
+        .collect::<Result<Vec<_>, _>>()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NewLock {
+    pub unit_id: Uuid,
+    pub language: String,
+    pub first_sq: Option<i32>,
+    pub last_sq: Option<i32>,
+    /// Seconds, two minutes if not given.
+    pub ttl: Option<i32>,
+}
+
+async fn acquire_lock(
+    State(repo): State<repo::Repo>,
+    claim: Claim,
+    Json(new_lock): Json<NewLock>,
+) -> Result<Json<LineLock>, ServiceError> {
+    let line_lock = acquire(
+        &repo,
+        &claim,
+        new_lock.unit_id,
+        &new_lock.language,
+        new_lock.first_sq,
+        new_lock.last_sq,
+        new_lock.ttl,
+    )?;
+
+    Ok(Json(LineLock::new(&repo, line_lock)?))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Heartbeat {
+    pub id: Uuid,
+    pub ttl: Option<i32>,
+}
+
+async fn heartbeat_lock(
+    State(repo): State<repo::Repo>,
+    claim: Claim,
+    Json(request): Json<Heartbeat>,
+) -> Result<Json<LineLock>, ServiceError> {
+    let line_lock = heartbeat(&repo, &claim, request.id, request.ttl)?;
+
+    Ok(Json(LineLock::new(&repo, line_lock)?))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LockId {
+    pub id: Uuid,
+}
+
+async fn release_lock(
+    State(repo): State<repo::Repo>,
+    claim: Claim,
+    Json(request): Json<LockId>,
+) -> Result<StatusCode, ServiceError> {
+    release(&repo, &claim, request.id)?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn break_lock(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<LockId>,
+) -> Result<StatusCode, ServiceError> {
+    force_release(&repo, &claim, Some(address), request.id)?;
+
+    Ok(StatusCode::OK)
+}
@@ -1,5 +1,18 @@
+pub mod audit;
 mod commit;
-mod project;
+mod folder;
+pub mod language;
+pub mod length_limit;
+pub mod lock;
+pub mod progress;
+pub mod project;
+mod propagation;
+mod replace;
+mod report;
+mod search;
+mod source_diff;
+pub mod source_meta;
+mod suggestion;
 mod unit;
 
 use axum::extract::FromRef;
@@ -8,18 +21,27 @@ use axum::response::{IntoResponse, Response};
 use axum::Router;
 
 use crate::auth::AuthRwLock;
+use crate::mt::MachineTranslation;
 use crate::repo;
 
 pub fn build_router<S>() -> Router<S>
 where
     S: Send + Sync + Clone + 'static,
     AuthRwLock: FromRef<S>,
+    MachineTranslation: FromRef<S>,
     repo::Repo: FromRef<S>,
 {
     Router::new()
         .nest("/project", project::build_router())
+        .nest("/folder", folder::build_router())
         .nest("/unit", unit::build_router())
         .nest("/commit", commit::build_router())
+        .nest("/lock", lock::build_router())
+        .nest("/replace", replace::build_router())
+        .nest("/report", report::build_router())
+        .nest("/search", search::build_router())
+        .nest("/suggestion", suggestion::build_router())
+        .nest("/admin/audit", audit::build_router())
 }
 
 #[derive(Debug)]
@@ -45,13 +67,14 @@ impl From<repo::Error> for ServiceError {
                 status_code: StatusCode::NOT_FOUND,
                 message: String::from("The requested resource could not be found"),
             },
-            NotUnique { .. } | ForeignKeyViolation { .. } | ConstraintViolation { .. } => {
-                ServiceError {
-                    status_code: StatusCode::CONFLICT,
-                    message: format!("The requested operation cannot be completeed: {}", error),
-                }
-            }
-            DataError { .. } => ServiceError {
+            NotUnique { .. }
+            | ForeignKeyViolation { .. }
+            | ConstraintViolation { .. }
+            | Conflict => ServiceError {
+                status_code: StatusCode::CONFLICT,
+                message: format!("The requested operation cannot be completeed: {}", error),
+            },
+            DataError => ServiceError {
                 status_code: StatusCode::BAD_REQUEST,
                 message: String::from("Cannot serialize or deserialize data"),
             },
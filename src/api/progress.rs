@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use juniper::GraphQLObject;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::repo;
+
+#[derive(Debug, Clone, Serialize, GraphQLObject)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageProgress {
+    pub language: String,
+    /// Lines with a translation not flagged for retranslation.
+    pub translated_count: i32,
+    /// Words of the source lines translated.
+    pub translated_word_count: i32,
+    /// Characters of the source lines translated.
+    pub translated_character_count: i32,
+    /// Translated lines marked as reviewed.
+    pub reviewed_count: i32,
+    pub last_activity_at: DateTime<Utc>,
+}
+
+/// Counts over the current sources of some units.
+#[derive(Debug, Default, Clone, Serialize, GraphQLObject)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    pub unit_count: i32,
+    pub line_count: i32,
+    pub word_count: i32,
+    pub character_count: i32,
+    /// The latest commit in any language.
+    pub last_activity_at: Option<DateTime<Utc>>,
+    /// Editors of any commit.
+    pub contributor_id_list: Vec<Uuid>,
+    pub language_list: Vec<LanguageProgress>,
+}
+
+impl Progress {
+    fn add(&mut self, other: &Progress) {
+        self.unit_count += other.unit_count;
+        self.line_count += other.line_count;
+        self.word_count += other.word_count;
+        self.character_count += other.character_count;
+        self.last_activity_at = self.last_activity_at.max(other.last_activity_at);
+
+        for contributor_id in other.contributor_id_list.iter() {
+            if let Err(index) = self.contributor_id_list.binary_search(contributor_id) {
+                self.contributor_id_list.insert(index, *contributor_id);
+            }
+        }
+
+        for language_progress in other.language_list.iter() {
+            match self
+                .language_list
+                .binary_search_by(|t| t.language.cmp(&language_progress.language))
+            {
+                Ok(index) => {
+                    let current = &mut self.language_list[index];
+                    current.translated_count += language_progress.translated_count;
+                    current.translated_word_count += language_progress.translated_word_count;
+                    current.translated_character_count +=
+                        language_progress.translated_character_count;
+                    current.reviewed_count += language_progress.reviewed_count;
+                    current.last_activity_at = current
+                        .last_activity_at
+                        .max(language_progress.last_activity_at);
+                }
+                Err(index) => self.language_list.insert(index, language_progress.clone()),
+            }
+        }
+    }
+}
+
+impl From<repo::UnitProgress> for Progress {
+    fn from(unit_progress: repo::UnitProgress) -> Self {
+        let mut language_list = unit_progress
+            .language_count_list
+            .into_iter()
+            .map(|t| LanguageProgress {
+                language: t.language,
+                translated_count: t.translated_count as i32,
+                translated_word_count: t.translated_word_count as i32,
+                translated_character_count: t.translated_character_count as i32,
+                reviewed_count: t.reviewed_count as i32,
+                last_activity_at: t.last_activity_at.and_utc(),
+            })
+            .collect::<Vec<_>>();
+        language_list.sort_by(|a, b| a.language.cmp(&b.language));
+
+        let mut contributor_id_list = unit_progress.contributor_id_list;
+        contributor_id_list.sort();
+
+        let source_count = unit_progress.source_count;
+        Progress {
+            unit_count: 1,
+            line_count: source_count.line_count as i32,
+            word_count: source_count.word_count as i32,
+            character_count: source_count.character_count as i32,
+            last_activity_at: language_list.iter().map(|t| t.last_activity_at).max(),
+            contributor_id_list,
+            language_list,
+        }
+    }
+}
+
+/// The progress of a project, with each folder counting the units below it
+/// at any depth.
+pub struct ProjectProgress {
+    pub total: Progress,
+    pub unit_map: HashMap<Uuid, Progress>,
+    pub folder_map: HashMap<Uuid, Progress>,
+}
+
+pub fn get_project_progress(
+    repo: &repo::Repo,
+    project_id: Uuid,
+    folder_list: &[repo::Folder],
+) -> Result<ProjectProgress, repo::Error> {
+    let parent_map = folder_list
+        .iter()
+        .map(|t| (t.id, t.parent_id))
+        .collect::<HashMap<_, _>>();
+
+    let mut project_progress = ProjectProgress {
+        total: Progress::default(),
+        unit_map: HashMap::new(),
+        folder_map: folder_list
+            .iter()
+            .map(|t| (t.id, Progress::default()))
+            .collect::<HashMap<_, _>>(),
+    };
+
+    for unit_progress in repo.get_unit_progress(Some(&[project_id]), None)? {
+        let unit_id = unit_progress.source_count.unit_id;
+        let mut folder_id = unit_progress.source_count.folder_id;
+        let progress = Progress::from(unit_progress);
+
+        while let Some(current_id) = folder_id {
+            if let Some(folder_progress) = project_progress.folder_map.get_mut(&current_id) {
+                folder_progress.add(&progress);
+            }
+            folder_id = parent_map.get(&current_id).copied().flatten();
+        }
+        project_progress.total.add(&progress);
+        project_progress.unit_map.insert(unit_id, progress);
+    }
+
+    Ok(project_progress)
+}
+
+/// The progress of each of the given projects with units.
+pub fn get_progress_by_project(
+    repo: &repo::Repo,
+    project_id_list: &[Uuid],
+) -> Result<HashMap<Uuid, Progress>, repo::Error> {
+    let mut progress_map: HashMap<Uuid, Progress> = HashMap::new();
+    for unit_progress in repo.get_unit_progress(Some(project_id_list), None)? {
+        progress_map
+            .entry(unit_progress.source_count.project_id)
+            .or_default()
+            .add(&Progress::from(unit_progress));
+    }
+
+    Ok(progress_map)
+}
+
+pub fn get_unit_progress(repo: &repo::Repo, unit_id: Uuid) -> Result<Progress, repo::Error> {
+    Ok(repo
+        .get_unit_progress(None, Some(unit_id))?
+        .into_iter()
+        .next()
+        .map(Progress::from)
+        .unwrap_or_default())
+}
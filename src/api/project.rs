@@ -1,9 +1,15 @@
-use axum::extract::{FromRef, Query, State};
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRef, Query, State};
+use axum::http::StatusCode;
 use axum::{routing, Json, Router};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::json;
 use uuid::Uuid;
 
-use crate::api::ServiceError;
+use crate::api::length_limit::{self, LengthLimit};
+use crate::api::progress::{self, Progress};
+use crate::api::{audit, language, source_meta, ServiceError};
 use crate::auth::{AuthRwLock, Claim};
 use crate::repo;
 
@@ -16,6 +22,98 @@ where
     Router::new()
         .route("/", routing::get(get_list).post(add))
         .route("/by-id", routing::get(get_by_id))
+        .route("/source", routing::get(get_source_list))
+        .route("/rename", routing::post(rename))
+        .route("/update", routing::post(update))
+        .route("/archive", routing::post(archive))
+        .route("/unarchive", routing::post(unarchive))
+        .route("/delete", routing::post(delete))
+}
+
+/// Archived projects are read-only.
+pub fn require_writable(
+    repo: &repo::Repo,
+    project_id: Uuid,
+) -> Result<repo::Project, ServiceError> {
+    let project = repo.get_project_by_id(project_id)?;
+    if project.is_archived {
+        return Err((StatusCode::CONFLICT, "The project is archived").into());
+    }
+
+    Ok(project)
+}
+
+/// Checks that a project is translated into `tag`, returning it in canonical
+/// form. Projects without configured target languages accept any valid tag.
+pub(super) fn check_target_language(
+    project: &repo::Project,
+    tag: &str,
+) -> Result<String, ServiceError> {
+    let tag = language::normalize_tag(tag)
+        .ok_or((StatusCode::BAD_REQUEST, "The language tag is invalid"))?;
+
+    if !project.target_language_list.is_empty() && !project.target_language_list.contains(&tag) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The language is not a target language of the project",
+        )
+            .into());
+    }
+
+    Ok(tag)
+}
+
+const MAX_QA_PROFILE_LENGTH: usize = 64;
+
+/// Validates metadata changes, bringing language tags into canonical form.
+pub fn check_changeset(
+    mut changeset: repo::ProjectChangeset,
+) -> Result<repo::ProjectChangeset, ServiceError> {
+    let invalid_tag = || (StatusCode::BAD_REQUEST, "The language tag is invalid");
+
+    if let Some(Some(source_language)) = &changeset.source_language {
+        changeset.source_language = Some(Some(
+            language::normalize_tag(source_language).ok_or_else(invalid_tag)?,
+        ));
+    }
+
+    if let Some(target_language_list) = changeset.target_language_list.take() {
+        let mut normalized_list: Vec<String> = Vec::new();
+        for tag in target_language_list {
+            let tag = language::normalize_tag(&tag).ok_or_else(invalid_tag)?;
+            if normalized_list.contains(&tag) {
+                return Err((StatusCode::BAD_REQUEST, "A target language is repeated").into());
+            }
+            normalized_list.push(tag);
+        }
+        changeset.target_language_list = Some(normalized_list);
+    }
+
+    if let Some(Some(qa_profile)) = &changeset.qa_profile {
+        if qa_profile.is_empty() || qa_profile.chars().count() > MAX_QA_PROFILE_LENGTH {
+            return Err((StatusCode::BAD_REQUEST, "The QA profile name is invalid").into());
+        }
+    }
+
+    if changeset.settings.as_ref().is_some_and(|t| !t.is_object()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The settings must be a JSON object",
+        )
+            .into());
+    }
+
+    if let Some(length_limit) = changeset
+        .settings
+        .as_ref()
+        .and_then(|t| t.get(length_limit::SETTINGS_KEY))
+    {
+        LengthLimit::deserialize(length_limit)
+            .map_err(|_| (StatusCode::BAD_REQUEST, "The length limit is invalid"))?
+            .check()?;
+    }
+
+    Ok(changeset)
 }
 
 #[derive(Debug, Serialize)]
@@ -23,17 +121,90 @@ where
 struct Project {
     pub id: Uuid,
     pub name: String,
+    pub is_archived: bool,
+    pub source_language: Option<String>,
+    pub target_language_list: Vec<String>,
+    pub description: String,
+    pub qa_profile: Option<String>,
+    pub settings: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<Progress>,
+}
+
+impl From<repo::Project> for Project {
+    fn from(project: repo::Project) -> Self {
+        Project {
+            id: project.id,
+            name: project.name,
+            is_archived: project.is_archived,
+            source_language: project.source_language,
+            target_language_list: project.target_language_list,
+            description: project.description,
+            qa_profile: project.qa_profile,
+            settings: project.settings,
+            progress: None,
+        }
+    }
+}
+
+// Tells an explicit `null` apart from a missing field.
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectMetadata {
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub source_language: Option<Option<String>>,
+    pub target_language_list: Option<Vec<String>>,
+    pub description: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub qa_profile: Option<Option<String>>,
+    pub settings: Option<serde_json::Value>,
+}
+
+impl From<ProjectMetadata> for repo::ProjectChangeset {
+    fn from(metadata: ProjectMetadata) -> Self {
+        repo::ProjectChangeset {
+            source_language: metadata.source_language,
+            target_language_list: metadata.target_language_list,
+            description: metadata.description,
+            qa_profile: metadata.qa_profile,
+            settings: metadata.settings,
+        }
+    }
 }
 
-async fn get_list(State(repo): State<repo::Repo>) -> Result<Json<Vec<Project>>, ServiceError> {
-    let project_list = repo.get_project()?;
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ListQuery {
+    pub include_archived: Option<bool>,
+}
+
+async fn get_list(
+    State(repo): State<repo::Repo>,
+    Query(query): Query<ListQuery>,
+) -> Result<Json<Vec<Project>>, ServiceError> {
+    let project_list = repo.get_project(query.include_archived.unwrap_or(false))?;
+    let mut progress_map = progress::get_progress_by_project(
+        &repo,
+        &project_list.iter().map(|t| t.id).collect::<Vec<_>>(),
+    )?;
 
     Ok(Json(
         project_list
             .into_iter()
-            .map(|t| Project {
-                id: t.id,
-                name: t.name,
+            .map(|t| {
+                let progress = progress_map.remove(&t.id).unwrap_or_default();
+                Project {
+                    progress: Some(progress),
+                    ..Project::from(t)
+                }
             })
             .collect::<Vec<_>>(),
     ))
@@ -50,31 +221,253 @@ async fn get_by_id(
     Query(query): Query<IdQuery>,
 ) -> Result<Json<Project>, ServiceError> {
     let project = repo.get_project_by_id(query.id)?;
+    let folder_list = repo.get_folder_by_project_id(project.id)?;
+    let progress = progress::get_project_progress(&repo, project.id, &folder_list)?.total;
 
     Ok(Json(Project {
-        id: project.id,
-        name: project.name,
+        progress: Some(progress),
+        ..Project::from(project)
     }))
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct SourceQuery {
+    pub id: Uuid,
+    pub speaker: Option<String>,
+    /// A JSON object the meta of the lines must contain.
+    pub meta: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Source {
+    pub unit_id: Uuid,
+    pub sq: i32,
+    pub content: String,
+    pub meta: serde_json::Value,
+}
+
+/// The current source lines of every unit, in unit order.
+async fn get_source_list(
+    State(repo): State<repo::Repo>,
+    Query(query): Query<SourceQuery>,
+) -> Result<Json<Vec<Source>>, ServiceError> {
+    let source_list = repo.get_source(repo::SourceFilter {
+        project_id: Some(query.id),
+        meta: source_meta::build_filter(query.meta.as_deref(), query.speaker)?,
+        ..Default::default()
+    })?;
+
+    Ok(Json(
+        source_list
+            .into_iter()
+            .map(|t| Source {
+                unit_id: t.unit_id,
+                sq: t.sq,
+                content: t.content,
+                meta: t.meta,
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct NewProject {
     pub name: String,
+    #[serde(flatten)]
+    pub metadata: ProjectMetadata,
 }
 
 async fn add(
     State(repo): State<repo::Repo>,
-    _claim: Claim,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
     Json(new_project): Json<NewProject>,
 ) -> Result<Json<Uuid>, ServiceError> {
+    let changeset = check_changeset(new_project.metadata.into())?;
+
     let project_id = Uuid::new_v4();
     let project = repo::Project {
         id: project_id,
-        name: new_project.name,
+        name: new_project.name.clone(),
+        is_archived: false,
+        source_language: changeset.source_language.flatten(),
+        target_language_list: changeset.target_language_list.unwrap_or_default(),
+        description: changeset.description.unwrap_or_default(),
+        qa_profile: changeset.qa_profile.flatten(),
+        settings: changeset.settings.unwrap_or_else(|| json!({})),
     };
 
-    repo.add_project(project)?;
+    repo.add_project(project, claim.id)?;
+
+    audit::record(
+        &repo,
+        "project.create",
+        Some(claim.id),
+        Some(address),
+        json!({ "projectId": project_id, "name": new_project.name }),
+    );
 
     Ok(Json(project_id))
 }
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RenameProject {
+    pub id: Uuid,
+    pub name: String,
+}
+
+async fn rename(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<RenameProject>,
+) -> Result<StatusCode, ServiceError> {
+    claim.require_owner(&repo, request.id)?;
+    require_writable(&repo, request.id)?;
+
+    repo.update_project_name(request.id, request.name.clone())?;
+
+    audit::record(
+        &repo,
+        "project.rename",
+        Some(claim.id),
+        Some(address),
+        json!({ "projectId": request.id, "name": request.name }),
+    );
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateProject {
+    pub id: Uuid,
+    #[serde(flatten)]
+    pub metadata: ProjectMetadata,
+}
+
+async fn update(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<UpdateProject>,
+) -> Result<Json<Project>, ServiceError> {
+    claim.require_owner(&repo, request.id)?;
+    require_writable(&repo, request.id)?;
+
+    let changeset = check_changeset(request.metadata.into())?;
+    let detail = changeset_detail(request.id, &changeset);
+
+    repo.update_project(request.id, changeset)
+        .map_err(|error| match error {
+            repo::Error::Conflict => (
+                StatusCode::CONFLICT,
+                "The project has translations in languages the list leaves out",
+            )
+                .into(),
+            _ => ServiceError::from(error),
+        })?;
+
+    audit::record(
+        &repo,
+        "project.update",
+        Some(claim.id),
+        Some(address),
+        detail,
+    );
+
+    Ok(Json(Project::from(repo.get_project_by_id(request.id)?)))
+}
+
+/// The names of the changed fields, for the audit log.
+pub fn changeset_detail(project_id: Uuid, changeset: &repo::ProjectChangeset) -> serde_json::Value {
+    let field_list = [
+        ("sourceLanguage", changeset.source_language.is_some()),
+        (
+            "targetLanguageList",
+            changeset.target_language_list.is_some(),
+        ),
+        ("description", changeset.description.is_some()),
+        ("qaProfile", changeset.qa_profile.is_some()),
+        ("settings", changeset.settings.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(name, changed)| changed.then_some(name))
+    .collect::<Vec<_>>();
+
+    json!({ "projectId": project_id, "fieldList": field_list })
+}
+
+async fn archive(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<IdQuery>,
+) -> Result<StatusCode, ServiceError> {
+    set_archived(&repo, address, &claim, request.id, true)
+}
+
+async fn unarchive(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<IdQuery>,
+) -> Result<StatusCode, ServiceError> {
+    set_archived(&repo, address, &claim, request.id, false)
+}
+
+fn set_archived(
+    repo: &repo::Repo,
+    address: SocketAddr,
+    claim: &Claim,
+    project_id: Uuid,
+    is_archived: bool,
+) -> Result<StatusCode, ServiceError> {
+    claim.require_owner(repo, project_id)?;
+
+    repo.update_project_is_archived(project_id, is_archived)?;
+
+    audit::record(
+        repo,
+        match is_archived {
+            true => "project.archive",
+            false => "project.unarchive",
+        },
+        Some(claim.id),
+        Some(address),
+        json!({ "projectId": project_id }),
+    );
+
+    Ok(StatusCode::OK)
+}
+
+async fn delete(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<IdQuery>,
+) -> Result<StatusCode, ServiceError> {
+    claim.require_owner(&repo, request.id)?;
+
+    let project = repo.get_project_by_id(request.id)?;
+    let deletion = repo.delete_project(project.id)?;
+
+    audit::record(
+        &repo,
+        "project.delete",
+        Some(claim.id),
+        Some(address),
+        json!({
+            "projectId": project.id,
+            "name": project.name,
+            "unitCount": deletion.unit_count,
+            "commitCount": deletion.commit_count,
+        }),
+    );
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::repo;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    #[default]
+    Unit,
+    Project,
+}
+
+/// Copies the lines a commit translates to untranslated lines with the same
+/// source content.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Propagation {
+    #[serde(default)]
+    pub scope: Scope,
+    /// Meta keys, such as `speaker` or `context`, whose values must be the
+    /// same on both lines.
+    #[serde(default)]
+    pub match_key_list: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PropagatedCommit {
+    pub unit_id: Uuid,
+    pub commit_id: Uuid,
+    /// The lines filled.
+    pub sq_list: Vec<i32>,
+}
+
+/// The lines of a unit to fill, with their translations.
+struct UnitFill<'a> {
+    unit_id: Uuid,
+    source_version: i32,
+    line_list: Vec<(i32, &'a str)>,
+}
+
+impl Propagation {
+    fn key_of(&self, source: &repo::Source) -> (String, Vec<Option<String>>) {
+        (
+            source.content.clone(),
+            self.match_key_list
+                .iter()
+                .map(|key| source.meta.get(key).map(Value::to_string))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Builds the commits filling repetitions of the lines `commit` changes
+    /// from `parent_record_list`, to follow it in the same transaction, along
+    /// with what they fill. Lines of the same source translated differently
+    /// in the commit are left out.
+    pub fn build_commit_list(
+        &self,
+        repo: &repo::Repo,
+        unit: &repo::Unit,
+        commit: &repo::Commit,
+        record_list: &[repo::Record],
+        parent_record_list: &[repo::Record],
+    ) -> Result<(Vec<repo::ChildCommit>, Vec<PropagatedCommit>), repo::Error> {
+        let parent_map = parent_record_list
+            .iter()
+            .map(|t| (t.sq, t.content.as_str()))
+            .collect::<HashMap<_, _>>();
+        let source_map = repo
+            .get_source_by_unit_id_version(unit.id, commit.source_version)?
+            .into_iter()
+            .map(|t| (t.sq, t))
+            .collect::<HashMap<_, _>>();
+
+        let mut translation_map = HashMap::new();
+        for record in record_list {
+            if record.content.is_empty()
+                || parent_map.get(&record.sq) == Some(&record.content.as_str())
+            {
+                continue;
+            }
+            let Some(source) = source_map.get(&record.sq) else {
+                continue;
+            };
+            translation_map
+                .entry(self.key_of(source))
+                .and_modify(|t: &mut Option<&str>| {
+                    if *t != Some(record.content.as_str()) {
+                        *t = None;
+                    }
+                })
+                .or_insert(Some(record.content.as_str()));
+        }
+        translation_map.retain(|_, t| t.is_some());
+        if translation_map.is_empty() {
+            return Ok((Vec::new(), Vec::new()));
+        }
+
+        // The commit itself is the base of its own unit, and the latest
+        // commits are those of the others.
+        let mut base_map: HashMap<Uuid, (Uuid, Vec<repo::Record>)> = HashMap::new();
+        let source_list = match self.scope {
+            Scope::Unit => repo.get_source(repo::SourceFilter {
+                unit_id: Some(unit.id),
+                ..Default::default()
+            })?,
+            Scope::Project => {
+                let head_list = repo
+                    .get_unit_head_by_project_id(unit.project_id)?
+                    .into_iter()
+                    .filter(|t| t.language == commit.language && t.unit_id != unit.id)
+                    .collect::<Vec<_>>();
+                let commit_map = head_list
+                    .iter()
+                    .map(|t| (t.commit_id, t.unit_id))
+                    .collect::<HashMap<_, _>>();
+                for head in head_list {
+                    base_map.insert(head.unit_id, (head.commit_id, Vec::new()));
+                }
+                for record in repo
+                    .get_record_by_commit_id_list(&commit_map.keys().copied().collect::<Vec<_>>())?
+                {
+                    if let Some(unit_id) = commit_map.get(&record.commit_id) {
+                        if let Some((_, base)) = base_map.get_mut(unit_id) {
+                            base.push(record);
+                        }
+                    }
+                }
+
+                repo.get_source(repo::SourceFilter {
+                    project_id: Some(unit.project_id),
+                    ..Default::default()
+                })?
+            }
+        };
+        base_map.insert(unit.id, (commit.id, record_list.to_vec()));
+
+        let translated_set = base_map
+            .iter()
+            .flat_map(|(unit_id, (_, base))| {
+                base.iter()
+                    .filter(|t| !t.content.is_empty())
+                    .map(move |t| (*unit_id, t.sq))
+            })
+            .collect::<HashSet<_>>();
+
+        let mut fill_list: Vec<UnitFill> = Vec::new();
+        for source in source_list.iter() {
+            if source.unit_id == unit.id && source.version != commit.source_version
+                || translated_set.contains(&(source.unit_id, source.sq))
+            {
+                continue;
+            }
+            let Some(Some(content)) = translation_map.get(&self.key_of(source)) else {
+                continue;
+            };
+            match fill_list.last_mut() {
+                Some(fill) if fill.unit_id == source.unit_id => {
+                    fill.line_list.push((source.sq, content))
+                }
+                _ => fill_list.push(UnitFill {
+                    unit_id: source.unit_id,
+                    source_version: source.version,
+                    line_list: vec![(source.sq, content)],
+                }),
+            }
+        }
+
+        let mut child_commit_list = Vec::new();
+        let mut propagated_list = Vec::new();
+        for UnitFill {
+            unit_id,
+            source_version,
+            line_list,
+        } in fill_list
+        {
+            let (parent_id, mut record_list) = base_map
+                .remove(&unit_id)
+                .map_or((None, Vec::new()), |(id, list)| (Some(id), list));
+            let commit_id = Uuid::new_v4();
+            record_list.retain(|t| !line_list.iter().any(|(sq, _)| *sq == t.sq));
+            for record in record_list.iter_mut() {
+                record.commit_id = commit_id;
+            }
+            record_list.extend(line_list.iter().map(|&(sq, content)| repo::Record {
+                commit_id,
+                sq,
+                content: String::from(content),
+                needs_retranslation: false,
+                is_reviewed: false,
+            }));
+
+            child_commit_list.push(repo::ChildCommit {
+                parent_id,
+                commit: repo::Commit {
+                    id: commit_id,
+                    unit_id,
+                    created_at: commit.created_at,
+                    editor_id: commit.editor_id,
+                    language: commit.language.clone(),
+                    source_version,
+                    propagated_from_id: Some(commit.id),
+                },
+                record_list,
+            });
+            propagated_list.push(PropagatedCommit {
+                unit_id,
+                commit_id,
+                sq_list: line_list.iter().map(|(sq, _)| *sq).collect::<Vec<_>>(),
+            });
+        }
+
+        Ok((child_commit_list, propagated_list))
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRef, State};
+use axum::http::StatusCode;
+use axum::{routing, Json, Router};
+use chrono::Utc;
+use regex::{NoExpand, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::api::{audit, lock, ServiceError};
+use crate::auth::{AuthRwLock, Claim};
+use crate::repo;
+
+pub fn build_router<S>() -> Router<S>
+where
+    S: Send + Sync + Clone + 'static,
+    AuthRwLock: FromRef<S>,
+    repo::Repo: FromRef<S>,
+{
+    Router::new().route("/", routing::post(replace))
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Mode {
+    #[default]
+    Literal,
+    /// The replacement may refer to groups as `$1` or `${name}`.
+    Regex,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Replacement {
+    pub project_id: Uuid,
+    pub language: String,
+    pub pattern: String,
+    pub replacement: String,
+    #[serde(default)]
+    pub mode: Mode,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Lists the changes without committing them.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Changes lines locked by other editors.
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Change {
+    pub unit_id: Uuid,
+    pub unit_title: String,
+    pub sq: i32,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UnitCommit {
+    pub unit_id: Uuid,
+    pub commit_id: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplacementResult {
+    pub change_list: Vec<Change>,
+    /// The commits made, empty on a dry run.
+    pub commit_list: Vec<UnitCommit>,
+}
+
+/// Replaces matches in the latest translations of a project, committing the
+/// changed units at once. Refuses to change lines locked by other editors
+/// unless forced.
+async fn replace(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<Replacement>,
+) -> Result<Json<ReplacementResult>, ServiceError> {
+    claim.require_member(&repo, request.project_id)?;
+    let project = super::project::require_writable(&repo, request.project_id)?;
+    let language = super::project::check_target_language(&project, &request.language)?;
+
+    if request.pattern.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "The pattern is empty").into());
+    }
+    let matcher = RegexBuilder::new(&match request.mode {
+        Mode::Literal => regex::escape(&request.pattern),
+        Mode::Regex => request.pattern.clone(),
+    })
+    .case_insensitive(!request.case_sensitive)
+    .build()
+    .map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            "The pattern is not a valid regular expression",
+        )
+    })?;
+
+    let unit_map = repo
+        .get_unit_by_project_id(project.id)?
+        .into_iter()
+        .map(|t| (t.id, t))
+        .collect::<HashMap<_, _>>();
+    let mut head_list = repo
+        .get_unit_head_by_project_id(project.id)?
+        .into_iter()
+        .filter(|t| t.language == language)
+        .collect::<Vec<_>>();
+    head_list.sort_by_key(|t| unit_map.get(&t.unit_id).map(|t| t.position));
+
+    let mut record_map: HashMap<Uuid, Vec<repo::Record>> = HashMap::new();
+    for record in repo
+        .get_record_by_commit_id_list(&head_list.iter().map(|t| t.commit_id).collect::<Vec<_>>())?
+    {
+        record_map.entry(record.commit_id).or_default().push(record);
+    }
+
+    let created_at = Utc::now().naive_utc();
+    let mut change_list = Vec::new();
+    let mut child_commit_list = Vec::new();
+    for head in head_list {
+        let Some(unit) = unit_map.get(&head.unit_id) else {
+            continue;
+        };
+        let mut record_list = record_map.remove(&head.commit_id).unwrap_or_default();
+        record_list.sort_by_key(|t| t.sq);
+
+        let commit_id = Uuid::new_v4();
+        let mut changed = false;
+        for record in record_list.iter_mut() {
+            record.commit_id = commit_id;
+
+            let content = match request.mode {
+                Mode::Literal => {
+                    matcher.replace_all(&record.content, NoExpand(&request.replacement))
+                }
+                Mode::Regex => matcher.replace_all(&record.content, request.replacement.as_str()),
+            };
+            if content == record.content {
+                continue;
+            }
+
+            let content = content.into_owned();
+            change_list.push(Change {
+                unit_id: unit.id,
+                unit_title: unit.title.clone(),
+                sq: record.sq,
+                before: std::mem::replace(&mut record.content, content.clone()),
+                after: content,
+            });
+            record.is_reviewed = false;
+            changed = true;
+        }
+
+        if changed {
+            child_commit_list.push(repo::ChildCommit {
+                parent_id: Some(head.commit_id),
+                commit: repo::Commit {
+                    id: commit_id,
+                    unit_id: unit.id,
+                    created_at,
+                    editor_id: claim.id,
+                    language: language.clone(),
+                    source_version: unit.source_version,
+                    propagated_from_id: None,
+                },
+                record_list,
+            });
+        }
+    }
+
+    let mut commit_list = Vec::new();
+    if !request.dry_run && !child_commit_list.is_empty() {
+        let mut lock_change_list: Vec<(Uuid, Vec<i32>)> = Vec::new();
+        for change in change_list.iter() {
+            match lock_change_list.last_mut() {
+                Some((unit_id, sq_list)) if *unit_id == change.unit_id => sq_list.push(change.sq),
+                _ => lock_change_list.push((change.unit_id, vec![change.sq])),
+            }
+        }
+        lock::check_change(
+            &repo,
+            &claim,
+            address,
+            &language,
+            &lock_change_list,
+            request.force,
+        )?;
+
+        commit_list = child_commit_list
+            .iter()
+            .map(|t| UnitCommit {
+                unit_id: t.commit.unit_id,
+                commit_id: t.commit.id,
+            })
+            .collect::<Vec<_>>();
+        repo.add_commit_list(child_commit_list)?;
+
+        audit::record(
+            &repo,
+            "commit.replace",
+            Some(claim.id),
+            Some(address),
+            json!({
+                "projectId": project.id,
+                "language": language,
+                "pattern": request.pattern,
+                "replacement": request.replacement,
+                "unitCount": commit_list.len(),
+                "lineCount": change_list.len(),
+            }),
+        );
+    }
+
+    Ok(Json(ReplacementResult {
+        change_list,
+        commit_list,
+    }))
+}
@@ -0,0 +1,229 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::extract::{FromRef, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::{routing, Json, Router};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::api::ServiceError;
+use crate::auth::{AuthRwLock, Claim};
+use crate::repo;
+
+pub fn build_router<S>() -> Router<S>
+where
+    S: Send + Sync + Clone + 'static,
+    AuthRwLock: FromRef<S>,
+    repo::Repo: FromRef<S>,
+{
+    Router::new().route("/contributor", routing::get(get_contributor_report))
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Format {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ContributorQuery {
+    pub project_id: Uuid,
+    pub editor_id: Option<Uuid>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub format: Format,
+}
+
+/// The work of one editor. Word counts are of the source lines, the way
+/// translation is usually paid for.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ContributorReport {
+    pub editor_id: Uuid,
+    pub editor_name: String,
+    pub commit_count: usize,
+    pub unit_count: usize,
+    /// Lines whose translation was added, edited or cleared.
+    pub changed_line_count: usize,
+    /// Lines translated where the parent commit had no translation.
+    pub new_line_count: usize,
+    /// Lines whose translation in the parent commit was replaced.
+    pub edited_line_count: usize,
+    pub new_word_count: usize,
+    pub edited_word_count: usize,
+}
+
+impl ContributorReport {
+    const CSV_HEADER: &'static str = "editor_id,editor_name,commit_count,unit_count,\
+        changed_line_count,new_line_count,edited_line_count,new_word_count,edited_word_count\r\n";
+
+    fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{}\r\n",
+            self.editor_id,
+            escape_csv(&self.editor_name),
+            self.commit_count,
+            self.unit_count,
+            self.changed_line_count,
+            self.new_line_count,
+            self.edited_line_count,
+            self.new_word_count,
+            self.edited_word_count,
+        )
+    }
+}
+
+fn escape_csv(field: &str) -> String {
+    match field.contains([',', '"', '\r', '\n']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => String::from(field),
+    }
+}
+
+/// Compares each commit in the range with the one before it on the same unit
+/// and language. Commits carrying translations over a source update or
+/// filling repetitions are not translation work and are left out.
+async fn get_contributor_report(
+    State(repo): State<repo::Repo>,
+    claim: Claim,
+    Query(query): Query<ContributorQuery>,
+) -> Result<Response, ServiceError> {
+    claim.require_owner(&repo, query.project_id)?;
+    if let (Some(since), Some(until)) = (query.since, query.until) {
+        if since >= until {
+            return Err((StatusCode::BAD_REQUEST, "The range is empty").into());
+        }
+    }
+
+    let commit_list = repo
+        .get_commit_with_parent(
+            query.project_id,
+            query.editor_id,
+            query.since.map(|t| t.naive_utc()),
+            query.until.map(|t| t.naive_utc()),
+        )?
+        .into_iter()
+        .filter(|t| {
+            t.commit.propagated_from_id.is_none()
+                && t.parent_source_version
+                    .is_none_or(|version| version == t.commit.source_version)
+        })
+        .collect::<Vec<_>>();
+
+    let mut commit_id_list = Vec::new();
+    let mut unit_id_list = Vec::new();
+    for commit in commit_list.iter() {
+        commit_id_list.push(commit.commit.id);
+        commit_id_list.extend(commit.parent_id);
+        unit_id_list.push(commit.commit.unit_id);
+    }
+    unit_id_list.sort();
+    unit_id_list.dedup();
+
+    let mut record_map: HashMap<Uuid, HashMap<i32, String>> = HashMap::new();
+    for record in repo.get_record_by_commit_id_list(&commit_id_list)? {
+        record_map
+            .entry(record.commit_id)
+            .or_default()
+            .insert(record.sq, record.content);
+    }
+    let mut word_count_map: HashMap<(Uuid, i32, i32), usize> = HashMap::new();
+    for source in repo.get_source_by_unit_id_list(&unit_id_list)? {
+        word_count_map.insert(
+            (source.unit_id, source.version, source.sq),
+            source.content.split_whitespace().count(),
+        );
+    }
+
+    let empty_map = HashMap::new();
+    let mut report_map: HashMap<Uuid, (ContributorReport, HashSet<Uuid>)> = HashMap::new();
+    for t in commit_list {
+        let commit = t.commit;
+        let (report, unit_id_set) = report_map.entry(commit.editor_id).or_default();
+        report.commit_count += 1;
+        unit_id_set.insert(commit.unit_id);
+
+        let record_map_of =
+            |id: Option<Uuid>| id.and_then(|id| record_map.get(&id)).unwrap_or(&empty_map);
+        let new_map = record_map_of(Some(commit.id));
+        let old_map = record_map_of(t.parent_id);
+
+        let mut sq_list = new_map.keys().chain(old_map.keys()).collect::<Vec<_>>();
+        sq_list.sort();
+        sq_list.dedup();
+        for &sq in sq_list {
+            let new_content = new_map.get(&sq).map_or("", |t| t.as_str());
+            let old_content = old_map.get(&sq).map_or("", |t| t.as_str());
+            if new_content == old_content {
+                continue;
+            }
+
+            report.changed_line_count += 1;
+            if new_content.is_empty() {
+                continue;
+            }
+
+            let word_count = word_count_map
+                .get(&(commit.unit_id, commit.source_version, sq))
+                .copied()
+                .unwrap_or(0);
+            if old_content.is_empty() {
+                report.new_line_count += 1;
+                report.new_word_count += word_count;
+            } else {
+                report.edited_line_count += 1;
+                report.edited_word_count += word_count;
+            }
+        }
+    }
+
+    let mut report_list = Vec::new();
+    for (editor_id, (mut report, unit_id_set)) in report_map {
+        report.editor_id = editor_id;
+        report.editor_name = repo.get_user_by_id(editor_id)?.name;
+        report.unit_count = unit_id_set.len();
+        report_list.push(report);
+    }
+    report_list.sort_by(|a, b| a.editor_name.cmp(&b.editor_name));
+
+    Ok(match query.format {
+        Format::Json => Json(report_list).into_response(),
+        Format::Csv => {
+            let mut body = String::from(ContributorReport::CSV_HEADER);
+            for report in report_list.iter() {
+                body += &report.to_csv_row();
+            }
+
+            (
+                [
+                    (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        "attachment; filename=\"contributor.csv\"",
+                    ),
+                ],
+                body,
+            )
+                .into_response()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_csv_fields() {
+        assert_eq!(escape_csv("alice"), "alice");
+        assert_eq!(escape_csv("smith, alice"), "\"smith, alice\"");
+        assert_eq!(escape_csv("the \"ace\""), "\"the \"\"ace\"\"\"");
+        assert_eq!(escape_csv("two\nlines"), "\"two\nlines\"");
+    }
+}
@@ -0,0 +1,276 @@
+use axum::extract::{FromRef, Query, State};
+use axum::http::StatusCode;
+use axum::{routing, Json, Router};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::api::{language, ServiceError};
+use crate::auth::{AuthRwLock, Claim};
+use crate::repo;
+
+const SEARCH_LIST_LIMIT: i64 = 100;
+const SNIPPET_CONTEXT_LENGTH: usize = 40;
+
+pub fn build_router<S>() -> Router<S>
+where
+    S: Send + Sync + Clone + 'static,
+    AuthRwLock: FromRef<S>,
+    repo::Repo: FromRef<S>,
+{
+    Router::new().route("/", routing::get(search))
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Mode {
+    #[default]
+    FullText,
+    Substring,
+    Regex,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct SearchQuery {
+    pub q: String,
+    #[serde(default)]
+    pub mode: Mode,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Every project accessible if not given.
+    pub project_id: Option<Uuid>,
+    /// Comma separated, out of `source`, `meta` and `translation`. All of
+    /// them if not given.
+    pub field_list: Option<String>,
+    pub language: Option<String>,
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SnippetPart {
+    pub text: String,
+    pub is_match: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchHit {
+    pub project_id: Uuid,
+    pub unit_id: Uuid,
+    pub unit_title: String,
+    pub sq: i32,
+    pub field: String,
+    /// Set on translations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// The content around the first match, cut at both ends if long.
+    pub snippet: Vec<SnippetPart>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchResult {
+    pub hit_list: Vec<SearchHit>,
+    pub has_more: bool,
+}
+
+/// Builds the expression that finds the matches to highlight. Full-text
+/// search matches whole words of the query, in any case.
+fn build_matcher(query: &SearchQuery) -> Result<Regex, regex::Error> {
+    let (pattern, case_insensitive) = match query.mode {
+        Mode::FullText => {
+            let word_list = query
+                .q
+                .split(|t: char| !t.is_alphanumeric())
+                .filter(|t| !t.is_empty() && !t.eq_ignore_ascii_case("or"))
+                .map(regex::escape)
+                .collect::<Vec<_>>();
+            (format!(r"\b(?:{})\b", word_list.join("|")), true)
+        }
+        Mode::Substring => (regex::escape(&query.q), !query.case_sensitive),
+        Mode::Regex => (query.q.clone(), !query.case_sensitive),
+    };
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(case_insensitive)
+        .build()
+}
+
+fn build_snippet(content: &str, matcher: &Regex) -> Vec<SnippetPart> {
+    let match_list = matcher
+        .find_iter(content)
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>();
+
+    // Keeps a number of characters on both sides of the first match.
+    let (first_start, first_end) = match_list.first().map_or((0, 0), |t| (t.start(), t.end()));
+    let start = content[..first_start]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_CONTEXT_LENGTH - 1)
+        .map_or(0, |(index, _)| index);
+    let end = content[first_end..]
+        .char_indices()
+        .nth(SNIPPET_CONTEXT_LENGTH)
+        .map_or(content.len(), |(index, _)| first_end + index);
+
+    let mut part_list = Vec::new();
+    let mut push = |text: &str, is_match: bool| {
+        if !text.is_empty() {
+            part_list.push(SnippetPart {
+                text: String::from(text),
+                is_match,
+            });
+        }
+    };
+
+    if start > 0 {
+        push("…", false);
+    }
+    let mut position = start;
+    for t in match_list.iter() {
+        if t.start() < start || t.end() > end {
+            continue;
+        }
+        push(&content[position..t.start()], false);
+        push(t.as_str(), true);
+        position = t.end();
+    }
+    push(&content[position..end], false);
+    if end < content.len() {
+        push("…", false);
+    }
+
+    part_list
+}
+
+async fn search(
+    State(repo): State<repo::Repo>,
+    claim: Claim,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchResult>, ServiceError> {
+    if query.q.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "The query is empty").into());
+    }
+    let matcher = build_matcher(&query).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            "The pattern is not a valid regular expression",
+        )
+    })?;
+
+    let (mut in_source, mut in_meta, mut in_translation) = (true, true, true);
+    if let Some(field_list) = query.field_list.as_deref() {
+        (in_source, in_meta, in_translation) = (false, false, false);
+        for field in field_list.split(',').map(str::trim) {
+            match field {
+                repo::SEARCH_FIELD_SOURCE => in_source = true,
+                repo::SEARCH_FIELD_META => in_meta = true,
+                repo::SEARCH_FIELD_TRANSLATION => in_translation = true,
+                _ => return Err((StatusCode::BAD_REQUEST, "The field is unknown").into()),
+            }
+        }
+    }
+
+    let project_id_list = match query.project_id {
+        Some(project_id) => {
+            claim.require_member(&repo, project_id)?;
+            Some(vec![project_id])
+        }
+        None if claim.is_admin => None,
+        None => Some(repo.get_project_id_by_member_id(claim.id)?),
+    };
+
+    let limit = query
+        .limit
+        .unwrap_or(SEARCH_LIST_LIMIT)
+        .clamp(0, SEARCH_LIST_LIMIT);
+    let mut hit_list = repo.search(
+        repo::SearchFilter {
+            project_id_list,
+            mode: match query.mode {
+                Mode::FullText => repo::SearchMode::FullText,
+                Mode::Substring => repo::SearchMode::Substring,
+                Mode::Regex => repo::SearchMode::Regex(matcher.clone()),
+            },
+            pattern: query.q.clone(),
+            case_sensitive: query.case_sensitive,
+            in_source,
+            in_meta,
+            in_translation,
+            language: query.language.map(language::normalize_filter),
+        },
+        query.offset.unwrap_or(0).max(0),
+        // One more tells whether there is a next page.
+        limit + 1,
+    )?;
+    let has_more = hit_list.len() as i64 > limit;
+    hit_list.truncate(limit as usize);
+
+    Ok(Json(SearchResult {
+        hit_list: hit_list
+            .into_iter()
+            .map(|t| SearchHit {
+                project_id: t.project_id,
+                unit_id: t.unit_id,
+                unit_title: t.unit_title,
+                sq: t.sq,
+                field: t.field,
+                language: t.language,
+                snippet: build_snippet(&t.content, &matcher),
+            })
+            .collect::<Vec<_>>(),
+        has_more,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(q: &str, mode: Mode) -> SearchQuery {
+        SearchQuery {
+            q: String::from(q),
+            mode,
+            case_sensitive: false,
+            project_id: None,
+            field_list: None,
+            language: None,
+            offset: None,
+            limit: None,
+        }
+    }
+
+    #[test]
+    fn matches_whole_words_in_full_text() {
+        let matcher = build_matcher(&query("dragon OR knight", Mode::FullText)).unwrap();
+        assert!(matcher.is_match("The Knight"));
+        assert!(!matcher.is_match("dragonfly"));
+        assert!(!matcher.is_match("or"));
+
+        let matcher = build_matcher(&query("a.b", Mode::Substring)).unwrap();
+        assert!(matcher.is_match("A.B"));
+        assert!(!matcher.is_match("axb"));
+        assert!(build_matcher(&query("(", Mode::Regex)).is_err());
+    }
+
+    #[test]
+    fn cuts_long_snippets_around_the_first_match() {
+        let matcher = build_matcher(&query("dragon", Mode::FullText)).unwrap();
+        let content = format!("{} dragon and dragon {}", "あ".repeat(50), "い".repeat(50));
+        let snippet = build_snippet(&content, &matcher);
+
+        // The context counts characters, not bytes.
+        let text_list = snippet.iter().map(|t| t.text.as_str()).collect::<Vec<_>>();
+        let before = format!("{} ", "あ".repeat(SNIPPET_CONTEXT_LENGTH - 1));
+        let after = format!(" {}", "い".repeat(SNIPPET_CONTEXT_LENGTH - 12));
+        assert_eq!(
+            text_list,
+            vec!["…", &before, "dragon", " and ", "dragon", &after, "…"]
+        );
+        assert_eq!(snippet.iter().filter(|t| t.is_match).count(), 2);
+    }
+}
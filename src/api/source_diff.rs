@@ -0,0 +1,414 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::repo;
+
+// Largest middle section, in cells, that is aligned with a full LCS table.
+const MAX_TABLE_SIZE: usize = 4_000_000;
+// Least bigram similarity for an edited line to be paired with its original.
+const MIN_SIMILARITY: f64 = 0.5;
+// Least similarity for a line that also moved.
+const MIN_MOVED_SIMILARITY: f64 = 0.7;
+// Most similarity computations spent on lines that also moved.
+const MAX_COMPARISON_COUNT: usize = 250_000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    Unchanged,
+    Moved,
+    Edited,
+}
+
+pub struct LineMatch {
+    pub old_sq: i32,
+    pub new_sq: i32,
+    pub kind: MatchKind,
+}
+
+/// Pairs the lines of two source lists, both ordered by `sq`. Lines kept in
+/// order are found as a longest common subsequence, identical lines out of
+/// order as moved, and similar lines as edited, preferring ones between the
+/// same two kept lines. Lines without a pair were removed or added.
+pub fn match_lines(old: &[repo::Source], new: &[repo::Source]) -> Vec<LineMatch> {
+    let (n, m) = (old.len(), new.len());
+    let mut old_pair: Vec<Option<(usize, MatchKind)>> = vec![None; n];
+    let mut new_paired = vec![false; m];
+
+    let mut anchor_list = Vec::new();
+
+    let mut prefix = 0;
+    while prefix < n.min(m) && old[prefix].content == new[prefix].content {
+        anchor_list.push((prefix, prefix));
+        prefix += 1;
+    }
+    let mut suffix = 0;
+    while suffix < n.min(m) - prefix && old[n - 1 - suffix].content == new[m - 1 - suffix].content {
+        suffix += 1;
+    }
+
+    anchor_list.extend(
+        common_subsequence(&old[prefix..n - suffix], &new[prefix..m - suffix])
+            .into_iter()
+            .map(|(i, j)| (prefix + i, prefix + j)),
+    );
+    anchor_list.extend((0..suffix).rev().map(|k| (n - 1 - k, m - 1 - k)));
+
+    for &(i, j) in &anchor_list {
+        old_pair[i] = Some((j, MatchKind::Unchanged));
+        new_paired[j] = true;
+    }
+
+    // Identical lines out of order.
+    let mut unpaired_map: HashMap<&str, VecDeque<usize>> = HashMap::new();
+    for (i, line) in old.iter().enumerate() {
+        if old_pair[i].is_none() {
+            unpaired_map.entry(&line.content).or_default().push_back(i);
+        }
+    }
+    for (j, line) in new.iter().enumerate() {
+        if new_paired[j] {
+            continue;
+        }
+        if let Some(i) = unpaired_map
+            .get_mut(line.content.as_str())
+            .and_then(VecDeque::pop_front)
+        {
+            old_pair[i] = Some((j, MatchKind::Moved));
+            new_paired[j] = true;
+        }
+    }
+
+    // Edited lines, looked for between consecutive kept lines only.
+    let mut bound_list = vec![(None, None)];
+    bound_list.extend(anchor_list.iter().map(|&(i, j)| (Some(i), Some(j))));
+    bound_list.push((Some(n), Some(m)));
+
+    for window in bound_list.windows(2) {
+        let (old_start, new_start) = (
+            window[0].0.map_or(0, |t| t + 1),
+            window[0].1.map_or(0, |t| t + 1),
+        );
+        let (old_end, new_end) = (window[1].0.unwrap_or(n), window[1].1.unwrap_or(m));
+
+        let old_candidate_list = (old_start..old_end)
+            .filter(|&i| old_pair[i].is_none())
+            .collect::<Vec<_>>();
+        let mut cursor = 0;
+
+        for j in new_start..new_end {
+            if new_paired[j] {
+                continue;
+            }
+            if let Some(k) = (cursor..old_candidate_list.len()).find(|&k| {
+                similarity(&old[old_candidate_list[k]].content, &new[j].content) >= MIN_SIMILARITY
+            }) {
+                old_pair[old_candidate_list[k]] = Some((j, MatchKind::Edited));
+                new_paired[j] = true;
+                cursor = k + 1;
+            }
+        }
+    }
+
+    // Lines both moved and edited, held to a stricter similarity.
+    let old_unpaired_list = (0..n)
+        .filter(|&i| old_pair[i].is_none())
+        .collect::<Vec<_>>();
+    let new_unpaired_list = (0..m).filter(|&j| !new_paired[j]).collect::<Vec<_>>();
+    if old_unpaired_list.len() * new_unpaired_list.len() <= MAX_COMPARISON_COUNT {
+        for j in new_unpaired_list {
+            let best = old_unpaired_list
+                .iter()
+                .filter(|&&i| old_pair[i].is_none())
+                .map(|&i| (i, similarity(&old[i].content, &new[j].content)))
+                .filter(|&(_, t)| t >= MIN_MOVED_SIMILARITY)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((i, _)) = best {
+                old_pair[i] = Some((j, MatchKind::Edited));
+                new_paired[j] = true;
+            }
+        }
+    }
+
+    old_pair
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, t)| {
+            t.map(|(j, kind)| LineMatch {
+                old_sq: old[i].sq,
+                new_sq: new[j].sq,
+                kind,
+            })
+        })
+        .collect::<Vec<_>>()
+}
+
+/// Index pairs of a longest common subsequence of the contents. Sections too
+/// large to align are left unaligned.
+fn common_subsequence(old: &[repo::Source], new: &[repo::Source]) -> Vec<(usize, usize)> {
+    let (n, m) = (old.len(), new.len());
+    if n == 0 || m == 0 || (n + 1) * (m + 1) > MAX_TABLE_SIZE {
+        return Vec::new();
+    }
+
+    // Length of the common subsequence of old[i..] and new[j..].
+    let mut table = vec![0u32; (n + 1) * (m + 1)];
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[at(i, j)] = match old[i].content == new[j].content {
+                true => table[at(i + 1, j + 1)] + 1,
+                false => table[at(i + 1, j)].max(table[at(i, j + 1)]),
+            };
+        }
+    }
+
+    let mut pair_list = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i].content == new[j].content {
+            pair_list.push((i, j));
+            i += 1;
+            j += 1;
+        } else if table[at(i + 1, j)] >= table[at(i, j + 1)] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    pair_list
+}
+
+/// Dice coefficient of the character bigrams, ignoring case and whitespace.
+fn similarity(a: &str, b: &str) -> f64 {
+    let normalize = |s: &str| {
+        s.chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect::<Vec<_>>()
+    };
+    let (a, b) = (normalize(a), normalize(b));
+    if a.len() < 2 || b.len() < 2 {
+        return if a == b { 1.0 } else { 0.0 };
+    }
+
+    let mut bigram_map: HashMap<(char, char), usize> = HashMap::new();
+    for t in a.windows(2) {
+        *bigram_map.entry((t[0], t[1])).or_default() += 1;
+    }
+    let mut common = 0;
+    for t in b.windows(2) {
+        if let Some(count) = bigram_map.get_mut(&(t[0], t[1])).filter(|t| **t > 0) {
+            *count -= 1;
+            common += 1;
+        }
+    }
+
+    2.0 * common as f64 / (a.len() + b.len() - 2) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use uuid::Uuid;
+
+    // Lines numbered in steps of ten, so that indices and numbers differ.
+    fn source_list(content_list: &[&str]) -> Vec<repo::Source> {
+        content_list
+            .iter()
+            .enumerate()
+            .map(|(i, t)| repo::Source {
+                unit_id: Uuid::nil(),
+                sq: 10 * (i as i32 + 1),
+                content: String::from(*t),
+                meta: serde_json::json!({}),
+                version: 1,
+            })
+            .collect::<Vec<_>>()
+    }
+
+    fn pair_list(old: &[&str], new: &[&str]) -> Vec<(i32, i32, &'static str)> {
+        let match_list = match_lines(&source_list(old), &source_list(new));
+
+        let mut old_sq_list = match_list.iter().map(|t| t.old_sq).collect::<Vec<_>>();
+        let mut new_sq_list = match_list.iter().map(|t| t.new_sq).collect::<Vec<_>>();
+        old_sq_list.sort();
+        old_sq_list.dedup();
+        new_sq_list.sort();
+        new_sq_list.dedup();
+        assert_eq!(
+            old_sq_list.len(),
+            match_list.len(),
+            "an old line paired twice"
+        );
+        assert_eq!(
+            new_sq_list.len(),
+            match_list.len(),
+            "a new line paired twice"
+        );
+
+        match_list
+            .into_iter()
+            .map(|t| {
+                let kind = match t.kind {
+                    MatchKind::Unchanged => "unchanged",
+                    MatchKind::Moved => "moved",
+                    MatchKind::Edited => "edited",
+                };
+                (t.old_sq, t.new_sq, kind)
+            })
+            .collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn matches_unchanged_lines() {
+        assert_eq!(
+            pair_list(&["A", "B", "C"], &["A", "B", "C"]),
+            vec![
+                (10, 10, "unchanged"),
+                (20, 20, "unchanged"),
+                (30, 30, "unchanged")
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_empty_lists() {
+        assert_eq!(pair_list(&[], &[]), vec![]);
+        assert_eq!(pair_list(&[], &["A", "B"]), vec![]);
+        assert_eq!(pair_list(&["A", "B"], &[]), vec![]);
+    }
+
+    #[test]
+    fn leaves_inserted_and_deleted_lines_unpaired() {
+        assert_eq!(
+            pair_list(&["A", "B", "C"], &["A", "New line", "B", "C"]),
+            vec![
+                (10, 10, "unchanged"),
+                (20, 30, "unchanged"),
+                (30, 40, "unchanged")
+            ]
+        );
+        assert_eq!(
+            pair_list(&["A", "Old line", "B", "C"], &["A", "B", "C"]),
+            vec![
+                (10, 10, "unchanged"),
+                (30, 20, "unchanged"),
+                (40, 30, "unchanged")
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_moved_lines() {
+        assert_eq!(
+            pair_list(&["A", "B", "C", "D"], &["B", "C", "D", "A"]),
+            vec![
+                (10, 40, "moved"),
+                (20, 10, "unchanged"),
+                (30, 20, "unchanged"),
+                (40, 30, "unchanged"),
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_edited_lines_between_kept_lines() {
+        assert_eq!(
+            pair_list(
+                &["Start", "The quick brown fox", "A lazy dog", "End"],
+                &["Start", "The quick brown foxes", "Something else", "End"],
+            ),
+            vec![
+                (10, 10, "unchanged"),
+                (20, 20, "edited"),
+                (40, 40, "unchanged")
+            ]
+        );
+
+        // Edits stay in order within the window.
+        assert_eq!(
+            pair_list(
+                &["Start", "First line here", "Second line here", "End"],
+                &["Start", "First line here!", "Second line here!", "End"],
+            ),
+            vec![
+                (10, 10, "unchanged"),
+                (20, 20, "edited"),
+                (30, 30, "edited"),
+                (40, 40, "unchanged"),
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_moved_and_edited_lines() {
+        assert_eq!(
+            pair_list(
+                &["Alpha line one here", "Beta", "Gamma", "Delta"],
+                &[
+                    "Beta",
+                    "Gamma",
+                    "Delta",
+                    "Alpha line one here!",
+                    "Alpha line one, here"
+                ],
+            ),
+            vec![
+                (10, 40, "edited"),
+                (20, 10, "unchanged"),
+                (30, 20, "unchanged"),
+                (40, 30, "unchanged"),
+            ]
+        );
+
+        // A looser match is not taken across kept lines.
+        assert_eq!(
+            pair_list(
+                &["The quick brown fox", "Beta"],
+                &["Beta", "The quick red fox"]
+            ),
+            vec![(20, 10, "unchanged")]
+        );
+    }
+
+    #[test]
+    fn pairs_duplicate_contents_once() {
+        assert_eq!(
+            pair_list(&["A", "A", "B"], &["A", "B", "A"]),
+            vec![
+                (10, 10, "unchanged"),
+                (20, 30, "moved"),
+                (30, 20, "unchanged")
+            ]
+        );
+        assert_eq!(
+            pair_list(&["A", "A"], &["A", "A", "A"]),
+            vec![(10, 10, "unchanged"), (20, 20, "unchanged")]
+        );
+    }
+
+    #[test]
+    fn finds_moved_lines_past_the_table_size() {
+        let old = (0..2001).map(|t| format!("Line {}", t)).collect::<Vec<_>>();
+        let mut new = old.clone();
+        new.rotate_left(1);
+        let old = old.iter().map(String::as_str).collect::<Vec<_>>();
+        let new = new.iter().map(String::as_str).collect::<Vec<_>>();
+
+        // Too large to align, so every line is found out of order instead.
+        let pair_list = pair_list(&old, &new);
+        assert_eq!(pair_list.len(), 2001);
+        assert!(pair_list.iter().all(|t| t.2 == "moved"));
+        assert_eq!(pair_list[0], (10, 20010, "moved"));
+    }
+
+    #[test]
+    fn similarity_ignores_case_and_whitespace() {
+        assert_eq!(similarity("Hello World", "hello  world"), 1.0);
+        assert_eq!(similarity("a", "a"), 1.0);
+        assert_eq!(similarity("a", "b"), 0.0);
+        assert_eq!(similarity("", ""), 1.0);
+        assert!(similarity("night", "nacht") < MIN_SIMILARITY);
+    }
+}
@@ -0,0 +1,153 @@
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::api::length_limit::LengthLimit;
+use crate::api::ServiceError;
+
+/// The meta of a source line, a JSON object. The keys below are understood
+/// by the server, and any other key is kept as it is for clients.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceMeta {
+    /// The character speaking the line.
+    pub speaker: Option<String>,
+    /// Where and how the line is used, as a note to translators.
+    pub context: Option<String>,
+    /// The most characters a translation may have.
+    pub max_length: Option<u32>,
+    /// The most bytes a translation may have in `encoding`.
+    pub max_byte_length: Option<u32>,
+    /// A WHATWG encoding label, UTF-8 if not given.
+    pub encoding: Option<String>,
+    /// The most lines a translation may have.
+    pub max_line_count: Option<u32>,
+    /// A path or URL of a screenshot showing the line.
+    pub screenshot: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl SourceMeta {
+    /// Reads stored meta, which is valid, leaving out anything that is not.
+    pub fn parse(meta: &Value) -> SourceMeta {
+        SourceMeta::deserialize(meta).unwrap_or_default()
+    }
+
+    pub fn length_limit(&self) -> LengthLimit {
+        LengthLimit {
+            max_length: self.max_length,
+            max_byte_length: self.max_byte_length,
+            encoding: self.encoding.clone(),
+            max_line_count: self.max_line_count,
+        }
+    }
+}
+
+/// Checks the well-known keys, turning a missing meta into an empty object.
+pub fn check(meta: Value) -> Result<Value, ServiceError> {
+    match meta {
+        Value::Null => Ok(Value::Object(Map::new())),
+        Value::Object(_) => match SourceMeta::deserialize(&meta) {
+            Ok(source_meta) => {
+                source_meta.length_limit().check()?;
+                Ok(meta)
+            }
+            Err(_) => Err((
+                StatusCode::BAD_REQUEST,
+                "A well-known key of the meta has the wrong type",
+            )
+                .into()),
+        },
+        _ => Err((StatusCode::BAD_REQUEST, "The meta must be a JSON object").into()),
+    }
+}
+
+/// Builds a meta filter out of a JSON object and a speaker, which adds to it.
+pub fn build_filter(
+    meta: Option<&str>,
+    speaker: Option<String>,
+) -> Result<Option<Value>, ServiceError> {
+    let mut filter = match meta {
+        Some(meta) => match serde_json::from_str::<Value>(meta) {
+            Ok(Value::Object(object)) => object,
+            _ => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "The meta filter must be a JSON object",
+                )
+                    .into())
+            }
+        },
+        None => Map::new(),
+    };
+    if let Some(speaker) = speaker {
+        filter.insert(String::from("speaker"), Value::String(speaker));
+    }
+
+    Ok((!filter.is_empty()).then_some(Value::Object(filter)))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn checks_well_known_keys() {
+        assert_eq!(check(Value::Null).unwrap(), json!({}));
+
+        let meta = json!({ "speaker": "Mira", "maxLength": 20, "voiceFile": "mira_01.ogg" });
+        assert_eq!(check(meta.clone()).unwrap(), meta);
+
+        for meta in [
+            json!({ "maxLength": "twenty" }),
+            json!({ "encoding": "klingon" }),
+            json!(["speaker"]),
+            json!("Mira"),
+        ] {
+            let error = check(meta.clone()).unwrap_err();
+            assert_eq!(error.status_code, StatusCode::BAD_REQUEST, "{}", meta);
+        }
+    }
+
+    #[test]
+    fn parses_stored_meta() {
+        let source_meta = SourceMeta::parse(&json!({
+            "speaker": "Mira",
+            "maxByteLength": 32,
+            "encoding": "shift_jis",
+            "voiceFile": "mira_01.ogg",
+        }));
+        assert_eq!(source_meta.speaker.as_deref(), Some("Mira"));
+        assert_eq!(source_meta.extra["voiceFile"], "mira_01.ogg");
+
+        let length_limit = source_meta.length_limit();
+        assert_eq!(length_limit.max_byte_length, Some(32));
+        assert_eq!(length_limit.encoding.as_deref(), Some("shift_jis"));
+        assert_eq!(length_limit.max_length, None);
+
+        assert!(SourceMeta::parse(&json!({ "speaker": 1 }))
+            .speaker
+            .is_none());
+    }
+
+    #[test]
+    fn builds_filters() {
+        assert_eq!(build_filter(None, None).unwrap(), None);
+        assert_eq!(
+            build_filter(Some(r#"{"context":"menu"}"#), Some(String::from("Mira"))).unwrap(),
+            Some(json!({ "context": "menu", "speaker": "Mira" }))
+        );
+        assert_eq!(
+            build_filter(None, Some(String::from("Mira"))).unwrap(),
+            Some(json!({ "speaker": "Mira" }))
+        );
+
+        for meta in ["[]", "menu", "{"] {
+            let error = build_filter(Some(meta), None).unwrap_err();
+            assert_eq!(error.status_code, StatusCode::BAD_REQUEST, "{}", meta);
+        }
+    }
+}
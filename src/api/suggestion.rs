@@ -0,0 +1,344 @@
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRef, Query, State};
+use axum::http::StatusCode;
+use axum::{routing, Json, Router};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::api::{audit, language, lock, ServiceError};
+use crate::auth::{AuthRwLock, Claim};
+use crate::mt::MachineTranslation;
+use crate::repo;
+
+pub fn build_router<S>() -> Router<S>
+where
+    S: Send + Sync + Clone + 'static,
+    AuthRwLock: FromRef<S>,
+    MachineTranslation: FromRef<S>,
+    repo::Repo: FromRef<S>,
+{
+    Router::new()
+        .route("/", routing::get(get_list))
+        .route("/fill", routing::post(fill))
+        .route("/accept", routing::post(accept))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct SuggestionQuery {
+    pub unit_id: Uuid,
+    /// Every language if not given.
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Suggestion {
+    pub sq: i32,
+    pub language: String,
+    pub content: String,
+    pub provider: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The suggestions for the current source list of a unit.
+async fn get_list(
+    State(repo): State<repo::Repo>,
+    Query(query): Query<SuggestionQuery>,
+) -> Result<Json<Vec<Suggestion>>, ServiceError> {
+    let suggestion_list = repo.get_suggestion_by_unit_id(
+        query.unit_id,
+        query.language.map(language::normalize_filter),
+    )?;
+
+    Ok(Json(
+        suggestion_list
+            .into_iter()
+            .map(|t| Suggestion {
+                sq: t.sq,
+                language: t.language,
+                content: t.content,
+                provider: t.provider,
+                created_at: t.created_at.and_utc(),
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// The lines of the latest commit in a language with a translation.
+fn get_translated_sq_set(
+    repo: &repo::Repo,
+    unit_id: Uuid,
+    language: &str,
+) -> Result<HashSet<i32>, repo::Error> {
+    match repo.get_unit_head(unit_id, String::from(language)) {
+        Ok(head) => Ok(repo
+            .get_record_by_commit_id(head.commit_id)?
+            .into_iter()
+            .filter(|t| !t.content.is_empty())
+            .map(|t| t.sq)
+            .collect::<HashSet<_>>()),
+        Err(repo::Error::NotFound) => Ok(HashSet::new()),
+        Err(error) => Err(error),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FillRequest {
+    /// Either a unit or a whole project is filled.
+    pub unit_id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    pub language: String,
+    /// Translates lines with a suggestion again.
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FillResult {
+    pub unit_count: usize,
+    pub line_count: usize,
+}
+
+/// Suggests translations for the untranslated lines of a unit or project.
+async fn fill(
+    State(repo): State<repo::Repo>,
+    State(mt): State<MachineTranslation>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<FillRequest>,
+) -> Result<Json<FillResult>, ServiceError> {
+    let Some(provider) = mt.0 else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Machine translation is not configured",
+        )
+            .into());
+    };
+
+    let (project_id, unit_list) = match (request.unit_id, request.project_id) {
+        (Some(unit_id), None) => {
+            let unit = repo.get_unit_by_id(unit_id)?;
+            (unit.project_id, vec![unit])
+        }
+        (None, Some(project_id)) => (project_id, repo.get_unit_by_project_id(project_id)?),
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Either a unit or a project is to be given",
+            )
+                .into())
+        }
+    };
+    claim.require_member(&repo, project_id)?;
+    let project = super::project::require_writable(&repo, project_id)?;
+    let language = super::project::check_target_language(&project, &request.language)?;
+
+    let mut result = FillResult {
+        unit_count: 0,
+        line_count: 0,
+    };
+    for unit in unit_list {
+        let translated_sq_set = get_translated_sq_set(&repo, unit.id, &language)?;
+        let suggested_sq_set = match request.overwrite {
+            true => HashSet::new(),
+            false => repo
+                .get_suggestion_by_unit_id(unit.id, Some(language.clone()))?
+                .into_iter()
+                .map(|t| t.sq)
+                .collect::<HashSet<_>>(),
+        };
+        let source_list = repo
+            .get_source_by_unit_id_version(unit.id, unit.source_version)?
+            .into_iter()
+            .filter(|t| {
+                !t.content.trim().is_empty()
+                    && !translated_sq_set.contains(&t.sq)
+                    && !suggested_sq_set.contains(&t.sq)
+            })
+            .collect::<Vec<_>>();
+        if source_list.is_empty() {
+            continue;
+        }
+
+        let translation_list = provider
+            .translate(
+                project.source_language.as_deref(),
+                &language,
+                &source_list
+                    .iter()
+                    .map(|t| t.content.clone())
+                    .collect::<Vec<_>>(),
+            )
+            .await?;
+
+        let created_at = Utc::now().naive_utc();
+        result.unit_count += 1;
+        result.line_count += source_list.len();
+        repo.add_suggestion_list(
+            source_list
+                .into_iter()
+                .zip(translation_list)
+                .map(|(source, content)| repo::Suggestion {
+                    unit_id: unit.id,
+                    source_version: unit.source_version,
+                    sq: source.sq,
+                    language: language.clone(),
+                    content,
+                    provider: String::from(provider.name()),
+                    created_at,
+                })
+                .collect::<Vec<_>>(),
+        )?;
+    }
+
+    audit::record(
+        &repo,
+        "suggestion.fill",
+        Some(claim.id),
+        Some(address),
+        json!({
+            "projectId": project.id,
+            "unitId": request.unit_id,
+            "language": language,
+            "provider": provider.name(),
+            "unitCount": result.unit_count,
+            "lineCount": result.line_count,
+        }),
+    );
+
+    Ok(Json(result))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AcceptRequest {
+    pub unit_id: Uuid,
+    pub language: String,
+    /// Every suggestion for an untranslated line if not given.
+    pub sq_list: Option<Vec<i32>>,
+    /// Changes lines locked by other editors.
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AcceptResult {
+    pub commit_id: Uuid,
+    pub sq_list: Vec<i32>,
+}
+
+/// Commits suggestions on top of the latest commit in the language. Lines
+/// listed explicitly take the suggestion over their current translation.
+/// Refuses to change lines locked by other editors unless forced.
+async fn accept(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<AcceptRequest>,
+) -> Result<Json<AcceptResult>, ServiceError> {
+    let unit = repo.get_unit_by_id(request.unit_id)?;
+    claim.require_member(&repo, unit.project_id)?;
+    let project = super::project::require_writable(&repo, unit.project_id)?;
+    let language = super::project::check_target_language(&project, &request.language)?;
+
+    let (parent_id, mut record_list) = match repo.get_unit_head(unit.id, language.clone()) {
+        Ok(head) => {
+            let commit = repo.get_commit_by_id(head.commit_id)?;
+            if commit.source_version != unit.source_version {
+                return Err(repo::Error::Conflict.into());
+            }
+            (
+                Some(head.commit_id),
+                repo.get_record_by_commit_id(head.commit_id)?,
+            )
+        }
+        Err(repo::Error::NotFound) => (None, Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+    let translated_sq_set = record_list
+        .iter()
+        .filter(|t| !t.content.is_empty())
+        .map(|t| t.sq)
+        .collect::<HashSet<_>>();
+
+    let suggestion_map = repo
+        .get_suggestion_by_unit_id(unit.id, Some(language.clone()))?
+        .into_iter()
+        .filter(|t| match &request.sq_list {
+            Some(sq_list) => sq_list.contains(&t.sq),
+            None => !translated_sq_set.contains(&t.sq),
+        })
+        .map(|t| (t.sq, t.content))
+        .collect::<HashMap<_, _>>();
+    if suggestion_map.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "There is no suggestion to accept").into());
+    }
+
+    let commit_id = Uuid::new_v4();
+    let mut sq_list = suggestion_map.keys().copied().collect::<Vec<_>>();
+    sq_list.sort();
+    lock::check_change(
+        &repo,
+        &claim,
+        address,
+        &language,
+        &[(unit.id, sq_list.clone())],
+        request.force,
+    )?;
+
+    record_list.retain(|t| !suggestion_map.contains_key(&t.sq));
+    for record in record_list.iter_mut() {
+        record.commit_id = commit_id;
+    }
+    record_list.extend(
+        suggestion_map
+            .into_iter()
+            .map(|(sq, content)| repo::Record {
+                commit_id,
+                sq,
+                content,
+                needs_retranslation: false,
+                is_reviewed: false,
+            }),
+    );
+
+    repo.accept_suggestion(
+        repo::ChildCommit {
+            parent_id,
+            commit: repo::Commit {
+                id: commit_id,
+                unit_id: unit.id,
+                created_at: Utc::now().naive_utc(),
+                editor_id: claim.id,
+                language: language.clone(),
+                source_version: unit.source_version,
+                propagated_from_id: None,
+            },
+            record_list,
+        },
+        &sq_list,
+    )?;
+
+    audit::record(
+        &repo,
+        "suggestion.accept",
+        Some(claim.id),
+        Some(address),
+        json!({
+            "unitId": unit.id,
+            "commitId": commit_id,
+            "language": language,
+            "lineCount": sq_list.len(),
+        }),
+    );
+
+    Ok(Json(AcceptResult { commit_id, sq_list }))
+}
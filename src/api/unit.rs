@@ -1,9 +1,17 @@
-use axum::extract::{FromRef, Json, Query, State};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRef, Json, Query, State};
+use axum::http::StatusCode;
 use axum::{routing, Router};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use uuid::Uuid;
 
-use crate::api::ServiceError;
+use crate::api::progress::{self, Progress};
+use crate::api::source_diff::{self, MatchKind};
+use crate::api::{audit, language, source_meta, ServiceError};
 use crate::auth::{AuthRwLock, Claim};
 use crate::repo;
 
@@ -16,13 +24,26 @@ where
     Router::new()
         .route("/", routing::get(get_list).post(add))
         .route("/by-id", routing::get(get_by_id))
-        .route("/source", routing::get(get_source_list))
+        .route("/source", routing::get(get_source_list).post(update_source))
+        .route("/rename", routing::post(rename))
+        .route("/reorder", routing::post(reorder))
+        .route("/move", routing::post(move_to_project))
+        .route("/folder", routing::post(move_to_folder))
+        .route("/delete", routing::post(delete))
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 struct ProjectIdQuery {
     pub project_id: Uuid,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Head {
+    pub language: String,
+    pub commit_id: Uuid,
 }
 
 #[derive(Debug, Serialize)]
@@ -30,8 +51,43 @@ struct ProjectIdQuery {
 struct Unit {
     pub id: Uuid,
     pub title: String,
+    pub source_version: i32,
+    pub position: i32,
+    pub folder_id: Option<Uuid>,
+    /// The latest commit in the requested language.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub commit_id: Option<Uuid>,
+    pub head_list: Vec<Head>,
+    pub progress: Progress,
+}
+
+impl Unit {
+    fn new(
+        unit: repo::Unit,
+        head_list: Vec<repo::UnitHead>,
+        progress: Progress,
+        language: Option<&str>,
+    ) -> Self {
+        Unit {
+            id: unit.id,
+            title: unit.title,
+            source_version: unit.source_version,
+            position: unit.position,
+            folder_id: unit.folder_id,
+            commit_id: head_list
+                .iter()
+                .find(|t| Some(t.language.as_str()) == language)
+                .map(|t| t.commit_id),
+            head_list: head_list
+                .into_iter()
+                .map(|t| Head {
+                    language: t.language,
+                    commit_id: t.commit_id,
+                })
+                .collect::<Vec<_>>(),
+            progress,
+        }
+    }
 }
 
 async fn get_list(
@@ -39,14 +95,22 @@ async fn get_list(
     Query(query): Query<ProjectIdQuery>,
 ) -> Result<Json<Vec<Unit>>, ServiceError> {
     let unit_list = repo.get_unit_by_project_id(query.project_id)?;
+    let language = query.language.map(language::normalize_filter);
+
+    let mut head_map: HashMap<Uuid, Vec<repo::UnitHead>> = HashMap::new();
+    for head in repo.get_unit_head_by_project_id(query.project_id)? {
+        head_map.entry(head.unit_id).or_default().push(head);
+    }
+
+    let mut progress_map = progress::get_project_progress(&repo, query.project_id, &[])?.unit_map;
 
     Ok(Json(
         unit_list
             .into_iter()
-            .map(|t| Unit {
-                id: t.id,
-                title: t.title,
-                commit_id: t.commit_id,
+            .map(|t| {
+                let head_list = head_map.remove(&t.id).unwrap_or_default();
+                let progress = progress_map.remove(&t.id).unwrap_or_default();
+                Unit::new(t, head_list, progress, language.as_deref())
             })
             .collect::<Vec<_>>(),
     ))
@@ -54,21 +118,26 @@ async fn get_list(
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
-struct IdQuery {
+struct UnitQuery {
     pub id: Uuid,
+    pub language: Option<String>,
 }
 
 async fn get_by_id(
     State(repo): State<repo::Repo>,
-    Query(query): Query<IdQuery>,
+    Query(query): Query<UnitQuery>,
 ) -> Result<Json<Unit>, ServiceError> {
     let unit = repo.get_unit_by_id(query.id)?;
+    let head_list = repo.get_unit_head_by_unit_id(unit.id)?;
+    let progress = progress::get_unit_progress(&repo, unit.id)?;
+    let language = query.language.map(language::normalize_filter);
 
-    Ok(Json(Unit {
-        id: unit.id,
-        title: unit.title,
-        commit_id: unit.commit_id,
-    }))
+    Ok(Json(Unit::new(
+        unit,
+        head_list,
+        progress,
+        language.as_deref(),
+    )))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -76,14 +145,32 @@ async fn get_by_id(
 struct Source {
     pub sq: i32,
     pub content: String,
-    pub meta: String,
+    /// An empty object if not given.
+    #[serde(default)]
+    pub meta: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct SourceQuery {
+    pub id: Uuid,
+    /// The current version if not given.
+    pub version: Option<i32>,
+    pub speaker: Option<String>,
+    /// A JSON object the meta of the lines must contain.
+    pub meta: Option<String>,
 }
 
 async fn get_source_list(
     State(repo): State<repo::Repo>,
-    Query(query): Query<IdQuery>,
+    Query(query): Query<SourceQuery>,
 ) -> Result<Json<Vec<Source>>, ServiceError> {
-    let source_list = repo.get_source_by_unit_id(query.id)?;
+    let source_list = repo.get_source(repo::SourceFilter {
+        unit_id: Some(query.id),
+        version: query.version,
+        meta: source_meta::build_filter(query.meta.as_deref(), query.speaker)?,
+        ..Default::default()
+    })?;
 
     Ok(Json(
         source_list
@@ -102,33 +189,358 @@ async fn get_source_list(
 struct NewUnit {
     pub project_id: Uuid,
     pub title: String,
+    /// The project root if not given.
+    pub folder_id: Option<Uuid>,
     pub source_list: Vec<Source>,
 }
 
 async fn add(
     State(repo): State<repo::Repo>,
-    _claim: Claim,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
     Json(new_unit): Json<NewUnit>,
 ) -> Result<Json<Uuid>, ServiceError> {
+    claim.require_member(&repo, new_unit.project_id)?;
+    super::project::require_writable(&repo, new_unit.project_id)?;
+    if let Some(folder_id) = new_unit.folder_id {
+        super::folder::require_folder_in(&repo, folder_id, new_unit.project_id)?;
+    }
+
     let unit_id = Uuid::new_v4();
     let unit = repo::Unit {
         id: unit_id,
         project_id: new_unit.project_id,
-        title: new_unit.title,
-        commit_id: None,
+        title: new_unit.title.clone(),
+        source_version: 1,
+        position: 0,
+        folder_id: new_unit.folder_id,
     };
     let source_list = new_unit
         .source_list
         .into_iter()
-        .map(|t| repo::Source {
-            unit_id: unit_id,
-            sq: t.sq,
-            content: t.content,
-            meta: t.meta,
+        .map(|t| {
+            Ok(repo::Source {
+                unit_id,
+                sq: t.sq,
+                content: t.content,
+                meta: source_meta::check(t.meta)?,
+                version: 1,
+            })
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, ServiceError>>()?;
 
+    let source_count = source_list.len();
     repo.add_unit(unit, source_list)?;
 
+    audit::record(
+        &repo,
+        "unit.create",
+        Some(claim.id),
+        Some(address),
+        json!({
+            "unitId": unit_id,
+            "projectId": new_unit.project_id,
+            "title": new_unit.title,
+            "sourceCount": source_count,
+        }),
+    );
+
     Ok(Json(unit_id))
 }
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SourceUpdate {
+    pub unit_id: Uuid,
+    /// The version the new list was edited from, the current one if not given.
+    pub source_version: Option<i32>,
+    pub source_list: Vec<Source>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SourceUpdateResult {
+    pub source_version: i32,
+    pub unchanged_count: usize,
+    pub moved_count: usize,
+    pub edited_count: usize,
+    pub added_count: usize,
+    pub removed_count: usize,
+    /// The commits carrying the translations over, one per language.
+    pub commit_list: Vec<Head>,
+}
+
+async fn update_source(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<SourceUpdate>,
+) -> Result<Json<SourceUpdateResult>, ServiceError> {
+    let unit = repo.get_unit_by_id(request.unit_id)?;
+    claim.require_owner(&repo, unit.project_id)?;
+    super::project::require_writable(&repo, unit.project_id)?;
+
+    let old_version = request.source_version.unwrap_or(unit.source_version);
+    if old_version != unit.source_version {
+        return Err(repo::Error::Conflict.into());
+    }
+
+    let old_list = repo.get_source_by_unit_id_version(unit.id, old_version)?;
+    let mut new_list = request
+        .source_list
+        .into_iter()
+        .map(|t| {
+            Ok(repo::Source {
+                unit_id: unit.id,
+                sq: t.sq,
+                content: t.content,
+                meta: source_meta::check(t.meta)?,
+                version: old_version + 1,
+            })
+        })
+        .collect::<Result<Vec<_>, ServiceError>>()?;
+    new_list.sort_by_key(|t| t.sq);
+    if new_list.windows(2).any(|t| t[0].sq == t[1].sq) {
+        return Err((StatusCode::BAD_REQUEST, "A line number is repeated").into());
+    }
+
+    let match_list = source_diff::match_lines(&old_list, &new_list);
+    let count = |kind| match_list.iter().filter(|t| t.kind == kind).count();
+    let (unchanged_count, moved_count, edited_count) = (
+        count(MatchKind::Unchanged),
+        count(MatchKind::Moved),
+        count(MatchKind::Edited),
+    );
+    let added_count = new_list.len() - match_list.len();
+    let removed_count = old_list.len() - match_list.len();
+
+    let remap_list = match_list
+        .into_iter()
+        .map(|t| repo::SqRemap {
+            old_sq: t.old_sq,
+            new_sq: t.new_sq,
+            changed: t.kind == MatchKind::Edited,
+        })
+        .collect::<Vec<_>>();
+
+    let commit_list = repo.update_source(
+        unit.id,
+        old_version,
+        new_list,
+        remap_list,
+        claim.id,
+        Utc::now().naive_utc(),
+    )?;
+
+    let result = SourceUpdateResult {
+        source_version: old_version + 1,
+        unchanged_count,
+        moved_count,
+        edited_count,
+        added_count,
+        removed_count,
+        commit_list: commit_list
+            .into_iter()
+            .map(|t| Head {
+                language: t.language,
+                commit_id: t.id,
+            })
+            .collect::<Vec<_>>(),
+    };
+
+    audit::record(
+        &repo,
+        "unit.update-source",
+        Some(claim.id),
+        Some(address),
+        json!({
+            "unitId": unit.id,
+            "sourceVersion": result.source_version,
+            "editedCount": result.edited_count,
+            "addedCount": result.added_count,
+            "removedCount": result.removed_count,
+        }),
+    );
+
+    Ok(Json(result))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RenameUnit {
+    pub id: Uuid,
+    pub title: String,
+}
+
+async fn rename(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<RenameUnit>,
+) -> Result<StatusCode, ServiceError> {
+    let unit = repo.get_unit_by_id(request.id)?;
+    claim.require_member(&repo, unit.project_id)?;
+    super::project::require_writable(&repo, unit.project_id)?;
+
+    repo.update_unit_title(unit.id, request.title.clone())?;
+
+    audit::record(
+        &repo,
+        "unit.rename",
+        Some(claim.id),
+        Some(address),
+        json!({ "unitId": unit.id, "title": request.title }),
+    );
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReorderUnit {
+    pub project_id: Uuid,
+    /// Every unit of the project, in the new order.
+    pub unit_id_list: Vec<Uuid>,
+}
+
+async fn reorder(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<ReorderUnit>,
+) -> Result<StatusCode, ServiceError> {
+    claim.require_member(&repo, request.project_id)?;
+    super::project::require_writable(&repo, request.project_id)?;
+
+    repo.reorder_unit(request.project_id, request.unit_id_list)
+        .map_err(|error| match error {
+            repo::Error::Conflict => (
+                StatusCode::CONFLICT,
+                "The list does not hold each unit of the project once",
+            )
+                .into(),
+            _ => ServiceError::from(error),
+        })?;
+
+    audit::record(
+        &repo,
+        "unit.reorder",
+        Some(claim.id),
+        Some(address),
+        json!({ "projectId": request.project_id }),
+    );
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MoveUnit {
+    pub id: Uuid,
+    pub project_id: Uuid,
+}
+
+/// Owners of both projects may move units between them.
+async fn move_to_project(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<MoveUnit>,
+) -> Result<StatusCode, ServiceError> {
+    let unit = repo.get_unit_by_id(request.id)?;
+    claim.require_owner(&repo, unit.project_id)?;
+    claim.require_owner(&repo, request.project_id)?;
+    super::project::require_writable(&repo, unit.project_id)?;
+    super::project::require_writable(&repo, request.project_id)?;
+
+    repo.move_unit(unit.id, request.project_id)
+        .map_err(|error| match error {
+            repo::Error::Conflict => (
+                StatusCode::CONFLICT,
+                "The unit has translations in languages the project does not list",
+            )
+                .into(),
+            _ => ServiceError::from(error),
+        })?;
+
+    audit::record(
+        &repo,
+        "unit.move",
+        Some(claim.id),
+        Some(address),
+        json!({
+            "unitId": unit.id,
+            "fromProjectId": unit.project_id,
+            "toProjectId": request.project_id,
+        }),
+    );
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MoveUnitToFolder {
+    pub id: Uuid,
+    /// The project root if not given.
+    pub folder_id: Option<Uuid>,
+}
+
+async fn move_to_folder(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<MoveUnitToFolder>,
+) -> Result<StatusCode, ServiceError> {
+    let unit = repo.get_unit_by_id(request.id)?;
+    claim.require_member(&repo, unit.project_id)?;
+    super::project::require_writable(&repo, unit.project_id)?;
+    if let Some(folder_id) = request.folder_id {
+        super::folder::require_folder_in(&repo, folder_id, unit.project_id)?;
+    }
+
+    repo.update_unit_folder_id(unit.id, request.folder_id)?;
+
+    audit::record(
+        &repo,
+        "unit.move-to-folder",
+        Some(claim.id),
+        Some(address),
+        json!({
+            "unitId": unit.id,
+            "fromFolderId": unit.folder_id,
+            "toFolderId": request.folder_id,
+        }),
+    );
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteUnit {
+    pub id: Uuid,
+}
+
+async fn delete(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<DeleteUnit>,
+) -> Result<StatusCode, ServiceError> {
+    let unit = repo.get_unit_by_id(request.id)?;
+    claim.require_owner(&repo, unit.project_id)?;
+    super::project::require_writable(&repo, unit.project_id)?;
+
+    repo.delete_unit(unit.id)?;
+
+    audit::record(
+        &repo,
+        "unit.delete",
+        Some(claim.id),
+        Some(address),
+        json!({ "unitId": unit.id, "projectId": unit.project_id, "title": unit.title }),
+    );
+
+    Ok(StatusCode::OK)
+}
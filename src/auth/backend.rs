@@ -0,0 +1,92 @@
+use std::env;
+use std::sync::Arc;
+
+use axum::async_trait;
+
+use crate::api::ServiceError;
+use crate::repo;
+
+use super::ldap::{LdapBackend, LDAP_BACKEND};
+use super::password;
+
+pub const LOCAL_BACKEND: &str = "local";
+
+/// A source of truth for user names and passwords. Each user row records the
+/// backend that owns it, and a backend must never accept users owned by
+/// another one.
+#[async_trait]
+pub trait Backend: Send + Sync {
+    /// Checks the credentials, returning the user they belong to, or `None`
+    /// if this backend does not accept them.
+    async fn authenticate(
+        &self,
+        repo: &repo::Repo,
+        name: &str,
+        pass: &str,
+    ) -> Result<Option<repo::User>, ServiceError>;
+}
+
+/// Checks passwords against the Argon2 hashes in the `user` table.
+pub struct LocalBackend;
+
+#[async_trait]
+impl Backend for LocalBackend {
+    async fn authenticate(
+        &self,
+        repo: &repo::Repo,
+        name: &str,
+        pass: &str,
+    ) -> Result<Option<repo::User>, ServiceError> {
+        let user = match repo.get_user_by_name(name.into()) {
+            Ok(user) if user.backend == LOCAL_BACKEND => user,
+            Ok(_) | Err(repo::Error::NotFound) => {
+                password::verify_dummy(pass);
+                return Ok(None);
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        if !password::verify(pass, &user.hash)? {
+            return Ok(None);
+        }
+
+        if password::needs_rehash(&user.hash) {
+            if let Err(error) = password::hash(pass).and_then(|hash| {
+                repo.update_user_hash(user.id, hash)
+                    .map_err(ServiceError::from)
+            }) {
+                tracing::warn!("Failed to upgrade password hash of {}: {}", user.id, error);
+            }
+        }
+
+        Ok(Some(user))
+    }
+}
+
+/// The configured backends, tried in order on sign-in.
+#[derive(Clone)]
+pub struct AuthBackends(pub Arc<Vec<Box<dyn Backend>>>);
+
+impl AuthBackends {
+    /// Reads the comma separated `AUTH_BACKENDS` list, which defaults to
+    /// `local`, or `local,ldap` when `LDAP_URL` is set.
+    pub fn from_env() -> Self {
+        let names = env::var("AUTH_BACKENDS").unwrap_or_else(|_| match env::var("LDAP_URL") {
+            Ok(_) => String::from("local,ldap"),
+            Err(_) => String::from(LOCAL_BACKEND),
+        });
+
+        let backends = names
+            .split(',')
+            .map(|name| -> Box<dyn Backend> {
+                match name.trim() {
+                    LOCAL_BACKEND => Box::new(LocalBackend),
+                    LDAP_BACKEND => Box::new(LdapBackend::from_env()),
+                    name => panic!("Unknown authentication backend {}", name),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        AuthBackends(Arc::new(backends))
+    }
+}
@@ -0,0 +1,167 @@
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRef, Json, State};
+use axum::http::StatusCode;
+use axum::{routing, Router};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::api::{audit, ServiceError};
+use crate::repo;
+
+use super::backend::LOCAL_BACKEND;
+use super::service::{hash_token, naive_from_timestamp};
+use super::{password, random_string, timestamp_now, AuthRwLock, Claim};
+
+const INVITATION_DURATION: u64 = 7 * 24 * 60 * 60;
+
+pub fn build_router<S>() -> Router<S>
+where
+    S: Send + Sync + Clone + 'static,
+    AuthRwLock: FromRef<S>,
+    repo::Repo: FromRef<S>,
+{
+    Router::new()
+        .route("/invite", routing::post(add_invitation))
+        .route("/accept-invite", routing::post(accept_invitation))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NewInvitation {
+    project_id: Option<Uuid>,
+    role: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Invitation {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// Admins may invite anyone to the server, project owners only to their
+/// projects.
+async fn add_invitation(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<NewInvitation>,
+) -> Result<Json<Invitation>, ServiceError> {
+    let role = match request.project_id {
+        Some(project_id) => {
+            claim.require_owner(&repo, project_id)?;
+            repo.get_project_by_id(project_id)?;
+
+            let role = request
+                .role
+                .unwrap_or_else(|| String::from(repo::ROLE_TRANSLATOR));
+            if !repo::ROLE_LIST.contains(&role.as_str()) {
+                return Err((StatusCode::BAD_REQUEST, "The role is unknown").into());
+            }
+            Some(role)
+        }
+        None => {
+            claim.require_admin()?;
+
+            if request.role.is_some() {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "A role can only be given together with a project",
+                )
+                    .into());
+            }
+            None
+        }
+    };
+
+    let token = random_string();
+    let hash = hash_token(&token);
+    let expires_at = naive_from_timestamp(timestamp_now() + INVITATION_DURATION);
+
+    repo.add_invitation(repo::Invitation {
+        hash: hash.clone(),
+        created_by: claim.id,
+        created_at: naive_from_timestamp(timestamp_now()),
+        expires_at,
+        project_id: request.project_id,
+        role: role.clone(),
+        used_by: None,
+    })?;
+
+    audit::record(
+        &repo,
+        "invitation.create",
+        Some(claim.id),
+        Some(address),
+        json!({ "invitation": hash, "projectId": request.project_id, "role": role }),
+    );
+
+    Ok(Json(Invitation {
+        token,
+        expires_at: expires_at.and_utc(),
+    }))
+}
+
+#[derive(Deserialize)]
+struct AcceptInvitationRequest {
+    token: String,
+    name: String,
+    pass: String,
+}
+
+async fn accept_invitation(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    Json(request): Json<AcceptInvitationRequest>,
+) -> Result<Json<Uuid>, ServiceError> {
+    password::check_policy(&request.pass)?;
+
+    let user_id = Uuid::new_v4();
+    let hash = hash_token(&request.token);
+
+    let invitation = repo
+        .accept_invitation(
+            hash.clone(),
+            repo::User {
+                id: user_id,
+                name: request.name.clone(),
+                hash: password::hash(&request.pass)?,
+                is_admin: false,
+                is_active: true,
+                totp_secret: None,
+                totp_enabled: false,
+                totp_last_step: None,
+                oidc_subject: None,
+                backend: String::from(LOCAL_BACKEND),
+            },
+        )
+        .map_err(|error| match error {
+            repo::Error::NotFound => (
+                StatusCode::UNAUTHORIZED,
+                "The invitation is invalid, expired or already used",
+            )
+                .into(),
+            _ => ServiceError::from(error),
+        })?;
+
+    audit::record(
+        &repo,
+        "user.create",
+        Some(user_id),
+        Some(address),
+        json!({
+            "userId": user_id,
+            "name": request.name,
+            "backend": LOCAL_BACKEND,
+            "invitation": hash,
+            "invitedBy": invitation.created_by,
+            "projectId": invitation.project_id,
+            "role": invitation.role,
+        }),
+    );
+
+    Ok(Json(user_id))
+}
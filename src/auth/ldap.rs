@@ -0,0 +1,162 @@
+use std::env;
+use std::time::Duration;
+
+use axum::async_trait;
+use axum::http::StatusCode;
+use ldap3::{dn_escape, ldap_escape, LdapConnAsync, LdapConnSettings, Scope};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::api::{audit, ServiceError};
+use crate::repo;
+
+use super::backend::Backend;
+use super::{password, random_string};
+
+pub const LDAP_BACKEND: &str = "ldap";
+
+const MAX_NAME_LENGTH: usize = 32;
+// Result code of a successful LDAP operation.
+const SUCCESS: u32 = 0;
+
+/// Authenticates users with a simple bind against an LDAP directory.
+/// Users are provisioned on their first successful bind.
+pub struct LdapBackend {
+    url: String,
+    // Bind DN template, with `{name}` replaced by the escaped user name.
+    user_dn: String,
+    /// Group whose members are administrators, if the directory decides.
+    pub admin_group_dn: Option<String>,
+    /// Filter matched against the admin group entry, with `{dn}` and `{name}`
+    /// replaced by the escaped bind DN and user name.
+    pub group_filter: String,
+}
+
+impl LdapBackend {
+    pub fn new(url: &str, user_dn: &str) -> Self {
+        LdapBackend {
+            url: url.into(),
+            user_dn: user_dn.into(),
+            admin_group_dn: None,
+            group_filter: String::from("(member={dn})"),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let mut backend = LdapBackend::new(
+            &env::var("LDAP_URL").expect("LDAP_URL"),
+            &env::var("LDAP_USER_DN").expect("LDAP_USER_DN"),
+        );
+        backend.admin_group_dn = env::var("LDAP_ADMIN_GROUP_DN").ok();
+        if let Ok(group_filter) = env::var("LDAP_GROUP_FILTER") {
+            backend.group_filter = group_filter;
+        }
+        backend
+    }
+}
+
+fn unavailable<T>(_: T) -> ServiceError {
+    (
+        StatusCode::BAD_GATEWAY,
+        "The directory server is unavailable",
+    )
+        .into()
+}
+
+#[async_trait]
+impl Backend for LdapBackend {
+    async fn authenticate(
+        &self,
+        repo: &repo::Repo,
+        name: &str,
+        pass: &str,
+    ) -> Result<Option<repo::User>, ServiceError> {
+        // An empty password would make an unauthenticated bind, which most
+        // servers report as successful.
+        if pass.is_empty() || name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Ok(None);
+        }
+
+        let existing = match repo.get_user_by_name(name.into()) {
+            Ok(user) if user.backend == LDAP_BACKEND => Some(user),
+            Ok(_) => return Ok(None),
+            Err(repo::Error::NotFound) => None,
+            Err(error) => return Err(error.into()),
+        };
+
+        let dn = self.user_dn.replace("{name}", &dn_escape(name));
+
+        let settings = LdapConnSettings::new().set_conn_timeout(Duration::from_secs(10));
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &self.url)
+            .await
+            .map_err(unavailable)?;
+        ldap3::drive!(conn);
+
+        let result = ldap.simple_bind(&dn, pass).await.map_err(unavailable)?;
+        if result.rc != SUCCESS {
+            let _ = ldap.unbind().await;
+            return Ok(None);
+        }
+
+        let is_admin = match &self.admin_group_dn {
+            Some(group_dn) => {
+                let filter = self
+                    .group_filter
+                    .replace("{dn}", &ldap_escape(&dn))
+                    .replace("{name}", &ldap_escape(name));
+                let search = ldap
+                    .search(group_dn, Scope::Base, &filter, vec!["1.1"])
+                    .await
+                    .and_then(|t| t.success());
+
+                Some(match search {
+                    Ok((entries, _)) => !entries.is_empty(),
+                    Err(error) => {
+                        tracing::warn!("Failed to look up admin group of {}: {}", name, error);
+                        false
+                    }
+                })
+            }
+            None => None,
+        };
+        let _ = ldap.unbind().await;
+
+        let user = match existing {
+            Some(mut user) => {
+                if let Some(is_admin) = is_admin.filter(|&t| t != user.is_admin) {
+                    repo.update_user_is_admin(user.id, is_admin)?;
+                    user.is_admin = is_admin;
+                }
+                user
+            }
+            None => {
+                let user = repo::User {
+                    id: Uuid::new_v4(),
+                    name: name.into(),
+                    // Never checked, the directory owns the password.
+                    hash: password::hash(&random_string())?,
+                    is_admin: is_admin.unwrap_or(false),
+                    is_active: true,
+                    totp_secret: None,
+                    totp_enabled: false,
+                    totp_last_step: None,
+                    oidc_subject: None,
+                    backend: String::from(LDAP_BACKEND),
+                };
+                repo.add_user(user.clone())?;
+
+                audit::record(
+                    repo,
+                    "user.create",
+                    Some(user.id),
+                    None,
+                    json!({ "userId": user.id, "name": user.name, "backend": LDAP_BACKEND }),
+                );
+
+                user
+            }
+        };
+
+        Ok(Some(user))
+    }
+}
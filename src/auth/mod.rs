@@ -1,4 +1,11 @@
+pub mod backend;
+mod invite;
+pub mod ldap;
+pub mod oidc;
+mod password;
 pub mod service;
+mod throttle;
+mod totp;
 
 use std::collections::VecDeque;
 use std::sync::{Arc, RwLock};
@@ -10,7 +17,10 @@ use axum::http::request::Parts;
 use axum::http::StatusCode;
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use axum_extra::extract::CookieJar;
-use base64::{engine::general_purpose::STANDARD, Engine as _};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine as _,
+};
 use hmac::{Mac, SimpleHmac};
 use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
@@ -19,6 +29,9 @@ use time::OffsetDateTime;
 use uuid::Uuid;
 
 use crate::api::ServiceError;
+use crate::repo;
+
+pub use throttle::ThrottleMutex;
 
 const TOKEN_DURATION: u64 = 2 * 24 * 60 * 60;
 
@@ -29,6 +42,13 @@ fn timestamp_now() -> u64 {
         .as_secs()
 }
 
+fn random_string() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
 pub struct Key {
     pub bytes: [u8; 32],
     pub expires: u64,
@@ -47,12 +67,14 @@ impl Key {
 
 struct Secret {
     pub keys: VecDeque<Key>,
+    pub require_admin_totp: bool,
 }
 
 impl Secret {
-    pub fn new() -> Self {
+    pub fn new(require_admin_totp: bool) -> Self {
         Secret {
             keys: VecDeque::new(),
+            require_admin_totp,
         }
     }
 
@@ -80,14 +102,17 @@ impl Secret {
 pub struct AuthRwLock(Arc<RwLock<Secret>>);
 
 impl AuthRwLock {
-    pub fn new() -> Self {
-        AuthRwLock(Arc::new(RwLock::new(Secret::new())))
+    /// With `require_admin_totp`, admins without an enabled second factor are
+    /// treated as regular users until they enroll.
+    pub fn new(require_admin_totp: bool) -> Self {
+        AuthRwLock(Arc::new(RwLock::new(Secret::new(require_admin_totp))))
     }
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct Claim {
     pub id: Uuid,
+    pub session_id: Uuid,
     pub expires: u64,
     pub is_admin: bool,
 }
@@ -105,71 +130,163 @@ where
     }
 }
 
-impl Claim {
-    fn from_token(s: &str, lock: Arc<RwLock<Secret>>) -> Result<Self, TokenError> {
-        let mut parts = s.split(".");
-
-        let claim_bytes = parts
-            .next()
-            .and_then(|t| STANDARD.decode(t).ok())
-            .ok_or(TokenError)?;
-        let sig_bytes = parts
-            .next()
-            .and_then(|t| STANDARD.decode(t).ok())
-            .ok_or(TokenError)?;
-
-        match parts.next() {
-            Some(_) => Err(TokenError),
-            None => Ok(()),
-        }?;
+fn verify_token(s: &str, lock: Arc<RwLock<Secret>>) -> Result<Vec<u8>, TokenError> {
+    let mut parts = s.split(".");
 
-        let current_timestamp = timestamp_now();
+    let claim_bytes = parts
+        .next()
+        .and_then(|t| STANDARD.decode(t).ok())
+        .ok_or(TokenError)?;
+    let sig_bytes = parts
+        .next()
+        .and_then(|t| STANDARD.decode(t).ok())
+        .ok_or(TokenError)?;
 
-        let mut valid = false;
-        {
-            let secret = lock.read().unwrap();
+    match parts.next() {
+        Some(_) => Err(TokenError),
+        None => Ok(()),
+    }?;
 
-            for key in secret.keys.iter().rev() {
-                if key.expires > current_timestamp {
-                    let mut mac = SimpleHmac::<Sha256>::new_from_slice(&key.bytes)?;
+    let current_timestamp = timestamp_now();
 
-                    mac.update(&claim_bytes);
-                    if mac.verify_slice(&sig_bytes).is_ok() {
-                        valid = true;
-                        break;
-                    }
-                }
+    let secret = lock.read().unwrap();
+
+    for key in secret.keys.iter().rev() {
+        if key.expires > current_timestamp {
+            let mut mac = SimpleHmac::<Sha256>::new_from_slice(&key.bytes)?;
+
+            mac.update(&claim_bytes);
+            if mac.verify_slice(&sig_bytes).is_ok() {
+                return Ok(claim_bytes);
             }
         }
-        if !valid {
+    }
+
+    Err(TokenError)
+}
+
+fn sign_token(claim_bytes: &[u8], lock: Arc<RwLock<Secret>>) -> Result<String, TokenError> {
+    let mut mac = {
+        let mut secret = lock.write().unwrap();
+        let key = secret.rotate();
+
+        SimpleHmac::<Sha256>::new_from_slice(&key.bytes).map_err(|_| TokenError)?
+    };
+
+    mac.update(claim_bytes);
+
+    let sig_bytes = mac.finalize().into_bytes();
+
+    let claim_str = STANDARD.encode(claim_bytes);
+    let sig_str = STANDARD.encode(sig_bytes);
+
+    Ok(format!("{}.{}", claim_str, sig_str))
+}
+
+impl Claim {
+    fn from_token(
+        s: &str,
+        lock: Arc<RwLock<Secret>>,
+        repo: &repo::Repo,
+    ) -> Result<Self, TokenError> {
+        let current_timestamp = timestamp_now();
+        let claim_bytes = verify_token(s, lock.clone())?;
+
+        let mut claim: Claim = serde_cbor::from_slice(&claim_bytes)?;
+        if claim.expires <= current_timestamp {
             return Err(TokenError);
         }
 
-        let claim: Claim = serde_cbor::from_slice(&claim_bytes)?;
-        if claim.expires <= current_timestamp {
+        let (session, user) = repo.get_session_user_by_id(claim.session_id)?;
+        if session.revoked || session.user_id != claim.id || !user.is_active {
             return Err(TokenError);
         }
 
+        // Privileges may have changed since the token was issued.
+        let require_admin_totp = lock.read().unwrap().require_admin_totp;
+        claim.is_admin = user.is_admin && (user.totp_enabled || !require_admin_totp);
+
         Ok(claim)
     }
 
-    fn to_token(&self, lock: Arc<RwLock<Secret>>) -> Result<String, TokenError> {
-        let mut mac = {
-            let mut secret = lock.write().unwrap();
-            let key = secret.rotate();
+    pub fn require_admin(&self) -> Result<(), ServiceError> {
+        if !self.is_admin {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                "You don't have the appropriate permission for the request",
+            )
+                .into());
+        }
 
-            SimpleHmac::<Sha256>::new_from_slice(&key.bytes).map_err(|_| TokenError)?
-        };
+        Ok(())
+    }
+
+    /// Admins and owners of the project pass.
+    pub fn require_owner(&self, repo: &repo::Repo, project_id: Uuid) -> Result<(), ServiceError> {
+        self.require_role(repo, project_id, &[repo::ROLE_OWNER])
+    }
+
+    /// Admins, owners and reviewers of the project pass.
+    pub fn require_reviewer(
+        &self,
+        repo: &repo::Repo,
+        project_id: Uuid,
+    ) -> Result<(), ServiceError> {
+        self.require_role(repo, project_id, &[repo::ROLE_OWNER, repo::ROLE_REVIEWER])
+    }
 
-        let claim_bytes = serde_cbor::to_vec(self)?;
-        mac.update(&claim_bytes);
+    /// Admins and members of the project in any role pass.
+    pub fn require_member(&self, repo: &repo::Repo, project_id: Uuid) -> Result<(), ServiceError> {
+        self.require_role(repo, project_id, &repo::ROLE_LIST)
+    }
 
-        let sig_bytes = mac.finalize().into_bytes();
+    fn require_role(
+        &self,
+        repo: &repo::Repo,
+        project_id: Uuid,
+        role_list: &[&str],
+    ) -> Result<(), ServiceError> {
+        if self.is_admin {
+            return Ok(());
+        }
 
-        let claim_str = STANDARD.encode(&claim_bytes);
-        let sig_str = STANDARD.encode(&sig_bytes);
+        match repo.get_project_member(project_id, self.id) {
+            Ok(member) if role_list.contains(&member.role.as_str()) => Ok(()),
+            Ok(_) | Err(repo::Error::NotFound) => Err((
+                StatusCode::UNAUTHORIZED,
+                "You don't have the appropriate permission for the request",
+            )
+                .into()),
+            Err(error) => Err(error.into()),
+        }
+    }
 
-        Ok(format!("{}.{}", claim_str, sig_str))
+    fn to_token(&self, lock: Arc<RwLock<Secret>>) -> Result<String, TokenError> {
+        sign_token(&serde_cbor::to_vec(self)?, lock)
+    }
+}
+
+/// Issued after a successful password check to users who still have to
+/// provide their second factor.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PreAuthClaim {
+    pub pre_auth_id: Uuid,
+    pub expires: u64,
+}
+
+impl PreAuthClaim {
+    fn from_token(s: &str, lock: Arc<RwLock<Secret>>) -> Result<Self, TokenError> {
+        let claim: PreAuthClaim = serde_cbor::from_slice(&verify_token(s, lock)?)?;
+        if claim.expires <= timestamp_now() {
+            return Err(TokenError);
+        }
+
+        Ok(claim)
+    }
+
+    fn to_token(&self, lock: Arc<RwLock<Secret>>) -> Result<String, TokenError> {
+        sign_token(&serde_cbor::to_vec(self)?, lock)
     }
 }
 
@@ -177,6 +294,7 @@ impl Claim {
 impl<S> FromRequestParts<S> for Claim
 where
     AuthRwLock: FromRef<S>,
+    repo::Repo: FromRef<S>,
     S: Send + Sync,
 {
     type Rejection = ServiceError;
@@ -184,11 +302,12 @@ where
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let cookie_jar = CookieJar::from_request_parts(parts, state).await.unwrap();
         let AuthRwLock(lock) = AuthRwLock::from_ref(state);
+        let repo = repo::Repo::from_ref(state);
         let cookie = cookie_jar
             .get("token")
             .ok_or((StatusCode::UNAUTHORIZED, "No token is set for the request"))?;
 
-        Ok(Claim::from_token(&cookie.value(), lock)
+        Ok(Claim::from_token(cookie.value(), lock, &repo)
             .map_err(|_| (StatusCode::UNAUTHORIZED, "The provided token is invalid"))?)
     }
 }
@@ -197,15 +316,14 @@ where
 impl<S> FromRequestParts<S> for OptionalClaim
 where
     AuthRwLock: FromRef<S>,
+    repo::Repo: FromRef<S>,
     S: Send + Sync,
 {
     type Rejection = ServiceError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         Ok(OptionalClaim(
-            Claim::from_request_parts(parts, state)
-                .await
-                .map_or(None, |claim| Some(claim)),
+            Claim::from_request_parts(parts, state).await.ok(),
         ))
     }
 }
@@ -220,9 +338,7 @@ fn make_token(lock: Arc<RwLock<Secret>>, claim: Claim) -> Result<CookieJar, Serv
         .secure(true)
         .http_only(true)
         .same_site(SameSite::Strict)
-        .expires(
-            OffsetDateTime::UNIX_EPOCH + Duration::from_secs(claim.expires.try_into().unwrap()),
-        )
+        .expires(OffsetDateTime::UNIX_EPOCH + Duration::from_secs(claim.expires))
         .into();
 
     Ok(CookieJar::new().add(cookie))
@@ -0,0 +1,563 @@
+use std::env;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{ConnectInfo, FromRef, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::{routing, Router};
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum_extra::extract::CookieJar;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::api::{audit, ServiceError};
+use crate::repo;
+
+use super::backend::LOCAL_BACKEND;
+use super::service::{make_pre_auth_token, start_session};
+use super::{
+    password, random_string, sign_token, timestamp_now, verify_token, AuthRwLock, OptionalClaim,
+};
+
+const STATE_COOKIE: &str = "oidc";
+const STATE_COOKIE_PATH: &str = "/api/auth/oidc";
+const STATE_DURATION: u64 = 10 * 60;
+const MAX_NAME_LENGTH: usize = 32;
+
+/// Identity provider settings.
+pub struct OidcConfig {
+    issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub groups_claim: String,
+    pub admin_group: Option<String>,
+    /// Users with TOTP enabled still give their code after the provider
+    /// signs them in, unless its own second factor is trusted instead.
+    pub skip_totp: bool,
+    client: reqwest::Client,
+}
+
+impl OidcConfig {
+    /// Settings with the defaults of the optional ones.
+    pub fn new(issuer: &str, client_id: &str, redirect_uri: &str) -> Self {
+        OidcConfig {
+            issuer: issuer.trim_end_matches('/').into(),
+            client_id: client_id.into(),
+            client_secret: None,
+            redirect_uri: redirect_uri.into(),
+            scope: String::from("openid profile"),
+            groups_claim: String::from("groups"),
+            admin_group: None,
+            skip_totp: false,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .expect("build HTTP client"),
+        }
+    }
+}
+
+/// `None` when single sign-on is not configured.
+#[derive(Clone)]
+pub struct Oidc(pub Option<Arc<OidcConfig>>);
+
+impl Oidc {
+    /// Reads the settings from `OIDC_*` environment variables.
+    /// `OIDC_SKIP_TOTP=true` lets users with TOTP enabled sign in through
+    /// the provider without their local second factor.
+    pub fn from_env() -> Self {
+        let (Ok(issuer), Ok(client_id), Ok(redirect_uri)) = (
+            env::var("OIDC_ISSUER"),
+            env::var("OIDC_CLIENT_ID"),
+            env::var("OIDC_REDIRECT_URI"),
+        ) else {
+            return Oidc(None);
+        };
+
+        let mut config = OidcConfig::new(&issuer, &client_id, &redirect_uri);
+        config.client_secret = env::var("OIDC_CLIENT_SECRET").ok();
+        if let Ok(scope) = env::var("OIDC_SCOPE") {
+            config.scope = scope;
+        }
+        if let Ok(groups_claim) = env::var("OIDC_GROUPS_CLAIM") {
+            config.groups_claim = groups_claim;
+        }
+        config.admin_group = env::var("OIDC_ADMIN_GROUP").ok();
+        config.skip_totp = env::var("OIDC_SKIP_TOTP").is_ok_and(|t| t == "true" || t == "1");
+
+        Oidc(Some(Arc::new(config)))
+    }
+}
+
+pub fn build_router<S>() -> Router<S>
+where
+    S: Send + Sync + Clone + 'static,
+    AuthRwLock: FromRef<S>,
+    Oidc: FromRef<S>,
+    repo::Repo: FromRef<S>,
+{
+    Router::new()
+        .route("/login", routing::get(login))
+        .route("/callback", routing::get(callback))
+}
+
+#[derive(Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: Option<String>,
+}
+
+async fn discover(config: &OidcConfig) -> Result<Discovery, ServiceError> {
+    let discovery: Discovery = config
+        .client
+        .get(format!(
+            "{}/.well-known/openid-configuration",
+            config.issuer
+        ))
+        .send()
+        .await
+        .and_then(|t| t.error_for_status())
+        .map_err(|_| {
+            (
+                StatusCode::BAD_GATEWAY,
+                "The identity provider is unavailable",
+            )
+        })?
+        .json()
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::BAD_GATEWAY,
+                "The identity provider is unavailable",
+            )
+        })?;
+
+    if discovery.issuer.trim_end_matches('/') != config.issuer {
+        return Err((
+            StatusCode::BAD_GATEWAY,
+            "The identity provider is misconfigured",
+        )
+            .into());
+    }
+
+    Ok(discovery)
+}
+
+/// Kept in a signed cookie between the redirect to the provider and the
+/// callback.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct OidcState {
+    state: String,
+    nonce: String,
+    verifier: String,
+    redirect: String,
+    link_user_id: Option<Uuid>,
+    expires: u64,
+}
+
+// Only same-origin paths are accepted as targets after signing in.
+fn is_local_path(path: &str) -> bool {
+    path.starts_with('/') && !path.starts_with("//") && !path.starts_with("/\\")
+}
+
+fn not_configured() -> ServiceError {
+    (StatusCode::NOT_FOUND, "Single sign-on is not configured").into()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct LoginQuery {
+    redirect: Option<String>,
+    #[serde(default)]
+    link: bool,
+}
+
+async fn login(
+    State(Oidc(config)): State<Oidc>,
+    State(AuthRwLock(lock)): State<AuthRwLock>,
+    OptionalClaim(option): OptionalClaim,
+    Query(query): Query<LoginQuery>,
+) -> Result<Response, ServiceError> {
+    let config = config.ok_or_else(not_configured)?;
+
+    let link_user_id = match (query.link, option) {
+        (false, _) => None,
+        (true, Some(claim)) => Some(claim.id),
+        (true, None) => {
+            return Err((StatusCode::UNAUTHORIZED, "No token is set for the request").into())
+        }
+    };
+
+    let discovery = discover(&config).await?;
+
+    let oidc_state = OidcState {
+        state: random_string(),
+        nonce: random_string(),
+        verifier: random_string(),
+        redirect: query
+            .redirect
+            .filter(|t| is_local_path(t))
+            .unwrap_or(String::from("/")),
+        link_user_id,
+        expires: timestamp_now() + STATE_DURATION,
+    };
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(oidc_state.verifier.as_bytes()));
+
+    let url = Url::parse_with_params(
+        &discovery.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", &config.client_id),
+            ("redirect_uri", &config.redirect_uri),
+            ("scope", &config.scope),
+            ("state", &oidc_state.state),
+            ("nonce", &oidc_state.nonce),
+            ("code_challenge", &challenge),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|_| {
+        (
+            StatusCode::BAD_GATEWAY,
+            "The identity provider is misconfigured",
+        )
+    })?;
+
+    let token = serde_cbor::to_vec(&oidc_state)
+        .ok()
+        .and_then(|t| sign_token(&t, lock).ok())
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, ""))?;
+    let cookie: Cookie = Cookie::build((STATE_COOKIE, token))
+        .path(STATE_COOKIE_PATH)
+        .secure(true)
+        .http_only(true)
+        // The callback is a cross-site navigation from the provider.
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(STATE_DURATION as i64))
+        .into();
+
+    Ok((CookieJar::new().add(cookie), Redirect::to(url.as_str())).into_response())
+}
+
+#[derive(Deserialize)]
+struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+    access_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    iss: String,
+    sub: String,
+    aud: Audience,
+    exp: u64,
+    nonce: Option<String>,
+    preferred_username: Option<String>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+fn invalid_response() -> ServiceError {
+    (
+        StatusCode::UNAUTHORIZED,
+        "The identity provider response is invalid",
+    )
+        .into()
+}
+
+/// Exchanges the authorization code and validates the returned ID token.
+/// The token comes straight from the token endpoint over TLS, so its claims
+/// are checked but its signature is not (OpenID Connect Core 3.1.3.7).
+/// Claims missing from the ID token are taken from the userinfo endpoint,
+/// where providers often put the groups.
+async fn exchange_code(
+    config: &OidcConfig,
+    discovery: &Discovery,
+    code: &str,
+    oidc_state: &OidcState,
+) -> Result<IdTokenClaims, ServiceError> {
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", &config.redirect_uri),
+        ("client_id", &config.client_id),
+        ("code_verifier", &oidc_state.verifier),
+    ];
+    if let Some(client_secret) = &config.client_secret {
+        form.push(("client_secret", client_secret));
+    }
+
+    let token_response: TokenResponse = config
+        .client
+        .post(&discovery.token_endpoint)
+        .form(&form)
+        .send()
+        .await
+        .and_then(|t| t.error_for_status())
+        .map_err(|_| invalid_response())?
+        .json()
+        .await
+        .map_err(|_| invalid_response())?;
+
+    let claim_bytes = token_response
+        .id_token
+        .split('.')
+        .nth(1)
+        .and_then(|t| URL_SAFE_NO_PAD.decode(t.trim_end_matches('=')).ok())
+        .ok_or_else(invalid_response)?;
+    let mut claims: IdTokenClaims =
+        serde_json::from_slice(&claim_bytes).map_err(|_| invalid_response())?;
+
+    let audience_valid = match &claims.aud {
+        Audience::One(aud) => *aud == config.client_id,
+        Audience::Many(aud) => aud.contains(&config.client_id),
+    };
+    if claims.iss.trim_end_matches('/') != config.issuer
+        || !audience_valid
+        || claims.exp <= timestamp_now()
+        || claims.nonce.as_deref() != Some(oidc_state.nonce.as_str())
+    {
+        return Err(invalid_response());
+    }
+
+    if let (Some(userinfo_endpoint), Some(access_token)) =
+        (&discovery.userinfo_endpoint, &token_response.access_token)
+    {
+        let mut userinfo: serde_json::Map<String, serde_json::Value> = config
+            .client
+            .get(userinfo_endpoint)
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .and_then(|t| t.error_for_status())
+            .map_err(|_| invalid_response())?
+            .json()
+            .await
+            .map_err(|_| invalid_response())?;
+
+        // The subject must match that of the ID token (5.3.2).
+        if userinfo.get("sub").and_then(|t| t.as_str()) != Some(claims.sub.as_str()) {
+            return Err(invalid_response());
+        }
+        if claims.preferred_username.is_none() {
+            claims.preferred_username = userinfo
+                .remove("preferred_username")
+                .and_then(|t| t.as_str().map(String::from));
+        }
+        for (key, value) in userinfo {
+            claims.extra.entry(key).or_insert(value);
+        }
+    }
+
+    Ok(claims)
+}
+
+fn is_in_admin_group(config: &OidcConfig, claims: &IdTokenClaims) -> Option<bool> {
+    let admin_group = config.admin_group.as_ref()?;
+
+    Some(match claims.extra.get(&config.groups_claim) {
+        Some(serde_json::Value::Array(groups)) => groups
+            .iter()
+            .any(|t| t.as_str() == Some(admin_group.as_str())),
+        Some(serde_json::Value::String(group)) => group == admin_group,
+        _ => false,
+    })
+}
+
+fn provision_user(
+    repo: &repo::Repo,
+    claims: &IdTokenClaims,
+    is_admin: bool,
+) -> Result<repo::User, ServiceError> {
+    let name: String = claims
+        .preferred_username
+        .as_deref()
+        .unwrap_or(&claims.sub)
+        .chars()
+        .take(MAX_NAME_LENGTH)
+        .collect();
+    let user_id = Uuid::new_v4();
+
+    // The account can only be signed into through the provider until an
+    // admin issues a password reset.
+    let hash = password::hash(&random_string())?;
+
+    let mut user = repo::User {
+        id: user_id,
+        name,
+        hash,
+        is_admin,
+        is_active: true,
+        totp_secret: None,
+        totp_enabled: false,
+        totp_last_step: None,
+        oidc_subject: Some(claims.sub.clone()),
+        backend: String::from(LOCAL_BACKEND),
+    };
+
+    match repo.add_user(user.clone()) {
+        Err(repo::Error::NotUnique { .. }) => {
+            let suffix = format!("-{}", &user_id.simple().to_string()[..8]);
+            user.name = user
+                .name
+                .chars()
+                .take(MAX_NAME_LENGTH - suffix.len())
+                .chain(suffix.chars())
+                .collect();
+            repo.add_user(user.clone())?;
+        }
+        result => result?,
+    }
+
+    Ok(user)
+}
+
+async fn callback(
+    State(Oidc(config)): State<Oidc>,
+    State(AuthRwLock(lock)): State<AuthRwLock>,
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    cookie_jar: CookieJar,
+    Query(query): Query<CallbackQuery>,
+) -> Result<Response, ServiceError> {
+    let config = config.ok_or_else(not_configured)?;
+
+    if query.error.is_some() {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "The identity provider denied the request",
+        )
+            .into());
+    }
+
+    let oidc_state: OidcState = cookie_jar
+        .get(STATE_COOKIE)
+        .and_then(|t| verify_token(t.value(), lock.clone()).ok())
+        .and_then(|t| serde_cbor::from_slice(&t).ok())
+        .filter(|t: &OidcState| t.expires > timestamp_now())
+        .ok_or((StatusCode::UNAUTHORIZED, "The sign-in request has expired"))?;
+    let code = match (query.code, query.state) {
+        (Some(code), Some(state)) if state == oidc_state.state => code,
+        _ => return Err(invalid_response()),
+    };
+
+    let discovery = discover(&config).await?;
+    let claims = exchange_code(&config, &discovery, &code, &oidc_state).await?;
+
+    let mut removal = Cookie::build((STATE_COOKIE, ""))
+        .path(STATE_COOKIE_PATH)
+        .build();
+    removal.make_removal();
+
+    let existing = match repo.get_user_by_oidc_subject(claims.sub.clone()) {
+        Ok(user) => Some(user),
+        Err(repo::Error::NotFound) => None,
+        Err(error) => return Err(error.into()),
+    };
+
+    if let Some(link_user_id) = oidc_state.link_user_id {
+        if existing.is_some_and(|t| t.id != link_user_id) {
+            return Err((
+                StatusCode::CONFLICT,
+                "The identity is already linked to another user",
+            )
+                .into());
+        }
+
+        repo.update_user_oidc_subject(link_user_id, Some(claims.sub.clone()))?;
+
+        audit::record(
+            &repo,
+            "user.link-oidc",
+            Some(link_user_id),
+            Some(address),
+            json!({ "userId": link_user_id, "subject": claims.sub }),
+        );
+
+        return Ok((
+            CookieJar::new().add(removal),
+            Redirect::to(&oidc_state.redirect),
+        )
+            .into_response());
+    }
+
+    let is_admin = is_in_admin_group(&config, &claims);
+    let mut user = match existing {
+        Some(user) => user,
+        None => {
+            let user = provision_user(&repo, &claims, is_admin.unwrap_or(false))?;
+
+            audit::record(
+                &repo,
+                "user.create",
+                Some(user.id),
+                Some(address),
+                json!({ "userId": user.id, "name": user.name, "backend": "oidc" }),
+            );
+
+            user
+        }
+    };
+
+    if !user.is_active {
+        return Err((StatusCode::FORBIDDEN, "The account has been deactivated").into());
+    }
+
+    if let Some(is_admin) = is_admin.filter(|&t| t != user.is_admin) {
+        repo.update_user_is_admin(user.id, is_admin)?;
+        user.is_admin = is_admin;
+    }
+
+    // The client finishes signing in at `/sign-in/totp` with the token from
+    // the fragment, which is never sent to a server.
+    if user.totp_enabled && !config.skip_totp {
+        // Base64 of the token is escaped so that it reads back as is when
+        // parsed as form data.
+        let pre_auth_token = make_pre_auth_token(lock, &user, timestamp_now())?
+            .replace('+', "%2B")
+            .replace('/', "%2F")
+            .replace('=', "%3D");
+        let separator = match oidc_state.redirect.contains('#') {
+            true => '&',
+            false => '#',
+        };
+
+        return Ok((
+            CookieJar::new().add(removal),
+            Redirect::to(&format!(
+                "{}{}pre-auth-token={}",
+                oidc_state.redirect, separator, pre_auth_token
+            )),
+        )
+            .into_response());
+    }
+
+    let token_jar = start_session(lock, &repo, &user, timestamp_now(), "oidc", Some(address))?;
+
+    Ok((token_jar.add(removal), Redirect::to(&oidc_state.redirect)).into_response())
+}
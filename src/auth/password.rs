@@ -0,0 +1,78 @@
+use std::sync::OnceLock;
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use axum::http::StatusCode;
+use rand::rngs::OsRng;
+
+use crate::api::ServiceError;
+
+const MIN_LENGTH: usize = 8;
+
+// Lower-cased, one password per line.
+const BREACHED_LIST: &str = include_str!("breached.txt");
+
+pub fn check_policy(pass: &str) -> Result<(), ServiceError> {
+    if pass.chars().count() < MIN_LENGTH {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The password must be at least 8 characters long",
+        )
+            .into());
+    }
+
+    let lowercase = pass.to_lowercase();
+    if BREACHED_LIST.lines().any(|t| t == lowercase) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The password appears in a list of breached passwords",
+        )
+            .into());
+    }
+
+    Ok(())
+}
+
+pub fn hash(pass: &str) -> Result<String, ServiceError> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Ok(Argon2::default()
+        .hash_password(pass.as_bytes(), &salt)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, ""))?
+        .to_string())
+}
+
+pub fn verify(pass: &str, hash: &str) -> Result<bool, ServiceError> {
+    let hash = PasswordHash::new(hash).map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, ""))?;
+
+    Ok(Argon2::default()
+        .verify_password(pass.as_bytes(), &hash)
+        .is_ok())
+}
+
+/// Performs a verification against a throwaway hash, so that looking up an
+/// unknown user takes as long as checking a wrong password.
+pub fn verify_dummy(pass: &str) {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+    let hash = DUMMY_HASH.get_or_init(|| hash("dummy password").unwrap_or_default());
+    let _ = verify(pass, hash);
+}
+
+/// Whether a stored hash was produced with parameters other than the current
+/// defaults, so it should be replaced after the next successful verification.
+pub fn needs_rehash(hash: &str) -> bool {
+    let Ok(hash) = PasswordHash::new(hash) else {
+        return true;
+    };
+    let Ok(params) = Params::try_from(&hash) else {
+        return true;
+    };
+    let current = Params::default();
+
+    hash.algorithm != Algorithm::default().ident()
+        || hash.version != Some(Version::default().into())
+        || params.m_cost() != current.m_cost()
+        || params.t_cost() != current.t_cost()
+        || params.p_cost() != current.p_cost()
+}
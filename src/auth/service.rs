@@ -1,29 +1,70 @@
-use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
-use argon2::Argon2;
-use axum::extract::{FromRef, Json, Query, State};
-use axum::http::StatusCode;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+
+use axum::extract::{ConnectInfo, FromRef, Json, Query, State};
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::{routing, Router};
-use rand::rngs::OsRng;
+use axum_extra::extract::CookieJar;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
+use crate::api::audit;
 use crate::auth::{Claim, ServiceError};
 use crate::repo;
 
-use super::{timestamp_now, AuthRwLock, OptionalClaim, TOKEN_DURATION};
+use super::backend::{AuthBackends, LOCAL_BACKEND};
+use super::invite;
+use super::oidc::{self, Oidc};
+use super::throttle::{self, ThrottleMutex};
+use super::{
+    password, timestamp_now, totp, AuthRwLock, OptionalClaim, PreAuthClaim, Secret, TOKEN_DURATION,
+};
+
+const RESET_TOKEN_DURATION: u64 = 24 * 60 * 60;
+const PRE_AUTH_TOKEN_DURATION: u64 = 5 * 60;
+const RECOVERY_CODE_COUNT: usize = 10;
 
 pub fn build_router<S>() -> Router<S>
 where
     S: Send + Sync + Clone + 'static,
     AuthRwLock: FromRef<S>,
+    AuthBackends: FromRef<S>,
+    ThrottleMutex: FromRef<S>,
+    Oidc: FromRef<S>,
     repo::Repo: FromRef<S>,
 {
     Router::new()
         .route("/sign-in", routing::post(sign_in))
+        .route("/sign-in/totp", routing::post(sign_in_totp))
+        .nest("/oidc", oidc::build_router())
+        .merge(invite::build_router())
         .route("/sign-out", routing::get(sign_out))
+        .route("/sign-out-all", routing::post(sign_out_all))
         .route("/claim", routing::get(get_claim))
+        .route("/session", routing::get(get_session_list))
+        .route("/password", routing::post(change_password))
+        .route("/reset-password", routing::post(reset_password))
+        .route("/totp/enroll", routing::post(enroll_totp))
+        .route("/totp/confirm", routing::post(confirm_totp))
+        .route("/totp/disable", routing::post(disable_totp))
+        .route(
+            "/totp/recovery-code",
+            routing::post(regenerate_recovery_code),
+        )
         .route("/user", routing::get(get_user).post(add_user))
+        .route("/user/revoke", routing::post(revoke_user))
+        .route("/user/reset", routing::post(add_password_reset))
+        .route("/user/reset-totp", routing::post(reset_user_totp))
+        .route("/user/list", routing::get(get_user_list))
+        .route("/user/rename", routing::post(rename_user))
+        .route("/user/set-admin", routing::post(set_user_admin))
+        .route("/user/set-active", routing::post(set_user_active))
 }
 
 #[derive(Deserialize)]
@@ -34,36 +75,314 @@ struct SignInRequest {
 
 async fn sign_in(
     State(AuthRwLock(lock)): State<AuthRwLock>,
+    State(AuthBackends(backends)): State<AuthBackends>,
+    State(ThrottleMutex(throttle)): State<ThrottleMutex>,
     State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(request): Json<SignInRequest>,
 ) -> Result<Response, ServiceError> {
-    let user = repo.get_user_by_name(request.name)?;
+    let now = timestamp_now();
+    let account_key = format!("name:{}", request.name);
+    let address_key = format!(
+        "ip:{}",
+        throttle
+            .lock()
+            .unwrap()
+            .client_address(address.ip(), &headers)
+    );
+
+    let locked_for = {
+        let throttle = throttle.lock().unwrap();
+        throttle
+            .locked_for(&account_key, now)
+            .max(throttle.locked_for(&address_key, now))
+    };
+    if let Some(locked_for) = locked_for {
+        audit::record(
+            &repo,
+            "sign-in.locked-out",
+            None,
+            Some(address),
+            json!({ "name": request.name, "lockedFor": locked_for }),
+        );
+
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            "Too many failed sign-in attempts, please try again later",
+        )
+            .into());
+    }
+
+    // A failing backend does not stop the others, a later one may still
+    // know the user.
+    let mut authenticated = None;
+    let mut backend_error = None;
+    for backend in backends.iter() {
+        match backend
+            .authenticate(&repo, &request.name, &request.pass)
+            .await
+        {
+            Ok(Some(user)) => {
+                authenticated = Some(user);
+                break;
+            }
+            Ok(None) => {}
+            Err(error) => {
+                tracing::warn!("Sign-in backend failed for {}: {}", request.name, error);
+                backend_error = Some(error);
+            }
+        }
+    }
 
-    let hash =
-        PasswordHash::new(&user.hash).map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, ""))?;
+    let user = match authenticated {
+        Some(user) if user.is_active => user,
+        _ => {
+            let mut throttle = throttle.lock().unwrap();
+            throttle.fail(&account_key, now, throttle::ACCOUNT_FREE_ATTEMPTS);
+            throttle.fail(&address_key, now, throttle::ADDRESS_FREE_ATTEMPTS);
+            drop(throttle);
 
-    Argon2::default()
-        .verify_password(&request.pass.into_bytes(), &hash)
-        .map_err(|_| {
-            (
+            let mut detail = json!({ "name": request.name, "method": "password" });
+            if let Some(error) = &backend_error {
+                detail["error"] = json!(error.to_string());
+            }
+            audit::record(&repo, "sign-in.failure", None, Some(address), detail);
+
+            // Backend errors get the same answer as a wrong password, so the
+            // response tells nothing about the account.
+            return Err((
                 StatusCode::UNAUTHORIZED,
                 "Invalid user name and password combination",
             )
-        })?;
+                .into());
+        }
+    };
+
+    throttle.lock().unwrap().succeed(&account_key);
+
+    if user.totp_enabled {
+        let pre_auth_token = make_pre_auth_token(lock, &user, now)?;
+
+        return Ok(Json(PreAuth { pre_auth_token }).into_response());
+    }
+
+    Ok((
+        StatusCode::OK,
+        start_session(lock, &repo, &user, now, "password", Some(address))?,
+    )
+        .into_response())
+}
 
-    let expires = timestamp_now() + TOKEN_DURATION;
+/// The token to exchange along with a second factor for a session at
+/// `/sign-in/totp`.
+pub(super) fn make_pre_auth_token(
+    lock: Arc<RwLock<Secret>>,
+    user: &repo::User,
+    now: u64,
+) -> Result<String, ServiceError> {
+    let pre_auth_claim = PreAuthClaim {
+        pre_auth_id: user.id,
+        expires: now + PRE_AUTH_TOKEN_DURATION,
+    };
+
+    pre_auth_claim
+        .to_token(lock)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "").into())
+}
+
+/// Records the sign-in, tagged with the `method` that authenticated the user.
+pub(super) fn start_session(
+    lock: Arc<RwLock<Secret>>,
+    repo: &repo::Repo,
+    user: &repo::User,
+    now: u64,
+    method: &str,
+    address: Option<SocketAddr>,
+) -> Result<CookieJar, ServiceError> {
+    let expires = now + TOKEN_DURATION;
+
+    let session_id = Uuid::new_v4();
+    repo.add_session(repo::Session {
+        id: session_id,
+        user_id: user.id,
+        created_at: naive_from_timestamp(now),
+        expires_at: naive_from_timestamp(expires),
+        revoked: false,
+    })?;
+
+    audit::record(
+        repo,
+        "sign-in.success",
+        Some(user.id),
+        address,
+        json!({ "sessionId": session_id, "method": method, "backend": user.backend }),
+    );
 
     let claim = Claim {
-        id: user.id.clone(),
+        id: user.id,
+        session_id,
         expires,
         is_admin: user.is_admin,
     };
 
-    Ok((StatusCode::OK, super::make_token(lock, claim)?).into_response())
+    super::make_token(lock, claim)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PreAuth {
+    pre_auth_token: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignInTotpRequest {
+    pre_auth_token: String,
+    code: String,
+}
+
+async fn sign_in_totp(
+    State(AuthRwLock(lock)): State<AuthRwLock>,
+    State(ThrottleMutex(throttle)): State<ThrottleMutex>,
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    Json(request): Json<SignInTotpRequest>,
+) -> Result<Response, ServiceError> {
+    let now = timestamp_now();
+
+    let pre_auth_claim = PreAuthClaim::from_token(&request.pre_auth_token, lock.clone())
+        .map_err(|_| (StatusCode::UNAUTHORIZED, "The provided token is invalid"))?;
+    let totp_key = format!("totp:{}", pre_auth_claim.pre_auth_id);
+
+    let locked_for = throttle.lock().unwrap().locked_for(&totp_key, now);
+    if let Some(locked_for) = locked_for {
+        audit::record(
+            &repo,
+            "sign-in.locked-out",
+            Some(pre_auth_claim.pre_auth_id),
+            Some(address),
+            json!({ "userId": pre_auth_claim.pre_auth_id, "lockedFor": locked_for }),
+        );
+
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            "Too many failed sign-in attempts, please try again later",
+        )
+            .into());
+    }
+
+    let user = repo.get_user_by_id(pre_auth_claim.pre_auth_id)?;
+    if !user.is_active || !user.totp_enabled {
+        return Err((StatusCode::UNAUTHORIZED, "The provided token is invalid").into());
+    }
+
+    if !verify_second_factor(&repo, &user, &request.code, now)? {
+        throttle
+            .lock()
+            .unwrap()
+            .fail(&totp_key, now, throttle::ACCOUNT_FREE_ATTEMPTS);
+
+        audit::record(
+            &repo,
+            "sign-in.failure",
+            Some(user.id),
+            Some(address),
+            json!({ "name": user.name, "method": "totp" }),
+        );
+
+        return Err((StatusCode::UNAUTHORIZED, "Invalid verification code").into());
+    }
+
+    throttle.lock().unwrap().succeed(&totp_key);
+
+    Ok((
+        StatusCode::OK,
+        start_session(lock, &repo, &user, now, "totp", Some(address))?,
+    )
+        .into_response())
+}
+
+/// Accepts either a current TOTP code or an unused recovery code.
+fn verify_second_factor(
+    repo: &repo::Repo,
+    user: &repo::User,
+    code: &str,
+    now: u64,
+) -> Result<bool, ServiceError> {
+    let secret = user.totp_secret.as_deref().unwrap_or_default();
+    let last_step = user.totp_last_step.map(|t| t as u64);
+
+    if let Some(step) = totp::verify(secret, code, now, last_step) {
+        return Ok(repo.update_user_totp_last_step(user.id, step as i64)?);
+    }
+
+    Ok(repo.use_recovery_code(user.id, hash_token(&normalize_recovery_code(code)))?)
+}
+
+fn generate_recovery_code_list(user_id: Uuid) -> (Vec<String>, Vec<repo::RecoveryCode>) {
+    const ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+    let code_list = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 10];
+            OsRng.fill_bytes(&mut bytes);
+            let chars = bytes
+                .iter()
+                .map(|&t| ALPHABET[t as usize % ALPHABET.len()] as char)
+                .collect::<String>();
+            format!("{}-{}", &chars[..5], &chars[5..])
+        })
+        .collect::<Vec<_>>();
+    let record_list = code_list
+        .iter()
+        .map(|t| repo::RecoveryCode {
+            hash: hash_token(t),
+            user_id,
+            used: false,
+        })
+        .collect::<Vec<_>>();
+
+    (code_list, record_list)
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.trim().to_lowercase()
+}
+
+pub(super) fn naive_from_timestamp(timestamp: u64) -> NaiveDateTime {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .naive_utc()
+}
+
+async fn sign_out(
+    State(repo): State<repo::Repo>,
+    OptionalClaim(option): OptionalClaim,
+) -> Result<Response, ServiceError> {
+    if let Some(claim) = option {
+        repo.revoke_session_by_id(claim.session_id)?;
+    }
+
+    Ok((StatusCode::OK, super::empty_token()).into_response())
 }
 
-async fn sign_out() -> Response {
-    (StatusCode::OK, super::empty_token()).into_response()
+async fn sign_out_all(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+) -> Result<Response, ServiceError> {
+    let count = repo.revoke_session_by_user_id(claim.id)?;
+
+    audit::record(
+        &repo,
+        "session.revoke-all",
+        Some(claim.id),
+        Some(address),
+        json!({ "userId": claim.id, "count": count }),
+    );
+
+    Ok((StatusCode::OK, super::empty_token()).into_response())
 }
 
 #[derive(Serialize)]
@@ -76,13 +395,38 @@ struct UserInfo {
 async fn get_claim(
     OptionalClaim(option): OptionalClaim,
 ) -> Result<Json<Option<UserInfo>>, ServiceError> {
-    Ok(Json(match option {
-        Some(claim) => Some(UserInfo {
-            id: claim.id,
-            is_admin: claim.is_admin,
-        }),
-        None => None,
-    }))
+    Ok(Json(option.map(|claim| UserInfo {
+        id: claim.id,
+        is_admin: claim.is_admin,
+    })))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Session {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    is_current: bool,
+}
+
+async fn get_session_list(
+    State(repo): State<repo::Repo>,
+    claim: Claim,
+) -> Result<Json<Vec<Session>>, ServiceError> {
+    let session_list = repo.get_session_by_user_id(claim.id)?;
+
+    Ok(Json(
+        session_list
+            .into_iter()
+            .map(|t| Session {
+                id: t.id,
+                created_at: t.created_at.and_utc(),
+                expires_at: t.expires_at.and_utc(),
+                is_current: t.id == claim.session_id,
+            })
+            .collect::<Vec<_>>(),
+    ))
 }
 
 pub fn create_user(
@@ -91,23 +435,44 @@ pub fn create_user(
     pass: &str,
     is_admin: bool,
 ) -> Result<Uuid, ServiceError> {
+    password::check_policy(pass)?;
+
     let user_id = Uuid::new_v4();
-    let salt = SaltString::generate(&mut OsRng);
-    let hash = Argon2::default()
-        .hash_password(pass.as_bytes(), &salt)
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, ""))?
-        .to_string();
+    let hash = password::hash(pass)?;
 
     repo.add_user(repo::User {
         id: user_id,
         name: name.into(),
         hash,
-        is_admin: is_admin,
+        is_admin,
+        is_active: true,
+        totp_secret: None,
+        totp_enabled: false,
+        totp_last_step: None,
+        oidc_subject: None,
+        backend: String::from(LOCAL_BACKEND),
     })?;
 
     Ok(user_id)
 }
 
+/// Creates the admin account named `name` unless there already is an admin,
+/// so that renaming the first admin does not bring back another one.
+/// Returns whether the account was created.
+pub fn create_initial_admin(
+    repo: repo::Repo,
+    name: &str,
+    pass: &str,
+) -> Result<bool, ServiceError> {
+    if repo.has_admin()? {
+        return Ok(false);
+    }
+
+    create_user(repo, name, pass, true)?;
+
+    Ok(true)
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "kebab-case")]
 struct IdQuery {
@@ -141,15 +506,492 @@ struct NewUser {
 
 async fn add_user(
     State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
     claim: Claim,
     Json(new_user): Json<NewUser>,
 ) -> Result<Json<Uuid>, ServiceError> {
-    if !claim.is_admin {
+    claim.require_admin()?;
+
+    let user_id = create_user(repo.clone(), &new_user.name, &new_user.pass, false)?;
+
+    audit::record(
+        &repo,
+        "user.create",
+        Some(claim.id),
+        Some(address),
+        json!({ "userId": user_id, "name": new_user.name, "backend": LOCAL_BACKEND }),
+    );
+
+    Ok(Json(user_id))
+}
+
+async fn revoke_user(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(query): Json<IdQuery>,
+) -> Result<Json<usize>, ServiceError> {
+    claim.require_admin()?;
+
+    let user = repo.get_user_by_id(query.id)?;
+
+    let count = repo.revoke_session_by_user_id(user.id)?;
+
+    audit::record(
+        &repo,
+        "session.revoke-all",
+        Some(claim.id),
+        Some(address),
+        json!({ "userId": user.id, "count": count }),
+    );
+
+    Ok(Json(count))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChangePasswordRequest {
+    old_pass: String,
+    new_pass: String,
+}
+
+async fn change_password(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<StatusCode, ServiceError> {
+    let user = repo.get_user_by_id(claim.id)?;
+
+    if user.backend != LOCAL_BACKEND {
         return Err((
-            StatusCode::UNAUTHORIZED,
-            "You don't have the appropriate permission for the request",
-        ).into());
+            StatusCode::CONFLICT,
+            "The password is managed by an external directory",
+        )
+            .into());
+    }
+
+    if !password::verify(&request.old_pass, &user.hash)? {
+        return Err((StatusCode::UNAUTHORIZED, "The old password is incorrect").into());
     }
 
-    create_user(repo.clone(), &new_user.name, &new_user.pass, false).map(|user_id| Json(user_id))
+    password::check_policy(&request.new_pass)?;
+
+    repo.update_user_hash(user.id, password::hash(&request.new_pass)?)?;
+    // Sessions elsewhere may belong to whoever learned the old password.
+    let count = repo.revoke_other_session(user.id, claim.session_id)?;
+
+    audit::record(
+        &repo,
+        "password.change",
+        Some(user.id),
+        Some(address),
+        json!({ "userId": user.id, "revokedSessionCount": count }),
+    );
+
+    Ok(StatusCode::OK)
+}
+
+pub(super) fn hash_token(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PasswordReset {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+async fn add_password_reset(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(query): Json<IdQuery>,
+) -> Result<Json<PasswordReset>, ServiceError> {
+    claim.require_admin()?;
+
+    let user = repo.get_user_by_id(query.id)?;
+
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token = URL_SAFE_NO_PAD.encode(bytes);
+    let expires_at = naive_from_timestamp(timestamp_now() + RESET_TOKEN_DURATION);
+
+    repo.add_password_reset(repo::PasswordReset {
+        hash: hash_token(&token),
+        user_id: user.id,
+        created_by: claim.id,
+        expires_at,
+        used: false,
+    })?;
+
+    audit::record(
+        &repo,
+        "password.reset-issue",
+        Some(claim.id),
+        Some(address),
+        json!({ "userId": user.id }),
+    );
+
+    Ok(Json(PasswordReset {
+        token,
+        expires_at: expires_at.and_utc(),
+    }))
+}
+
+#[derive(Deserialize)]
+struct ResetPasswordRequest {
+    token: String,
+    pass: String,
+}
+
+async fn reset_password(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<StatusCode, ServiceError> {
+    password::check_policy(&request.pass)?;
+
+    let user_id = repo
+        .reset_password(hash_token(&request.token), password::hash(&request.pass)?)
+        .map_err(|error| match error {
+            repo::Error::NotFound => (
+                StatusCode::UNAUTHORIZED,
+                "The reset token is invalid, expired or already used",
+            )
+                .into(),
+            _ => ServiceError::from(error),
+        })?;
+
+    audit::record(
+        &repo,
+        "password.reset",
+        Some(user_id),
+        Some(address),
+        json!({ "userId": user_id }),
+    );
+
+    Ok(StatusCode::OK)
+}
+
+const USER_LIST_LIMIT: i64 = 100;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct UserListQuery {
+    search: Option<String>,
+    offset: Option<i64>,
+    limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UserDetail {
+    id: Uuid,
+    name: String,
+    is_admin: bool,
+    is_active: bool,
+}
+
+async fn get_user_list(
+    State(repo): State<repo::Repo>,
+    claim: Claim,
+    Query(query): Query<UserListQuery>,
+) -> Result<Json<Vec<UserDetail>>, ServiceError> {
+    claim.require_admin()?;
+
+    let user_list = repo.get_user(
+        query.search,
+        query.offset.unwrap_or(0).max(0),
+        query
+            .limit
+            .unwrap_or(USER_LIST_LIMIT)
+            .clamp(0, USER_LIST_LIMIT),
+    )?;
+
+    Ok(Json(
+        user_list
+            .into_iter()
+            .map(|t| UserDetail {
+                id: t.id,
+                name: t.name,
+                is_admin: t.is_admin,
+                is_active: t.is_active,
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+#[derive(Deserialize)]
+struct RenameUserRequest {
+    id: Uuid,
+    name: String,
+}
+
+async fn rename_user(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<RenameUserRequest>,
+) -> Result<StatusCode, ServiceError> {
+    claim.require_admin()?;
+
+    repo.update_user_name(request.id, request.name.clone())?;
+
+    audit::record(
+        &repo,
+        "user.rename",
+        Some(claim.id),
+        Some(address),
+        json!({ "userId": request.id, "name": request.name }),
+    );
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetAdminRequest {
+    id: Uuid,
+    is_admin: bool,
+}
+
+async fn set_user_admin(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<SetAdminRequest>,
+) -> Result<StatusCode, ServiceError> {
+    claim.require_admin()?;
+
+    if request.id == claim.id && !request.is_admin {
+        return Err((
+            StatusCode::CONFLICT,
+            "You cannot revoke your own admin role",
+        )
+            .into());
+    }
+
+    repo.update_user_is_admin(request.id, request.is_admin)?;
+
+    audit::record(
+        &repo,
+        "user.set-admin",
+        Some(claim.id),
+        Some(address),
+        json!({ "userId": request.id, "isAdmin": request.is_admin }),
+    );
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetActiveRequest {
+    id: Uuid,
+    is_active: bool,
+}
+
+async fn set_user_active(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<SetActiveRequest>,
+) -> Result<StatusCode, ServiceError> {
+    claim.require_admin()?;
+
+    if request.id == claim.id && !request.is_active {
+        return Err((
+            StatusCode::CONFLICT,
+            "You cannot deactivate your own account",
+        )
+            .into());
+    }
+
+    repo.update_user_is_active(request.id, request.is_active)?;
+
+    audit::record(
+        &repo,
+        "user.set-active",
+        Some(claim.id),
+        Some(address),
+        json!({ "userId": request.id, "isActive": request.is_active }),
+    );
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+struct PassRequest {
+    pass: String,
+}
+
+fn verify_own_password(
+    repo: &repo::Repo,
+    claim: &Claim,
+    pass: &str,
+) -> Result<repo::User, ServiceError> {
+    let user = repo.get_user_by_id(claim.id)?;
+
+    if !password::verify(pass, &user.hash)? {
+        return Err((StatusCode::UNAUTHORIZED, "The password is incorrect").into());
+    }
+
+    Ok(user)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TotpEnrollment {
+    secret: String,
+    uri: String,
+}
+
+async fn enroll_totp(
+    State(repo): State<repo::Repo>,
+    claim: Claim,
+    Json(request): Json<PassRequest>,
+) -> Result<Json<TotpEnrollment>, ServiceError> {
+    let user = verify_own_password(&repo, &claim, &request.pass)?;
+
+    // Rotating the secret would otherwise take the second factor away
+    // from whoever learned the password.
+    if user.totp_enabled {
+        return Err((
+            StatusCode::CONFLICT,
+            "Two-factor authentication is already enabled",
+        )
+            .into());
+    }
+
+    let secret = totp::generate_secret();
+    repo.update_user_totp_secret(user.id, secret.clone())?;
+
+    Ok(Json(TotpEnrollment {
+        uri: totp::provisioning_uri(&secret, &user.name),
+        secret,
+    }))
+}
+
+#[derive(Deserialize)]
+struct CodeRequest {
+    code: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RecoveryCodeList {
+    recovery_code_list: Vec<String>,
+}
+
+async fn confirm_totp(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<CodeRequest>,
+) -> Result<Json<RecoveryCodeList>, ServiceError> {
+    let user = repo.get_user_by_id(claim.id)?;
+
+    let secret = match (&user.totp_secret, user.totp_enabled) {
+        (Some(secret), false) => secret,
+        _ => {
+            return Err((StatusCode::CONFLICT, "There is no pending enrollment").into());
+        }
+    };
+
+    let step = totp::verify(secret, &request.code, timestamp_now(), None)
+        .ok_or((StatusCode::UNAUTHORIZED, "Invalid verification code"))?;
+
+    let (code_list, record_list) = generate_recovery_code_list(user.id);
+    repo.enable_user_totp(user.id, step as i64, record_list)?;
+
+    audit::record(
+        &repo,
+        "totp.enable",
+        Some(user.id),
+        Some(address),
+        json!({ "userId": user.id }),
+    );
+
+    Ok(Json(RecoveryCodeList {
+        recovery_code_list: code_list,
+    }))
+}
+
+#[derive(Deserialize)]
+struct PassCodeRequest {
+    pass: String,
+    code: String,
+}
+
+/// Takes the second factor as well, so that the password alone cannot
+/// disable and enroll it again.
+async fn disable_totp(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(request): Json<PassCodeRequest>,
+) -> Result<StatusCode, ServiceError> {
+    let user = verify_own_password(&repo, &claim, &request.pass)?;
+
+    if user.totp_enabled && !verify_second_factor(&repo, &user, &request.code, timestamp_now())? {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid verification code").into());
+    }
+
+    repo.disable_user_totp(user.id)?;
+
+    audit::record(
+        &repo,
+        "totp.disable",
+        Some(user.id),
+        Some(address),
+        json!({ "userId": user.id }),
+    );
+
+    Ok(StatusCode::OK)
+}
+
+async fn regenerate_recovery_code(
+    State(repo): State<repo::Repo>,
+    claim: Claim,
+    Json(request): Json<PassRequest>,
+) -> Result<Json<RecoveryCodeList>, ServiceError> {
+    let user = verify_own_password(&repo, &claim, &request.pass)?;
+
+    if !user.totp_enabled {
+        return Err((
+            StatusCode::CONFLICT,
+            "Two-factor authentication is not enabled",
+        )
+            .into());
+    }
+
+    let (code_list, record_list) = generate_recovery_code_list(user.id);
+    repo.replace_recovery_code(user.id, record_list)?;
+
+    Ok(Json(RecoveryCodeList {
+        recovery_code_list: code_list,
+    }))
+}
+
+async fn reset_user_totp(
+    State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    claim: Claim,
+    Json(query): Json<IdQuery>,
+) -> Result<StatusCode, ServiceError> {
+    claim.require_admin()?;
+
+    repo.disable_user_totp(query.id)?;
+
+    audit::record(
+        &repo,
+        "totp.disable",
+        Some(claim.id),
+        Some(address),
+        json!({ "userId": query.id }),
+    );
+
+    Ok(StatusCode::OK)
 }
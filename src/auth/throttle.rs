@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::env;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use axum::http::HeaderMap;
+
+const BASE_DELAY: u64 = 2;
+const MAX_DELAY: u64 = 15 * 60;
+// Entries with no failure for this long are forgotten.
+const FORGET_AFTER: u64 = 60 * 60;
+const PRUNE_THRESHOLD: usize = 16 * 1024;
+// Past this many entries, those whose lock-out or last failure ends earliest
+// are evicted even if recent, so rotating account names cannot grow the map
+// without bound.
+const MAX_ENTRIES: usize = 64 * 1024;
+
+pub const ACCOUNT_FREE_ATTEMPTS: u32 = 5;
+pub const ADDRESS_FREE_ATTEMPTS: u32 = 20;
+
+struct Attempt {
+    failures: u32,
+    last_failure: u64,
+    locked_until: u64,
+}
+
+/// Failed sign-in attempts per key (an account name or a client address).
+/// Once a key has used up its free attempts, each further failure locks it
+/// out for twice as long as the previous one.
+///
+/// The attempts are kept in memory: they are lost on restart and not shared
+/// between processes, so the throttle assumes a single server instance.
+///
+/// Client addresses are those of the peers, which behind a reverse proxy is
+/// the proxy for everyone, unless the proxy is listed in `TRUSTED_PROXIES`.
+pub struct Throttle {
+    attempts: HashMap<String, Attempt>,
+    trusted_proxy_list: Vec<IpAddr>,
+}
+
+impl Throttle {
+    pub fn new() -> Self {
+        Throttle {
+            attempts: HashMap::new(),
+            trusted_proxy_list: Vec::new(),
+        }
+    }
+
+    /// Returns the address failures of a request count against. While the
+    /// request comes from a trusted proxy, the address it forwards for, the
+    /// last one of `X-Forwarded-For`, is taken instead, so a client cannot
+    /// pick its address by sending the header itself.
+    pub fn client_address(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let mut forwarded_list = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .flat_map(|t| t.to_str().unwrap_or("").split(','))
+            .map(|t| t.trim().parse::<IpAddr>())
+            .collect::<Vec<_>>();
+
+        let mut address = peer;
+        while self.trusted_proxy_list.contains(&address) {
+            match forwarded_list.pop() {
+                Some(Ok(forwarded)) => address = forwarded,
+                _ => break,
+            }
+        }
+        address
+    }
+
+    /// Returns the number of seconds the key is still locked out for.
+    pub fn locked_for(&self, key: &str, now: u64) -> Option<u64> {
+        self.attempts
+            .get(key)
+            .filter(|t| t.locked_until > now)
+            .map(|t| t.locked_until - now)
+    }
+
+    pub fn fail(&mut self, key: &str, now: u64, free_attempts: u32) {
+        if self.attempts.len() >= PRUNE_THRESHOLD {
+            self.attempts
+                .retain(|_, t| t.locked_until > now || t.last_failure + FORGET_AFTER > now);
+        }
+        if self.attempts.len() >= MAX_ENTRIES {
+            let mut order = self
+                .attempts
+                .iter()
+                .map(|(key, t)| (t.locked_until.max(t.last_failure), key.clone()))
+                .collect::<Vec<_>>();
+            order.sort_unstable();
+            for (_, key) in order.into_iter().take(MAX_ENTRIES / 4) {
+                self.attempts.remove(&key);
+            }
+        }
+
+        let attempt = self.attempts.entry(key.into()).or_insert(Attempt {
+            failures: 0,
+            last_failure: now,
+            locked_until: 0,
+        });
+        if attempt.last_failure + FORGET_AFTER <= now {
+            attempt.failures = 0;
+        }
+
+        attempt.failures += 1;
+        attempt.last_failure = now;
+        if attempt.failures >= free_attempts {
+            let exponent = (attempt.failures - free_attempts).min(16);
+            let delay = (BASE_DELAY << exponent).min(MAX_DELAY);
+            attempt.locked_until = now + delay;
+        }
+    }
+
+    pub fn succeed(&mut self, key: &str) {
+        self.attempts.remove(key);
+    }
+}
+
+#[derive(Clone)]
+pub struct ThrottleMutex(pub Arc<Mutex<Throttle>>);
+
+impl ThrottleMutex {
+    pub fn new() -> Self {
+        ThrottleMutex(Arc::new(Mutex::new(Throttle::new())))
+    }
+
+    pub fn from_env() -> Self {
+        let mut throttle = Throttle::new();
+        if let Ok(trusted_proxies) = env::var("TRUSTED_PROXIES") {
+            throttle.trusted_proxy_list = trusted_proxies
+                .split(',')
+                .map(|t| t.trim())
+                .filter(|t| !t.is_empty())
+                .map(|t| t.parse().expect("TRUSTED_PROXIES"))
+                .collect();
+        }
+        ThrottleMutex(Arc::new(Mutex::new(throttle)))
+    }
+}
+
+impl Default for ThrottleMutex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locks_out_after_the_free_attempts() {
+        let mut throttle = Throttle::new();
+
+        for _ in 0..2 {
+            throttle.fail("alice", 100, 3);
+            assert_eq!(throttle.locked_for("alice", 100), None);
+        }
+        throttle.fail("alice", 100, 3);
+        assert_eq!(throttle.locked_for("alice", 100), Some(BASE_DELAY));
+        assert_eq!(throttle.locked_for("alice", 100 + BASE_DELAY), None);
+        assert_eq!(throttle.locked_for("bob", 100), None);
+    }
+
+    #[test]
+    fn doubles_the_delay_up_to_the_maximum() {
+        let mut throttle = Throttle::new();
+
+        let mut delay_list = Vec::new();
+        for _ in 0..12 {
+            throttle.fail("alice", 100, 1);
+            delay_list.push(throttle.locked_for("alice", 100).unwrap());
+        }
+        assert_eq!(
+            delay_list,
+            vec![2, 4, 8, 16, 32, 64, 128, 256, 512, MAX_DELAY, MAX_DELAY, MAX_DELAY]
+        );
+    }
+
+    #[test]
+    fn resets_on_success() {
+        let mut throttle = Throttle::new();
+
+        for _ in 0..4 {
+            throttle.fail("alice", 100, 3);
+        }
+        assert!(throttle.locked_for("alice", 100).is_some());
+
+        throttle.succeed("alice");
+        assert_eq!(throttle.locked_for("alice", 100), None);
+        throttle.fail("alice", 100, 3);
+        assert_eq!(throttle.locked_for("alice", 100), None);
+    }
+
+    #[test]
+    fn forgets_old_failures() {
+        let mut throttle = Throttle::new();
+
+        throttle.fail("alice", 100, 2);
+        throttle.fail("alice", 100 + FORGET_AFTER - 1, 2);
+        assert_eq!(
+            throttle.locked_for("alice", 100 + FORGET_AFTER - 1),
+            Some(BASE_DELAY)
+        );
+
+        // The window restarts after a quiet hour.
+        let now = 100 + 2 * FORGET_AFTER;
+        throttle.fail("alice", now, 2);
+        assert_eq!(throttle.locked_for("alice", now), None);
+        throttle.fail("alice", now, 2);
+        assert_eq!(throttle.locked_for("alice", now), Some(BASE_DELAY));
+    }
+
+    #[test]
+    fn takes_forwarded_addresses_from_trusted_proxies_only() {
+        let mut throttle = Throttle::new();
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let client: IpAddr = "192.0.2.7".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.9, 192.0.2.7".parse().unwrap());
+
+        // Without trusted proxies the header is ignored.
+        assert_eq!(throttle.client_address(proxy, &headers), proxy);
+
+        throttle.trusted_proxy_list = vec![proxy];
+        assert_eq!(throttle.client_address(proxy, &headers), client);
+        assert_eq!(throttle.client_address(client, &headers), client);
+        assert_eq!(throttle.client_address(proxy, &HeaderMap::new()), proxy);
+
+        // Chained proxies are skipped, what the client sent is not trusted.
+        let chained: IpAddr = "10.0.0.2".parse().unwrap();
+        throttle.trusted_proxy_list.push(chained);
+        headers.insert(
+            "x-forwarded-for",
+            "203.0.113.9, 192.0.2.7, 10.0.0.2".parse().unwrap(),
+        );
+        assert_eq!(throttle.client_address(proxy, &headers), client);
+        headers.insert("x-forwarded-for", "192.0.2.7, garbage".parse().unwrap());
+        assert_eq!(throttle.client_address(proxy, &headers), proxy);
+    }
+
+    #[test]
+    fn evicts_entries_past_the_bound() {
+        let mut throttle = Throttle::new();
+
+        // All within the last hour, so pruning forgets none of them.
+        let now = 2 * FORGET_AFTER;
+        for i in 0..MAX_ENTRIES as u64 {
+            throttle.attempts.insert(
+                i.to_string(),
+                Attempt {
+                    failures: 1,
+                    last_failure: FORGET_AFTER + 1 + i * (FORGET_AFTER - 1) / MAX_ENTRIES as u64,
+                    locked_until: 0,
+                },
+            );
+        }
+
+        throttle.fail("alice", now, 1);
+        assert_eq!(throttle.attempts.len(), MAX_ENTRIES - MAX_ENTRIES / 4 + 1);
+        // The entries ending earliest go first.
+        assert!(!throttle.attempts.contains_key("0"));
+        assert!(throttle
+            .attempts
+            .contains_key(&(MAX_ENTRIES - 1).to_string()));
+    }
+}
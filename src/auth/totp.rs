@@ -0,0 +1,206 @@
+use hmac::{Mac, SimpleHmac};
+use rand::{rngs::OsRng, RngCore};
+use sha1::Sha1;
+
+const ISSUER: &str = "MTS";
+const SECRET_LENGTH: usize = 20;
+const STEP: u64 = 30;
+const DIGITS: u32 = 6;
+// Number of steps a code is still accepted for on either side of the current one.
+const SKEW: u64 = 1;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_LENGTH];
+    OsRng.fill_bytes(&mut bytes);
+
+    encode_base32(&bytes)
+}
+
+fn encode_base32(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            output.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        output.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    output
+}
+
+fn decode_base32(s: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in s.bytes().filter(|&c| c != b'=') {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&t| t == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(output)
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|c| match c {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (c as char).to_string()
+            }
+            _ => format!("%{:02X}", c),
+        })
+        .collect()
+}
+
+/// The `otpauth://` URI to be rendered as a QR code by authenticator apps.
+pub fn provisioning_uri(secret: &str, account_name: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        ISSUER,
+        percent_encode(account_name),
+        secret,
+        ISSUER,
+        DIGITS,
+        STEP
+    )
+}
+
+/// HOTP value for a counter as specified by RFC 4226.
+fn code_at(key: &[u8], counter: u64) -> u32 {
+    let mut mac = SimpleHmac::<Sha1>::new_from_slice(key).unwrap();
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+
+    value % 10u32.pow(DIGITS)
+}
+
+/// Checks a code against the steps around `now` and returns the step it
+/// matched. Steps up to and including `last_step` are rejected, so that a
+/// code cannot be replayed.
+pub fn verify(secret: &str, code: &str, now: u64, last_step: Option<u64>) -> Option<u64> {
+    let key = decode_base32(secret)?;
+    let code = code.trim();
+    if code.len() != DIGITS as usize {
+        return None;
+    }
+    let code: u32 = code.parse().ok()?;
+
+    let current_step = now / STEP;
+
+    (current_step.saturating_sub(SKEW)..=current_step + SKEW)
+        .filter(|&step| last_step.is_none_or(|t| step > t))
+        .find(|&step| code_at(&key, step) == code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The SHA-1 seed of RFC 6238, appendix B.
+    const RFC_KEY: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn code_at_matches_rfc_6238_vectors() {
+        // The last six digits of the eight-digit values in the RFC.
+        for (time, code) in [
+            (59, 287082),
+            (1111111109, 81804),
+            (1111111111, 50471),
+            (1234567890, 5924),
+            (2000000000, 279037),
+            (20000000000, 353130),
+        ] {
+            assert_eq!(code_at(RFC_KEY, time / STEP), code, "at {}", time);
+        }
+    }
+
+    #[test]
+    fn verify_accepts_codes_around_now() {
+        let secret = encode_base32(RFC_KEY);
+
+        assert_eq!(verify(&secret, "005924", 1234567890, None), Some(41152263));
+        assert_eq!(
+            verify(&secret, " 005924 ", 1234567890 + STEP, None),
+            Some(41152263)
+        );
+        assert_eq!(verify(&secret, "005924", 1234567890 + 2 * STEP, None), None);
+        assert_eq!(verify(&secret, "5924", 1234567890, None), None);
+        assert_eq!(verify(&secret, "00592a", 1234567890, None), None);
+    }
+
+    #[test]
+    fn verify_rejects_replayed_steps() {
+        let secret = encode_base32(RFC_KEY);
+
+        assert_eq!(verify(&secret, "005924", 1234567890, Some(41152263)), None);
+        assert_eq!(
+            verify(&secret, "005924", 1234567890, Some(41152262)),
+            Some(41152263)
+        );
+    }
+
+    #[test]
+    fn base32_matches_rfc_4648_vectors() {
+        for (bytes, text) in [
+            (&b""[..], ""),
+            (b"f", "MY"),
+            (b"fo", "MZXQ"),
+            (b"foo", "MZXW6"),
+            (b"foob", "MZXW6YQ"),
+            (b"fooba", "MZXW6YTB"),
+            (b"foobar", "MZXW6YTBOI"),
+        ] {
+            assert_eq!(encode_base32(bytes), text);
+            assert_eq!(decode_base32(text).as_deref(), Some(bytes));
+        }
+    }
+
+    #[test]
+    fn base32_decodes_padding_and_lowercase() {
+        assert_eq!(decode_base32("mzxw6yq=").as_deref(), Some(&b"foob"[..]));
+        assert_eq!(
+            decode_base32("MZXW6YTBOI======").as_deref(),
+            Some(&b"foobar"[..])
+        );
+        assert_eq!(decode_base32("MZ1"), None);
+    }
+
+    #[test]
+    fn base32_round_trips() {
+        for length in 0..=SECRET_LENGTH {
+            let bytes = (0..length as u8)
+                .map(|t| t.wrapping_mul(97).wrapping_add(13))
+                .collect::<Vec<_>>();
+            assert_eq!(decode_base32(&encode_base32(&bytes)), Some(bytes));
+        }
+
+        let secret = generate_secret();
+        assert_eq!(secret.len(), 32);
+        assert_eq!(decode_base32(&secret).map(|t| t.len()), Some(SECRET_LENGTH));
+    }
+}
@@ -1,14 +1,16 @@
+mod mutation;
 mod query;
 
+use std::net::SocketAddr;
 use std::sync::Arc;
 
-use axum::extract::{FromRef, Json, State};
+use axum::extract::{ConnectInfo, FromRef, Json, State};
 use axum::http::header::CONTENT_TYPE;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::{routing, Router};
 use juniper::http::{GraphQLRequest, GraphQLResponse};
-use juniper::{EmptyMutation, EmptySubscription, RootNode};
+use juniper::{EmptySubscription, RootNode};
 
 use crate::auth::{AuthRwLock, Claim, OptionalClaim};
 use crate::repo;
@@ -16,6 +18,7 @@ use crate::repo;
 pub struct Context {
     pub repo: repo::Repo,
     pub option_claim: Option<Claim>,
+    pub address: Option<SocketAddr>,
 }
 
 pub fn build_router<S>() -> Router<S>
@@ -32,8 +35,9 @@ impl juniper::Context for Context {}
 
 pub struct QueryRoot;
 
-pub type Schema =
-    Arc<RootNode<'static, QueryRoot, EmptyMutation<Context>, EmptySubscription<Context>>>;
+pub struct MutationRoot;
+
+pub type Schema = Arc<RootNode<'static, QueryRoot, MutationRoot, EmptySubscription<Context>>>;
 
 async fn graphiql() -> impl IntoResponse {
     (
@@ -46,12 +50,14 @@ async fn graphiql() -> impl IntoResponse {
 async fn index(
     State(schema): State<Schema>,
     State(repo): State<repo::Repo>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
     OptionalClaim(option): OptionalClaim,
     data: Json<GraphQLRequest>,
 ) -> Json<GraphQLResponse> {
     let ctx = Context {
         repo: repo.clone(),
         option_claim: option,
+        address: Some(address),
     };
     Json(data.execute(&schema, &ctx).await)
 }
@@ -59,24 +65,7 @@ async fn index(
 pub fn create_schema() -> Schema {
     Arc::new(RootNode::new(
         QueryRoot,
-        EmptyMutation::new(),
+        MutationRoot,
         EmptySubscription::new(),
     ))
 }
-
-#[derive(Debug)]
-struct ServiceError(repo::Error);
-
-impl From<repo::Error> for ServiceError {
-    fn from(error: repo::Error) -> Self {
-        ServiceError(error)
-    }
-}
-
-impl std::fmt::Display for ServiceError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.fmt(f)
-    }
-}
-
-impl std::error::Error for ServiceError {}
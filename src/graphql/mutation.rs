@@ -0,0 +1,203 @@
+use juniper::{FieldResult, GraphQLInputObject, Nullable};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::api::{audit, lock, project};
+use crate::auth::Claim;
+use crate::repo;
+
+use super::{Context, MutationRoot};
+
+fn require_claim(ctx: &Context) -> FieldResult<&Claim> {
+    ctx.option_claim
+        .as_ref()
+        .ok_or_else(|| "No token is set for the request".into())
+}
+
+/// Project metadata to change. Omitted fields are left as they are, and
+/// `null` clears the nullable ones.
+#[derive(GraphQLInputObject)]
+struct ProjectInput {
+    source_language: Nullable<String>,
+    target_language_list: Option<Vec<String>>,
+    description: Option<String>,
+    qa_profile: Nullable<String>,
+    /// A JSON object.
+    settings: Option<String>,
+}
+
+#[juniper::graphql_object(context = Context)]
+impl MutationRoot {
+    fn update_project(ctx: &Context, id: Uuid, input: ProjectInput) -> FieldResult<repo::Project> {
+        let claim = require_claim(ctx)?;
+        claim.require_owner(&ctx.repo, id)?;
+        project::require_writable(&ctx.repo, id)?;
+
+        let settings = match input.settings {
+            Some(settings) => Some(serde_json::from_str(&settings)?),
+            None => None,
+        };
+        let changeset = project::check_changeset(repo::ProjectChangeset {
+            source_language: input.source_language.explicit(),
+            target_language_list: input.target_language_list,
+            description: input.description,
+            qa_profile: input.qa_profile.explicit(),
+            settings,
+        })?;
+        let detail = project::changeset_detail(id, &changeset);
+
+        match ctx.repo.update_project(id, changeset) {
+            Err(repo::Error::Conflict) => {
+                return Err("The project has translations in languages the list leaves out".into());
+            }
+            result => result?,
+        }
+
+        audit::record(
+            &ctx.repo,
+            "project.update",
+            Some(claim.id),
+            ctx.address,
+            detail,
+        );
+
+        Ok(ctx.repo.get_project_by_id(id)?)
+    }
+
+    fn rename_unit(ctx: &Context, id: Uuid, title: String) -> FieldResult<repo::Unit> {
+        let claim = require_claim(ctx)?;
+        let unit = ctx.repo.get_unit_by_id(id)?;
+        claim.require_member(&ctx.repo, unit.project_id)?;
+        project::require_writable(&ctx.repo, unit.project_id)?;
+
+        ctx.repo.update_unit_title(id, title.clone())?;
+
+        audit::record(
+            &ctx.repo,
+            "unit.rename",
+            Some(claim.id),
+            ctx.address,
+            json!({ "unitId": id, "title": title }),
+        );
+
+        Ok(ctx.repo.get_unit_by_id(id)?)
+    }
+
+    /// Orders the units of a project, listing each of them once.
+    fn reorder_unit(
+        ctx: &Context,
+        project_id: Uuid,
+        unit_id_list: Vec<Uuid>,
+    ) -> FieldResult<Vec<repo::Unit>> {
+        let claim = require_claim(ctx)?;
+        claim.require_member(&ctx.repo, project_id)?;
+        project::require_writable(&ctx.repo, project_id)?;
+
+        match ctx.repo.reorder_unit(project_id, unit_id_list) {
+            Err(repo::Error::Conflict) => {
+                return Err("The list does not hold each unit of the project once".into());
+            }
+            result => result?,
+        }
+
+        audit::record(
+            &ctx.repo,
+            "unit.reorder",
+            Some(claim.id),
+            ctx.address,
+            json!({ "projectId": project_id }),
+        );
+
+        Ok(ctx.repo.get_unit_by_project_id(project_id)?)
+    }
+
+    /// Owners of both projects may move units between them.
+    fn move_unit(ctx: &Context, id: Uuid, project_id: Uuid) -> FieldResult<repo::Unit> {
+        let claim = require_claim(ctx)?;
+        let unit = ctx.repo.get_unit_by_id(id)?;
+        claim.require_owner(&ctx.repo, unit.project_id)?;
+        claim.require_owner(&ctx.repo, project_id)?;
+        project::require_writable(&ctx.repo, unit.project_id)?;
+        project::require_writable(&ctx.repo, project_id)?;
+
+        match ctx.repo.move_unit(id, project_id) {
+            Err(repo::Error::Conflict) => {
+                return Err(
+                    "The unit has translations in languages the project does not list".into(),
+                );
+            }
+            result => result?,
+        }
+
+        audit::record(
+            &ctx.repo,
+            "unit.move",
+            Some(claim.id),
+            ctx.address,
+            json!({
+                "unitId": id,
+                "fromProjectId": unit.project_id,
+                "toProjectId": project_id,
+            }),
+        );
+
+        Ok(ctx.repo.get_unit_by_id(id)?)
+    }
+
+    fn delete_unit(ctx: &Context, id: Uuid) -> FieldResult<bool> {
+        let claim = require_claim(ctx)?;
+        let unit = ctx.repo.get_unit_by_id(id)?;
+        claim.require_owner(&ctx.repo, unit.project_id)?;
+        project::require_writable(&ctx.repo, unit.project_id)?;
+
+        ctx.repo.delete_unit(id)?;
+
+        audit::record(
+            &ctx.repo,
+            "unit.delete",
+            Some(claim.id),
+            ctx.address,
+            json!({ "unitId": id, "projectId": unit.project_id, "title": unit.title }),
+        );
+
+        Ok(true)
+    }
+
+    /// Locks the lines from `firstSq` to `lastSq`, or the whole unit, for
+    /// `ttl` seconds.
+    fn acquire_lock(
+        ctx: &Context,
+        unit_id: Uuid,
+        language: String,
+        first_sq: Option<i32>,
+        last_sq: Option<i32>,
+        ttl: Option<i32>,
+    ) -> FieldResult<repo::LineLock> {
+        let claim = require_claim(ctx)?;
+
+        Ok(lock::acquire(
+            &ctx.repo, claim, unit_id, &language, first_sq, last_sq, ttl,
+        )?)
+    }
+
+    fn heartbeat_lock(ctx: &Context, id: Uuid, ttl: Option<i32>) -> FieldResult<repo::LineLock> {
+        let claim = require_claim(ctx)?;
+
+        Ok(lock::heartbeat(&ctx.repo, claim, id, ttl)?)
+    }
+
+    fn release_lock(ctx: &Context, id: Uuid) -> FieldResult<bool> {
+        let claim = require_claim(ctx)?;
+        lock::release(&ctx.repo, claim, id)?;
+
+        Ok(true)
+    }
+
+    /// Removes the lock of another editor. Admins only.
+    fn break_lock(ctx: &Context, id: Uuid) -> FieldResult<bool> {
+        let claim = require_claim(ctx)?;
+        lock::force_release(&ctx.repo, claim, ctx.address, id)?;
+
+        Ok(true)
+    }
+}
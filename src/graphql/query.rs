@@ -2,10 +2,34 @@ use chrono::{DateTime, Utc};
 use juniper::FieldResult;
 use uuid::Uuid;
 
+use crate::api::language;
+use crate::api::progress::{self, Progress};
+use crate::api::source_meta::{self, SourceMeta};
 use crate::repo;
 
 use super::{Context, QueryRoot};
 
+const USER_LIST_LIMIT: i32 = 100;
+
+#[juniper::graphql_object(context = Context)]
+impl repo::User {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn is_admin(&self) -> bool {
+        self.is_admin
+    }
+
+    fn is_active(&self) -> bool {
+        self.is_active
+    }
+}
+
 #[juniper::graphql_object(context = Context)]
 impl repo::Project {
     fn id(&self) -> Uuid {
@@ -16,9 +40,121 @@ impl repo::Project {
         &self.name
     }
 
+    fn is_archived(&self) -> bool {
+        self.is_archived
+    }
+
+    /// BCP 47 tag of the source text.
+    fn source_language(&self) -> Option<&str> {
+        self.source_language.as_deref()
+    }
+
+    /// BCP 47 tags of the languages translated into.
+    fn target_language_list(&self) -> &[String] {
+        &self.target_language_list
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn qa_profile(&self) -> Option<&str> {
+        self.qa_profile.as_deref()
+    }
+
+    /// Client specific settings as a JSON object.
+    fn settings(&self) -> String {
+        self.settings.to_string()
+    }
+
     fn unit_list(&self, ctx: &Context) -> FieldResult<Vec<repo::Unit>> {
         Ok(ctx.repo.get_unit_by_project_id(self.id)?)
     }
+
+    /// The current source lines of every unit, filtered like those of a unit.
+    fn source_list(
+        &self,
+        ctx: &Context,
+        speaker: Option<String>,
+        meta: Option<String>,
+    ) -> FieldResult<Vec<repo::Source>> {
+        Ok(ctx.repo.get_source(repo::SourceFilter {
+            project_id: Some(self.id),
+            meta: source_meta::build_filter(meta.as_deref(), speaker)?,
+            ..Default::default()
+        })?)
+    }
+
+    /// The folders at the project root.
+    fn folder_list(&self, ctx: &Context) -> FieldResult<Vec<repo::Folder>> {
+        Ok(ctx
+            .repo
+            .get_folder_by_project_id(self.id)?
+            .into_iter()
+            .filter(|t| t.parent_id.is_none())
+            .collect::<Vec<_>>())
+    }
+
+    fn progress(&self, ctx: &Context) -> FieldResult<Progress> {
+        let folder_list = ctx.repo.get_folder_by_project_id(self.id)?;
+        Ok(progress::get_project_progress(&ctx.repo, self.id, &folder_list)?.total)
+    }
+}
+
+#[juniper::graphql_object(context = Context)]
+impl repo::Folder {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn project_id(&self) -> Uuid {
+        self.project_id
+    }
+
+    fn parent_id(&self) -> Option<Uuid> {
+        self.parent_id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Place of the folder among its siblings.
+    fn position(&self) -> i32 {
+        self.position
+    }
+
+    fn project(&self, ctx: &Context) -> FieldResult<repo::Project> {
+        Ok(ctx.repo.get_project_by_id(self.project_id)?)
+    }
+
+    fn parent(&self, ctx: &Context) -> FieldResult<Option<repo::Folder>> {
+        match self.parent_id {
+            Some(parent_id) => Ok(Some(ctx.repo.get_folder_by_id(parent_id)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The folders directly inside this one.
+    fn children(&self, ctx: &Context) -> FieldResult<Vec<repo::Folder>> {
+        Ok(ctx.repo.get_folder_by_parent_id(self.id)?)
+    }
+
+    /// The units directly inside this folder.
+    fn unit_list(&self, ctx: &Context) -> FieldResult<Vec<repo::Unit>> {
+        Ok(ctx.repo.get_unit_by_folder_id(self.id)?)
+    }
+
+    /// Counts over the units inside this folder at any depth.
+    fn progress(&self, ctx: &Context) -> FieldResult<Progress> {
+        let folder_list = ctx.repo.get_folder_by_project_id(self.project_id)?;
+        Ok(
+            progress::get_project_progress(&ctx.repo, self.project_id, &folder_list)?
+                .folder_map
+                .remove(&self.id)
+                .unwrap_or_default(),
+        )
+    }
 }
 
 #[juniper::graphql_object(context = Context)]
@@ -35,30 +171,264 @@ impl repo::Unit {
         &self.title
     }
 
-    fn latest_commit_id(&self) -> Option<Uuid> {
-        self.commit_id
+    fn source_version(&self) -> i32 {
+        self.source_version
+    }
+
+    /// Place of the unit in its project.
+    fn position(&self) -> i32 {
+        self.position
+    }
+
+    /// The folder holding the unit, the project root if null.
+    fn folder_id(&self) -> Option<Uuid> {
+        self.folder_id
+    }
+
+    fn progress(&self, ctx: &Context) -> FieldResult<Progress> {
+        Ok(progress::get_unit_progress(&ctx.repo, self.id)?)
+    }
+
+    fn folder(&self, ctx: &Context) -> FieldResult<Option<repo::Folder>> {
+        match self.folder_id {
+            Some(folder_id) => Ok(Some(ctx.repo.get_folder_by_id(folder_id)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The latest commit in `language`.
+    fn latest_commit_id(&self, ctx: &Context, language: String) -> FieldResult<Option<Uuid>> {
+        match ctx
+            .repo
+            .get_unit_head(self.id, language::normalize_filter(language))
+        {
+            Ok(head) => Ok(Some(head.commit_id)),
+            Err(repo::Error::NotFound) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// The latest commit in each language translated into so far.
+    fn head_list(&self, ctx: &Context) -> FieldResult<Vec<repo::UnitHead>> {
+        Ok(ctx.repo.get_unit_head_by_unit_id(self.id)?)
     }
 
     fn project(&self, ctx: &Context) -> FieldResult<repo::Project> {
         Ok(ctx.repo.get_project_by_id(self.project_id)?)
     }
 
-    fn commit_list(&self, ctx: &Context) -> FieldResult<Vec<repo::Commit>> {
-        Ok(ctx.repo.get_commit_by_unit_id(self.id)?)
+    fn commit_list(
+        &self,
+        ctx: &Context,
+        language: Option<String>,
+    ) -> FieldResult<Vec<repo::Commit>> {
+        Ok(ctx
+            .repo
+            .get_commit_by_unit_id(self.id, language.map(language::normalize_filter))?)
     }
 
-    fn source_list(&self, ctx: &Context) -> FieldResult<Vec<repo::Source>> {
-        Ok(ctx.repo.get_source_by_unit_id(self.id)?)
+    /// The current source list unless a `version` is given. Lines may be
+    /// filtered by `speaker`, and by `meta`, a JSON object their meta must
+    /// contain.
+    fn source_list(
+        &self,
+        ctx: &Context,
+        version: Option<i32>,
+        speaker: Option<String>,
+        meta: Option<String>,
+    ) -> FieldResult<Vec<repo::Source>> {
+        Ok(ctx.repo.get_source(repo::SourceFilter {
+            unit_id: Some(self.id),
+            version: Some(version.unwrap_or(self.source_version)),
+            meta: source_meta::build_filter(meta.as_deref(), speaker)?,
+            ..Default::default()
+        })?)
     }
 
-    fn latest_commit(&self, ctx: &Context) -> FieldResult<Option<repo::Commit>> {
-        match self.commit_id {
-            Some(id) => Ok(Some(ctx.repo.get_commit_by_id(id)?)),
-            None => Ok(None),
+    /// Unexpired locks, showing who is editing the unit, in every language
+    /// unless one is given.
+    fn lock_list(
+        &self,
+        ctx: &Context,
+        language: Option<String>,
+    ) -> FieldResult<Vec<repo::LineLock>> {
+        Ok(ctx
+            .repo
+            .get_line_lock_by_unit_id(self.id, language.map(language::normalize_filter))?)
+    }
+
+    /// Machine translations of the current source lines not yet accepted,
+    /// in every language unless one is given.
+    fn suggestion_list(
+        &self,
+        ctx: &Context,
+        language: Option<String>,
+    ) -> FieldResult<Vec<repo::Suggestion>> {
+        Ok(ctx
+            .repo
+            .get_suggestion_by_unit_id(self.id, language.map(language::normalize_filter))?)
+    }
+
+    fn latest_commit(&self, ctx: &Context, language: String) -> FieldResult<Option<repo::Commit>> {
+        match ctx
+            .repo
+            .get_unit_head(self.id, language::normalize_filter(language))
+        {
+            Ok(head) => Ok(Some(ctx.repo.get_commit_by_id(head.commit_id)?)),
+            Err(repo::Error::NotFound) => Ok(None),
+            Err(error) => Err(error.into()),
         }
     }
 }
 
+#[juniper::graphql_object(context = Context)]
+impl repo::Source {
+    fn unit_id(&self) -> Uuid {
+        self.unit_id
+    }
+
+    fn sq(&self) -> i32 {
+        self.sq
+    }
+
+    fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// The meta as a JSON object, with client specific keys.
+    fn meta(&self) -> String {
+        self.meta.to_string()
+    }
+
+    fn speaker(&self) -> Option<String> {
+        SourceMeta::parse(&self.meta).speaker
+    }
+
+    fn context(&self) -> Option<String> {
+        SourceMeta::parse(&self.meta).context
+    }
+
+    /// The most characters a translation may have.
+    fn max_length(&self) -> Option<i32> {
+        SourceMeta::parse(&self.meta)
+            .max_length
+            .map(|t| t.min(i32::MAX as u32) as i32)
+    }
+
+    /// The most bytes a translation may have in `encoding`.
+    fn max_byte_length(&self) -> Option<i32> {
+        SourceMeta::parse(&self.meta)
+            .max_byte_length
+            .map(|t| t.min(i32::MAX as u32) as i32)
+    }
+
+    fn encoding(&self) -> Option<String> {
+        SourceMeta::parse(&self.meta).encoding
+    }
+
+    /// The most lines a translation may have.
+    fn max_line_count(&self) -> Option<i32> {
+        SourceMeta::parse(&self.meta)
+            .max_line_count
+            .map(|t| t.min(i32::MAX as u32) as i32)
+    }
+
+    fn screenshot(&self) -> Option<String> {
+        SourceMeta::parse(&self.meta).screenshot
+    }
+
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+#[juniper::graphql_object(context = Context)]
+impl repo::LineLock {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn unit_id(&self) -> Uuid {
+        self.unit_id
+    }
+
+    fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Null on locks of the whole unit.
+    fn first_sq(&self) -> Option<i32> {
+        self.first_sq
+    }
+
+    fn last_sq(&self) -> Option<i32> {
+        self.last_sq
+    }
+
+    fn holder_id(&self) -> Uuid {
+        self.holder_id
+    }
+
+    fn holder(&self, ctx: &Context) -> FieldResult<repo::User> {
+        Ok(ctx.repo.get_user_by_id(self.holder_id)?)
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at.and_utc()
+    }
+
+    fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at.and_utc()
+    }
+}
+
+#[juniper::graphql_object(context = Context)]
+impl repo::Suggestion {
+    fn unit_id(&self) -> Uuid {
+        self.unit_id
+    }
+
+    fn source_version(&self) -> i32 {
+        self.source_version
+    }
+
+    fn sq(&self) -> i32 {
+        self.sq
+    }
+
+    fn language(&self) -> &str {
+        &self.language
+    }
+
+    fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// The machine translation provider that made the suggestion.
+    fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at.and_utc()
+    }
+}
+
+#[juniper::graphql_object(context = Context)]
+impl repo::UnitHead {
+    fn language(&self) -> &str {
+        &self.language
+    }
+
+    fn commit_id(&self) -> Uuid {
+        self.commit_id
+    }
+
+    fn commit(&self, ctx: &Context) -> FieldResult<repo::Commit> {
+        Ok(ctx.repo.get_commit_by_id(self.commit_id)?)
+    }
+}
+
 #[juniper::graphql_object(context = Context)]
 impl repo::Commit {
     fn id(&self) -> Uuid {
@@ -77,6 +447,31 @@ impl repo::Commit {
         self.editor_id
     }
 
+    fn language(&self) -> &str {
+        &self.language
+    }
+
+    fn source_version(&self) -> i32 {
+        self.source_version
+    }
+
+    /// The commit whose translations this one copied to repetitions of
+    /// their source lines.
+    fn propagated_from_id(&self) -> Option<Uuid> {
+        self.propagated_from_id
+    }
+
+    /// The source list the records were written against.
+    fn source_list(&self, ctx: &Context) -> FieldResult<Vec<repo::Source>> {
+        Ok(ctx
+            .repo
+            .get_source_by_unit_id_version(self.unit_id, self.source_version)?)
+    }
+
+    fn editor(&self, ctx: &Context) -> FieldResult<repo::User> {
+        Ok(ctx.repo.get_user_by_id(self.editor_id)?)
+    }
+
     fn unit(&self, ctx: &Context) -> FieldResult<repo::Unit> {
         Ok(ctx.repo.get_unit_by_id(self.unit_id)?)
     }
@@ -88,14 +483,22 @@ impl repo::Commit {
 
 #[juniper::graphql_object(context = Context)]
 impl QueryRoot {
-    fn project_list(ctx: &Context) -> FieldResult<Vec<repo::Project>> {
-        Ok(ctx.repo.get_project()?)
+    /// Archived projects are left out unless `includeArchived` is set.
+    fn project_list(
+        ctx: &Context,
+        include_archived: Option<bool>,
+    ) -> FieldResult<Vec<repo::Project>> {
+        Ok(ctx.repo.get_project(include_archived.unwrap_or(false))?)
     }
 
     fn project(ctx: &Context, id: Uuid) -> FieldResult<repo::Project> {
         Ok(ctx.repo.get_project_by_id(id)?)
     }
 
+    fn folder(ctx: &Context, id: Uuid) -> FieldResult<repo::Folder> {
+        Ok(ctx.repo.get_folder_by_id(id)?)
+    }
+
     fn unit(ctx: &Context, id: Uuid) -> FieldResult<repo::Unit> {
         Ok(ctx.repo.get_unit_by_id(id)?)
     }
@@ -103,4 +506,28 @@ impl QueryRoot {
     fn commit(ctx: &Context, id: Uuid) -> FieldResult<repo::Commit> {
         Ok(ctx.repo.get_commit_by_id(id)?)
     }
+
+    fn user(ctx: &Context, id: Uuid) -> FieldResult<repo::User> {
+        Ok(ctx.repo.get_user_by_id(id)?)
+    }
+
+    fn user_list(
+        ctx: &Context,
+        search: Option<String>,
+        offset: Option<i32>,
+        limit: Option<i32>,
+    ) -> FieldResult<Vec<repo::User>> {
+        if !ctx.option_claim.as_ref().is_some_and(|t| t.is_admin) {
+            return Err("You don't have the appropriate permission for the request".into());
+        }
+
+        Ok(ctx.repo.get_user(
+            search,
+            offset.unwrap_or(0).max(0).into(),
+            limit
+                .unwrap_or(USER_LIST_LIMIT)
+                .clamp(0, USER_LIST_LIMIT)
+                .into(),
+        )?)
+    }
 }
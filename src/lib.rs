@@ -0,0 +1,106 @@
+pub mod api;
+pub mod auth;
+pub mod graphql;
+pub mod mt;
+pub mod repo;
+pub mod schema;
+
+use auth::backend::AuthBackends;
+use auth::oidc::Oidc;
+use auth::{AuthRwLock, ThrottleMutex};
+use axum::{extract::FromRef, http::Method};
+use diesel::{r2d2::ConnectionManager, PgConnection};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use graphql::Schema;
+use mt::MachineTranslation;
+use tower_http::cors::{AllowCredentials, AllowHeaders, AllowOrigin, CorsLayer};
+use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
+use tracing::Level;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub repo: repo::Repo,
+    pub auth: AuthRwLock,
+    pub backends: AuthBackends,
+    pub throttle: ThrottleMutex,
+    pub oidc: Oidc,
+    pub mt: MachineTranslation,
+    pub schema: Schema,
+}
+
+impl FromRef<AppState> for repo::Repo {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.repo.clone()
+    }
+}
+
+impl FromRef<AppState> for AuthRwLock {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.auth.clone()
+    }
+}
+
+impl FromRef<AppState> for AuthBackends {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.backends.clone()
+    }
+}
+
+impl FromRef<AppState> for ThrottleMutex {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.throttle.clone()
+    }
+}
+
+impl FromRef<AppState> for Oidc {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.oidc.clone()
+    }
+}
+
+impl FromRef<AppState> for MachineTranslation {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.mt.clone()
+    }
+}
+
+impl FromRef<AppState> for Schema {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.schema.clone()
+    }
+}
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations");
+
+pub fn run_migrations(
+    connection: &mut impl MigrationHarness<diesel::pg::Pg>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    connection.run_pending_migrations(MIGRATIONS)?;
+
+    Ok(())
+}
+
+pub type ConnectionPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+/// The routes of the service, without the listener.
+pub fn build_app(app_state: AppState) -> axum::Router {
+    axum::Router::new()
+        .nest(
+            "/api",
+            api::build_router().nest("/auth", auth::service::build_router()),
+        )
+        .nest("/graphql", graphql::build_router())
+        .with_state(app_state)
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        .layer(
+            CorsLayer::new()
+                .allow_methods([Method::GET, Method::POST])
+                .allow_credentials(AllowCredentials::yes())
+                .allow_headers(AllowHeaders::mirror_request())
+                .allow_origin(AllowOrigin::mirror_request()),
+        )
+}
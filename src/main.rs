@@ -1,57 +1,12 @@
-mod api;
-mod auth;
-mod graphql;
-mod repo;
-mod schema;
-
 use std::env;
+use std::net::SocketAddr;
 
-use auth::AuthRwLock;
-use axum::{extract::FromRef, http::Method};
 use diesel::{r2d2::ConnectionManager, PgConnection};
-use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
-use graphql::Schema;
-use tower_http;
-use tower_http::cors::{AllowCredentials, AllowHeaders, AllowOrigin, CorsLayer};
-use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
-use tracing::Level;
-
-#[derive(Clone)]
-struct AppState {
-    repo: repo::Repo,
-    auth: AuthRwLock,
-    schema: Schema,
-}
-
-impl FromRef<AppState> for repo::Repo {
-    fn from_ref(app_state: &AppState) -> Self {
-        app_state.repo.clone()
-    }
-}
-
-impl FromRef<AppState> for AuthRwLock {
-    fn from_ref(app_state: &AppState) -> Self {
-        app_state.auth.clone()
-    }
-}
-
-impl FromRef<AppState> for Schema {
-    fn from_ref(app_state: &AppState) -> Self {
-        app_state.schema.clone()
-    }
-}
-
-pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations");
-
-fn run_migrations(
-    connection: &mut impl MigrationHarness<diesel::pg::Pg>,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
-    connection.run_pending_migrations(MIGRATIONS)?;
-
-    Ok(())
-}
-
-pub type ConnectionPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+use mts_server::auth::backend::AuthBackends;
+use mts_server::auth::oidc::Oidc;
+use mts_server::auth::{AuthRwLock, ThrottleMutex};
+use mts_server::mt::MachineTranslation;
+use mts_server::{auth, build_app, graphql, repo, run_migrations, AppState};
 
 #[tokio::main]
 async fn main() {
@@ -69,6 +24,7 @@ async fn main() {
     let host = env::var("HOST").unwrap_or(String::from("0.0.0.0"));
     let port = env::var("PORT").unwrap_or(String::from("8000"));
     let listen_addr = format!("{}:{}", host, port);
+    let require_admin_totp = env::var("REQUIRE_ADMIN_TOTP").is_ok_and(|t| t == "true" || t == "1");
 
     let manager = ConnectionManager::<PgConnection>::new(database_url);
     let pool = r2d2::Pool::builder().build(manager).unwrap();
@@ -77,32 +33,24 @@ async fn main() {
 
     let app_state = AppState {
         repo: repo::Repo::new(pool),
-        auth: AuthRwLock::new(),
+        auth: AuthRwLock::new(require_admin_totp),
+        backends: AuthBackends::from_env(),
+        throttle: ThrottleMutex::from_env(),
+        oidc: Oidc::from_env(),
+        mt: MachineTranslation::from_env(),
         schema: graphql::create_schema(),
     };
 
-    let _ = auth::service::create_user(app_state.repo.clone(), "admin", &admin_pass, true);
+    auth::service::create_initial_admin(app_state.repo.clone(), "admin", &admin_pass)
+        .expect("create admin, INIT_PASS must meet the password policy");
 
-    let app = axum::Router::new()
-        .nest(
-            "/api",
-            api::build_router().nest("/auth", auth::service::build_router()),
-        )
-        .nest("/graphql", graphql::build_router())
-        .with_state(app_state)
-        .layer(
-            TraceLayer::new_for_http()
-                .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
-                .on_response(DefaultOnResponse::new().level(Level::INFO)),
-        )
-        .layer(
-            CorsLayer::new()
-                .allow_methods([Method::GET, Method::POST])
-                .allow_credentials(AllowCredentials::yes())
-                .allow_headers(AllowHeaders::mirror_request())
-                .allow_origin(AllowOrigin::mirror_request()),
-        );
+    let app = build_app(app_state);
     let listener = tokio::net::TcpListener::bind(listen_addr).await.unwrap();
 
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
@@ -0,0 +1,165 @@
+use std::env;
+use std::time::Duration;
+
+use axum::async_trait;
+use axum::http::StatusCode;
+use serde_json::{Map, Value};
+
+use crate::api::ServiceError;
+
+use super::Provider;
+
+pub const HTTP_PROVIDER: &str = "http";
+
+/// Calls a JSON translation API. Requests are objects holding a list of
+/// texts and the languages under configurable keys, and the translations
+/// are found in the response by JSON pointers.
+pub struct HttpProvider {
+    url: String,
+    /// Sent as a bearer token if set.
+    pub api_key: Option<String>,
+    pub text_key: String,
+    pub source_key: String,
+    pub target_key: String,
+    /// Points to the list of translations in the response.
+    pub response_pointer: String,
+    /// Points to the text in each item of the list, the item itself if not
+    /// set.
+    pub text_pointer: Option<String>,
+    /// The most texts sent in one request.
+    pub batch_size: usize,
+    client: reqwest::Client,
+}
+
+impl HttpProvider {
+    pub fn new(url: &str) -> Self {
+        HttpProvider {
+            url: url.into(),
+            api_key: None,
+            text_key: String::from("text"),
+            source_key: String::from("source"),
+            target_key: String::from("target"),
+            response_pointer: String::from("/translations"),
+            text_pointer: None,
+            batch_size: 50,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .expect("build HTTP client"),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let mut provider = HttpProvider::new(&env::var("MT_URL").expect("MT_URL"));
+        provider.api_key = env::var("MT_API_KEY").ok();
+        if let Ok(text_key) = env::var("MT_TEXT_KEY") {
+            provider.text_key = text_key;
+        }
+        if let Ok(source_key) = env::var("MT_SOURCE_KEY") {
+            provider.source_key = source_key;
+        }
+        if let Ok(target_key) = env::var("MT_TARGET_KEY") {
+            provider.target_key = target_key;
+        }
+        if let Ok(response_pointer) = env::var("MT_RESPONSE_POINTER") {
+            provider.response_pointer = response_pointer;
+        }
+        provider.text_pointer = env::var("MT_TEXT_POINTER").ok();
+        if let Some(batch_size) = env::var("MT_BATCH_SIZE")
+            .ok()
+            .and_then(|t| t.parse::<usize>().ok())
+            .filter(|&t| t > 0)
+        {
+            provider.batch_size = batch_size;
+        }
+        provider
+    }
+
+    async fn translate_batch(
+        &self,
+        source_language: Option<&str>,
+        target_language: &str,
+        text_list: &[String],
+    ) -> Result<Vec<String>, ServiceError> {
+        let mut body = Map::new();
+        body.insert(self.text_key.clone(), Value::from(text_list));
+        if let Some(source_language) = source_language {
+            body.insert(self.source_key.clone(), Value::from(source_language));
+        }
+        body.insert(self.target_key.clone(), Value::from(target_language));
+
+        let mut request = self.client.post(&self.url).json(&body);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request
+            .send()
+            .await
+            .and_then(|t| t.error_for_status())
+            .map_err(unavailable)?
+            .json::<Value>()
+            .await
+            .map_err(invalid_response)?;
+
+        let item_list = response
+            .pointer(&self.response_pointer)
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid_response(()))?;
+        if item_list.len() != text_list.len() {
+            return Err(invalid_response(()));
+        }
+
+        item_list
+            .iter()
+            .map(|item| {
+                let text = match &self.text_pointer {
+                    Some(pointer) => item.pointer(pointer),
+                    None => Some(item),
+                };
+                text.and_then(Value::as_str)
+                    .map(String::from)
+                    .ok_or_else(|| invalid_response(()))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    }
+}
+
+fn unavailable<T>(_: T) -> ServiceError {
+    (
+        StatusCode::BAD_GATEWAY,
+        "The translation service is unavailable",
+    )
+        .into()
+}
+
+fn invalid_response<T>(_: T) -> ServiceError {
+    (
+        StatusCode::BAD_GATEWAY,
+        "The translation service gave an invalid response",
+    )
+        .into()
+}
+
+#[async_trait]
+impl Provider for HttpProvider {
+    fn name(&self) -> &str {
+        HTTP_PROVIDER
+    }
+
+    async fn translate(
+        &self,
+        source_language: Option<&str>,
+        target_language: &str,
+        text_list: &[String],
+    ) -> Result<Vec<String>, ServiceError> {
+        let mut translation_list = Vec::with_capacity(text_list.len());
+        for batch in text_list.chunks(self.batch_size.max(1)) {
+            translation_list.extend(
+                self.translate_batch(source_language, target_language, batch)
+                    .await?,
+            );
+        }
+
+        Ok(translation_list)
+    }
+}
@@ -0,0 +1,47 @@
+pub mod http;
+
+use std::env;
+use std::sync::Arc;
+
+use axum::async_trait;
+
+use crate::api::ServiceError;
+
+use http::{HttpProvider, HTTP_PROVIDER};
+
+/// A machine translation service filling in suggestions for untranslated
+/// lines.
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// Recorded on each suggestion made.
+    fn name(&self) -> &str;
+
+    /// Translates each text, returning the translations in the same order.
+    /// The service guesses the source language if none is given.
+    async fn translate(
+        &self,
+        source_language: Option<&str>,
+        target_language: &str,
+        text_list: &[String],
+    ) -> Result<Vec<String>, ServiceError>;
+}
+
+/// The configured provider, if any.
+#[derive(Clone)]
+pub struct MachineTranslation(pub Option<Arc<dyn Provider>>);
+
+impl MachineTranslation {
+    /// Reads `MT_PROVIDER`, which defaults to `http` when `MT_URL` is set.
+    pub fn from_env() -> Self {
+        let name = match env::var("MT_PROVIDER") {
+            Ok(name) => name,
+            Err(_) if env::var("MT_URL").is_ok() => String::from(HTTP_PROVIDER),
+            Err(_) => return MachineTranslation(None),
+        };
+
+        MachineTranslation(Some(match name.trim() {
+            HTTP_PROVIDER => Arc::new(HttpProvider::from_env()),
+            name => panic!("Unknown machine translation provider {}", name),
+        }))
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::{HashMap, HashSet};
+
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
 use diesel::result::DatabaseErrorKind;
@@ -9,12 +11,17 @@ use crate::schema;
 
 type ConnectionPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 
+/// Deltas following each other before a commit stores every record again.
+/// Matches the migration storing existing commits as deltas.
+const MAX_DELTA_DEPTH: i32 = 32;
+
 #[derive(Clone)]
 pub struct Repo {
     pool: ConnectionPool,
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     NotFound,
     NotUnique {
@@ -28,6 +35,8 @@ pub enum Error {
         constraint_name: Option<String>,
     },
     DataError,
+    /// The entity is not in a state the operation can be applied to.
+    Conflict,
     ConnectionError(r2d2::Error),
     DieselError(diesel::result::Error),
 }
@@ -90,6 +99,7 @@ impl std::fmt::Display for Error {
                 column_name.clone().unwrap_or("<?>".to_string())
             ),
             Error::DataError => write!(f, "Data value is invalid"),
+            Error::Conflict => write!(f, "The entity conflicts with the requested change"),
             Error::ConnectionError(_) => write!(f, "Failed to connect to database"),
             Error::DieselError(_) => write!(f, "Database operation error"),
         }
@@ -99,12 +109,65 @@ impl std::fmt::Display for Error {
 impl std::error::Error for Error {}
 
 #[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::audit_event)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub kind: String,
+    pub actor_id: Option<Uuid>,
+    pub ip: Option<String>,
+    pub detail: serde_json::Value,
+}
+
+#[derive(Default)]
+pub struct AuditEventFilter {
+    pub kind: Option<String>,
+    pub actor_id: Option<Uuid>,
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+}
+
+#[derive(Clone, Queryable, Selectable, Insertable)]
 #[diesel(table_name = schema::user)]
 pub struct User {
     pub id: Uuid,
     pub name: String,
     pub hash: String,
     pub is_admin: bool,
+    pub is_active: bool,
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
+    pub totp_last_step: Option<i64>,
+    pub oidc_subject: Option<String>,
+    pub backend: String,
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::recovery_code)]
+pub struct RecoveryCode {
+    pub hash: String,
+    pub user_id: Uuid,
+    pub used: bool,
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::session)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub revoked: bool,
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::password_reset)]
+pub struct PasswordReset {
+    pub hash: String,
+    pub user_id: Uuid,
+    pub created_by: Uuid,
+    pub expires_at: NaiveDateTime,
+    pub used: bool,
 }
 
 #[derive(Queryable, Selectable, Insertable)]
@@ -112,6 +175,63 @@ pub struct User {
 pub struct Project {
     pub id: Uuid,
     pub name: String,
+    pub is_archived: bool,
+    pub source_language: Option<String>,
+    pub target_language_list: Vec<String>,
+    pub description: String,
+    pub qa_profile: Option<String>,
+    pub settings: serde_json::Value,
+}
+
+/// Metadata fields to change, `None` leaves a field as it is.
+#[derive(Default, AsChangeset)]
+#[diesel(table_name = schema::project)]
+pub struct ProjectChangeset {
+    pub source_language: Option<Option<String>>,
+    pub target_language_list: Option<Vec<String>>,
+    pub description: Option<String>,
+    pub qa_profile: Option<Option<String>>,
+    pub settings: Option<serde_json::Value>,
+}
+
+impl ProjectChangeset {
+    pub fn is_empty(&self) -> bool {
+        self.source_language.is_none()
+            && self.target_language_list.is_none()
+            && self.description.is_none()
+            && self.qa_profile.is_none()
+            && self.settings.is_none()
+    }
+}
+
+pub struct ProjectDeletion {
+    pub unit_count: i64,
+    pub commit_count: i64,
+}
+
+pub const ROLE_OWNER: &str = "owner";
+pub const ROLE_TRANSLATOR: &str = "translator";
+pub const ROLE_REVIEWER: &str = "reviewer";
+pub const ROLE_LIST: [&str; 3] = [ROLE_OWNER, ROLE_TRANSLATOR, ROLE_REVIEWER];
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::project_member)]
+pub struct ProjectMember {
+    pub project_id: Uuid,
+    pub user_id: Uuid,
+    pub role: String,
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::invitation)]
+pub struct Invitation {
+    pub hash: String,
+    pub created_by: Uuid,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub project_id: Option<Uuid>,
+    pub role: Option<String>,
+    pub used_by: Option<Uuid>,
 }
 
 #[derive(Queryable, Selectable, Insertable)]
@@ -120,33 +240,237 @@ pub struct Unit {
     pub id: Uuid,
     pub project_id: Uuid,
     pub title: String,
-    pub commit_id: Option<Uuid>,
+    pub source_version: i32,
+    pub position: i32,
+    pub folder_id: Option<Uuid>,
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::folder)]
+pub struct Folder {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    /// The folder holding this one, the project root if `None`.
+    pub parent_id: Option<Uuid>,
+    pub name: String,
+    pub position: i32,
+}
+
+/// Counts over the current source of a unit.
+#[derive(QueryableByName)]
+pub struct UnitSourceCount {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub unit_id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub project_id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Uuid>)]
+    pub folder_id: Option<Uuid>,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub line_count: i64,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub word_count: i64,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub character_count: i64,
+}
+
+/// Counts over the latest commit of a unit in one language. Word and
+/// character counts are of the source lines translated.
+#[derive(QueryableByName)]
+pub struct UnitLanguageCount {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub unit_id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Varchar)]
+    pub language: String,
+    #[diesel(sql_type = diesel::sql_types::Timestamp)]
+    pub last_activity_at: NaiveDateTime,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub translated_count: i64,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub translated_word_count: i64,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub translated_character_count: i64,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub reviewed_count: i64,
 }
 
+pub struct UnitProgress {
+    pub source_count: UnitSourceCount,
+    pub language_count_list: Vec<UnitLanguageCount>,
+    /// Editors of any commit on the unit.
+    pub contributor_id_list: Vec<Uuid>,
+}
+
+/// The latest commit of a unit in one target language.
 #[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::unit_head)]
+pub struct UnitHead {
+    pub unit_id: Uuid,
+    pub language: String,
+    pub commit_id: Uuid,
+}
+
+#[derive(Queryable, QueryableByName, Selectable, Insertable)]
 #[diesel(table_name = schema::commit)]
 pub struct Commit {
     pub id: Uuid,
     pub unit_id: Uuid,
     pub created_at: NaiveDateTime,
     pub editor_id: Uuid,
+    pub language: String,
+    pub source_version: i32,
+    /// The commit whose translations this one copied to repetitions of their
+    /// source lines.
+    pub propagated_from_id: Option<Uuid>,
+}
+
+/// A commit with the one before it on the same unit in the same language.
+#[derive(QueryableByName)]
+pub struct CommitWithParent {
+    #[diesel(embed)]
+    pub commit: Commit,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Uuid>)]
+    pub parent_id: Option<Uuid>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Integer>)]
+    pub parent_source_version: Option<i32>,
 }
 
-#[derive(Queryable, Selectable, Insertable, GraphQLObject)]
+#[derive(Queryable, Selectable, Insertable)]
 #[diesel(table_name = schema::source)]
 pub struct Source {
     pub unit_id: Uuid,
     pub sq: i32,
     pub content: String,
-    pub meta: String,
+    /// A JSON object, see `api::source_meta::SourceMeta`.
+    pub meta: serde_json::Value,
+    pub version: i32,
+}
+
+#[derive(Default)]
+pub struct SourceFilter {
+    pub project_id: Option<Uuid>,
+    pub unit_id: Option<Uuid>,
+    /// The current version of each unit if not given.
+    pub version: Option<i32>,
+    /// A JSON object the meta of the lines must contain.
+    pub meta: Option<serde_json::Value>,
 }
 
-#[derive(Queryable, Selectable, Insertable, GraphQLObject)]
+#[derive(Clone, Queryable, QueryableByName, Selectable, Insertable, GraphQLObject)]
 #[diesel(table_name = schema::record)]
 pub struct Record {
     pub commit_id: Uuid,
     pub sq: i32,
     pub content: String,
+    pub needs_retranslation: bool,
+    pub is_reviewed: bool,
+}
+
+/// A machine translation of a source line, not yet accepted into a commit.
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::suggestion)]
+pub struct Suggestion {
+    pub unit_id: Uuid,
+    pub source_version: i32,
+    pub sq: i32,
+    pub language: String,
+    pub content: String,
+    pub provider: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// A soft lock on the lines from `first_sq` to `last_sq` of a unit in a
+/// language, or on every line if they are not set.
+#[derive(Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::line_lock)]
+pub struct LineLock {
+    pub id: Uuid,
+    pub unit_id: Uuid,
+    pub language: String,
+    pub first_sq: Option<i32>,
+    pub last_sq: Option<i32>,
+    pub holder_id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+impl LineLock {
+    pub fn covers(&self, sq: i32) -> bool {
+        match (self.first_sq, self.last_sq) {
+            (Some(first_sq), Some(last_sq)) => first_sq <= sq && sq <= last_sq,
+            _ => true,
+        }
+    }
+
+    pub fn overlaps(&self, other: &LineLock) -> bool {
+        match (other.first_sq, other.last_sq) {
+            (Some(first_sq), Some(last_sq)) => match (self.first_sq, self.last_sq) {
+                (Some(self_first_sq), Some(self_last_sq)) => {
+                    self_first_sq <= last_sq && first_sq <= self_last_sq
+                }
+                _ => true,
+            },
+            _ => true,
+        }
+    }
+}
+
+/// A commit with the latest commit it follows in its language, if any.
+pub struct ChildCommit {
+    pub parent_id: Option<Uuid>,
+    pub commit: Commit,
+    pub record_list: Vec<Record>,
+}
+
+pub enum SearchMode {
+    /// Words of the pattern in web search syntax.
+    FullText,
+    Substring,
+    /// Matched here rather than by the database, whose dialect differs from
+    /// the one of the highlighter, for example on `\b`.
+    Regex(regex::Regex),
+}
+
+pub struct SearchFilter {
+    /// Every project if `None`.
+    pub project_id_list: Option<Vec<Uuid>>,
+    pub mode: SearchMode,
+    pub pattern: String,
+    /// Ignored by full-text search, which never is.
+    pub case_sensitive: bool,
+    pub in_source: bool,
+    pub in_meta: bool,
+    /// Searches the latest commits, in one language if given.
+    pub in_translation: bool,
+    pub language: Option<String>,
+}
+
+pub const SEARCH_FIELD_SOURCE: &str = "source";
+pub const SEARCH_FIELD_META: &str = "meta";
+pub const SEARCH_FIELD_TRANSLATION: &str = "translation";
+
+#[derive(QueryableByName)]
+pub struct SearchHit {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub project_id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub unit_id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Varchar)]
+    pub unit_title: String,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub sq: i32,
+    #[diesel(sql_type = diesel::sql_types::Varchar)]
+    pub field: String,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Varchar>)]
+    pub language: Option<String>,
+    #[diesel(sql_type = diesel::sql_types::Varchar)]
+    pub content: String,
+}
+
+/// Where a line of the previous source version went in the new one.
+pub struct SqRemap {
+    pub old_sq: i32,
+    pub new_sq: i32,
+    pub changed: bool,
 }
 
 impl Repo {
@@ -163,6 +487,15 @@ impl Repo {
             .map_err(Error::from)
     }
 
+    pub fn get_user_by_oidc_subject(&self, oidc_subject: String) -> Result<User, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::user::table
+            .filter(schema::user::oidc_subject.eq(oidc_subject))
+            .first::<User>(&mut conn)
+            .map_err(Error::from)
+    }
+
     pub fn get_user_by_id(&self, id: Uuid) -> Result<User, Error> {
         let mut conn = self.pool.get()?;
 
@@ -172,6 +505,17 @@ impl Repo {
             .map_err(Error::from)
     }
 
+    /// Whether any user is an admin, active or not.
+    pub fn has_admin(&self) -> Result<bool, Error> {
+        let mut conn = self.pool.get()?;
+
+        diesel::select(diesel::dsl::exists(
+            schema::user::table.filter(schema::user::is_admin.eq(true)),
+        ))
+        .get_result::<bool>(&mut conn)
+        .map_err(Error::from)
+    }
+
     pub fn add_user(&self, user: User) -> Result<(), Error> {
         let mut conn = self.pool.get()?;
 
@@ -182,120 +526,1748 @@ impl Repo {
         Ok(())
     }
 
-    pub fn get_project(&self) -> Result<Vec<Project>, Error> {
+    pub fn get_user(
+        &self,
+        search: Option<String>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<User>, Error> {
         let mut conn = self.pool.get()?;
 
-        schema::project::table
-            .load::<Project>(&mut conn)
+        let mut query = schema::user::table.into_boxed();
+        if let Some(search) = search {
+            query = query.filter(schema::user::name.ilike(format!(
+                "%{}%",
+                search.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+            )));
+        }
+
+        query
+            .order_by(schema::user::name)
+            .offset(offset)
+            .limit(limit)
+            .load::<User>(&mut conn)
             .map_err(Error::from)
     }
 
-    pub fn get_project_by_id(&self, id: Uuid) -> Result<Project, Error> {
+    pub fn update_user_name(&self, id: Uuid, name: String) -> Result<(), Error> {
         let mut conn = self.pool.get()?;
 
-        schema::project::table
-            .filter(schema::project::id.eq(id))
-            .first::<Project>(&mut conn)
-            .map_err(Error::from)
+        let count = diesel::update(schema::user::table)
+            .filter(schema::user::id.eq(id))
+            .set(schema::user::name.eq(name))
+            .execute(&mut conn)?;
+
+        match count {
+            0 => Err(Error::NotFound),
+            _ => Ok(()),
+        }
     }
 
-    pub fn add_project(&self, project: Project) -> Result<(), Error> {
+    pub fn update_user_oidc_subject(
+        &self,
+        id: Uuid,
+        oidc_subject: Option<String>,
+    ) -> Result<(), Error> {
         let mut conn = self.pool.get()?;
 
-        diesel::insert_into(schema::project::table)
-            .values(&project)
+        let count = diesel::update(schema::user::table)
+            .filter(schema::user::id.eq(id))
+            .set(schema::user::oidc_subject.eq(oidc_subject))
             .execute(&mut conn)?;
 
-        Ok(())
+        match count {
+            0 => Err(Error::NotFound),
+            _ => Ok(()),
+        }
     }
 
-    pub fn get_unit_by_project_id(&self, project_id: Uuid) -> Result<Vec<Unit>, Error> {
+    pub fn update_user_is_admin(&self, id: Uuid, is_admin: bool) -> Result<(), Error> {
         let mut conn = self.pool.get()?;
 
-        schema::unit::table
-            .filter(schema::unit::project_id.eq(project_id))
-            .order_by(schema::unit::title)
-            .load::<Unit>(&mut conn)
-            .map_err(Error::from)
+        let count = diesel::update(schema::user::table)
+            .filter(schema::user::id.eq(id))
+            .set(schema::user::is_admin.eq(is_admin))
+            .execute(&mut conn)?;
+
+        match count {
+            0 => Err(Error::NotFound),
+            _ => Ok(()),
+        }
     }
 
-    pub fn get_unit_by_id(&self, id: Uuid) -> Result<Unit, Error> {
+    /// Deactivating a user also revokes all of their sessions.
+    pub fn update_user_is_active(&self, id: Uuid, is_active: bool) -> Result<(), Error> {
         let mut conn = self.pool.get()?;
 
-        schema::unit::table
-            .filter(schema::unit::id.eq(id))
-            .first::<Unit>(&mut conn)
-            .map_err(Error::from)
+        conn.transaction::<_, Error, _>(|conn| {
+            let count = diesel::update(schema::user::table)
+                .filter(schema::user::id.eq(id))
+                .set(schema::user::is_active.eq(is_active))
+                .execute(conn)?;
+            if count == 0 {
+                return Err(Error::NotFound);
+            }
+
+            if !is_active {
+                diesel::update(schema::session::table)
+                    .filter(schema::session::user_id.eq(id))
+                    .set(schema::session::revoked.eq(true))
+                    .execute(conn)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    pub fn update_user_hash(&self, id: Uuid, hash: String) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        let count = diesel::update(schema::user::table)
+            .filter(schema::user::id.eq(id))
+            .set(schema::user::hash.eq(hash))
+            .execute(&mut conn)?;
+
+        match count {
+            0 => Err(Error::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    /// Starts a new enrollment, replacing any previous second factor.
+    pub fn update_user_totp_secret(&self, id: Uuid, secret: String) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        let count = diesel::update(schema::user::table)
+            .filter(schema::user::id.eq(id))
+            .set((
+                schema::user::totp_secret.eq(secret),
+                schema::user::totp_enabled.eq(false),
+                schema::user::totp_last_step.eq(None::<i64>),
+            ))
+            .execute(&mut conn)?;
+
+        match count {
+            0 => Err(Error::NotFound),
+            _ => Ok(()),
+        }
     }
 
-    pub fn add_unit(&self, unit: Unit, source_list: Vec<Source>) -> Result<(), Error> {
+    pub fn enable_user_totp(
+        &self,
+        id: Uuid,
+        last_step: i64,
+        recovery_code_list: Vec<RecoveryCode>,
+    ) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            let count = diesel::update(schema::user::table)
+                .filter(schema::user::id.eq(id))
+                .filter(schema::user::totp_secret.is_not_null())
+                .set((
+                    schema::user::totp_enabled.eq(true),
+                    schema::user::totp_last_step.eq(last_step),
+                ))
+                .execute(conn)?;
+            if count == 0 {
+                return Err(Error::NotFound);
+            }
+
+            diesel::delete(schema::recovery_code::table)
+                .filter(schema::recovery_code::user_id.eq(id))
+                .execute(conn)?;
+
+            diesel::insert_into(schema::recovery_code::table)
+                .values(recovery_code_list)
+                .execute(conn)?;
+
+            Ok(())
+        })
+    }
+
+    pub fn disable_user_totp(&self, id: Uuid) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            let count = diesel::update(schema::user::table)
+                .filter(schema::user::id.eq(id))
+                .set((
+                    schema::user::totp_secret.eq(None::<String>),
+                    schema::user::totp_enabled.eq(false),
+                    schema::user::totp_last_step.eq(None::<i64>),
+                ))
+                .execute(conn)?;
+            if count == 0 {
+                return Err(Error::NotFound);
+            }
+
+            diesel::delete(schema::recovery_code::table)
+                .filter(schema::recovery_code::user_id.eq(id))
+                .execute(conn)?;
+
+            Ok(())
+        })
+    }
+
+    /// Records a used TOTP step, returning `false` if the same or a later
+    /// step has already been used.
+    pub fn update_user_totp_last_step(&self, id: Uuid, last_step: i64) -> Result<bool, Error> {
+        let mut conn = self.pool.get()?;
+
+        let count = diesel::update(schema::user::table)
+            .filter(schema::user::id.eq(id))
+            .filter(
+                schema::user::totp_last_step
+                    .is_null()
+                    .or(schema::user::totp_last_step.lt(last_step)),
+            )
+            .set(schema::user::totp_last_step.eq(last_step))
+            .execute(&mut conn)?;
+
+        Ok(count > 0)
+    }
+
+    pub fn replace_recovery_code(
+        &self,
+        user_id: Uuid,
+        recovery_code_list: Vec<RecoveryCode>,
+    ) -> Result<(), Error> {
         let mut conn = self.pool.get()?;
 
         conn.transaction(|conn| {
-            diesel::insert_into(schema::unit::table)
-                .values(unit)
+            diesel::delete(schema::recovery_code::table)
+                .filter(schema::recovery_code::user_id.eq(user_id))
                 .execute(conn)?;
 
-            diesel::insert_into(schema::source::table)
-                .values(source_list)
+            diesel::insert_into(schema::recovery_code::table)
+                .values(recovery_code_list)
                 .execute(conn)
         })?;
 
         Ok(())
     }
 
-    pub fn get_source_by_unit_id(&self, unit_id: Uuid) -> Result<Vec<Source>, Error> {
+    /// Marks an unused recovery code as used, returning whether there was one.
+    pub fn use_recovery_code(&self, user_id: Uuid, hash: String) -> Result<bool, Error> {
         let mut conn = self.pool.get()?;
 
-        schema::source::table
-            .filter(schema::source::unit_id.eq(unit_id))
-            .order_by(schema::source::sq)
-            .load::<Source>(&mut conn)
-            .map_err(Error::from)
+        let count = diesel::update(schema::recovery_code::table)
+            .filter(schema::recovery_code::hash.eq(hash))
+            .filter(schema::recovery_code::user_id.eq(user_id))
+            .filter(schema::recovery_code::used.eq(false))
+            .set(schema::recovery_code::used.eq(true))
+            .execute(&mut conn)?;
+
+        Ok(count > 0)
     }
 
-    pub fn get_commit_by_unit_id(&self, unit_id: Uuid) -> Result<Vec<Commit>, Error> {
+    pub fn add_password_reset(&self, password_reset: PasswordReset) -> Result<(), Error> {
         let mut conn = self.pool.get()?;
 
-        schema::commit::table
-            .filter(schema::commit::unit_id.eq(unit_id))
-            .order_by(schema::commit::created_at)
-            .load::<Commit>(&mut conn)
+        diesel::insert_into(schema::password_reset::table)
+            .values(&password_reset)
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Consumes an unused, unexpired reset token, replaces the password hash
+    /// of its user and revokes all of their sessions.
+    pub fn reset_password(&self, token_hash: String, hash: String) -> Result<Uuid, Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            let user_id = diesel::update(schema::password_reset::table)
+                .filter(schema::password_reset::hash.eq(token_hash))
+                .filter(schema::password_reset::used.eq(false))
+                .filter(schema::password_reset::expires_at.gt(diesel::dsl::now))
+                .set(schema::password_reset::used.eq(true))
+                .returning(schema::password_reset::user_id)
+                .get_result::<Uuid>(conn)?;
+
+            diesel::update(schema::user::table)
+                .filter(schema::user::id.eq(user_id))
+                .set(schema::user::hash.eq(hash))
+                .execute(conn)?;
+
+            diesel::update(schema::session::table)
+                .filter(schema::session::user_id.eq(user_id))
+                .set(schema::session::revoked.eq(true))
+                .execute(conn)?;
+
+            Ok(user_id)
+        })
+    }
+
+    pub fn get_session_user_by_id(&self, id: Uuid) -> Result<(Session, User), Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::session::table
+            .inner_join(schema::user::table)
+            .filter(schema::session::id.eq(id))
+            .select((Session::as_select(), User::as_select()))
+            .first::<(Session, User)>(&mut conn)
             .map_err(Error::from)
     }
 
-    pub fn get_commit_by_id(&self, id: Uuid) -> Result<Commit, Error> {
+    pub fn get_session_by_user_id(&self, user_id: Uuid) -> Result<Vec<Session>, Error> {
         let mut conn = self.pool.get()?;
 
-        schema::commit::table
-            .filter(schema::commit::id.eq(id))
-            .first::<Commit>(&mut conn)
+        schema::session::table
+            .filter(schema::session::user_id.eq(user_id))
+            .filter(schema::session::revoked.eq(false))
+            .filter(schema::session::expires_at.gt(diesel::dsl::now))
+            .order_by(schema::session::created_at)
+            .load::<Session>(&mut conn)
             .map_err(Error::from)
     }
 
-    pub fn add_commit(&self, commit: Commit, record_list: Vec<Record>) -> Result<(), Error> {
+    pub fn add_session(&self, session: Session) -> Result<(), Error> {
         let mut conn = self.pool.get()?;
 
         conn.transaction(|conn| {
-            diesel::insert_into(schema::commit::table)
-                .values(commit)
+            diesel::delete(schema::session::table)
+                .filter(schema::session::expires_at.le(session.created_at))
                 .execute(conn)?;
 
-            diesel::insert_into(schema::record::table)
-                .values(record_list)
+            diesel::insert_into(schema::session::table)
+                .values(&session)
                 .execute(conn)
         })?;
 
         Ok(())
     }
 
-    pub fn get_record_by_commit_id(&self, commit_id: Uuid) -> Result<Vec<Record>, Error> {
+    pub fn revoke_session_by_id(&self, id: Uuid) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        diesel::update(schema::session::table)
+            .filter(schema::session::id.eq(id))
+            .set(schema::session::revoked.eq(true))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    pub fn revoke_session_by_user_id(&self, user_id: Uuid) -> Result<usize, Error> {
+        let mut conn = self.pool.get()?;
+
+        diesel::update(schema::session::table)
+            .filter(schema::session::user_id.eq(user_id))
+            .filter(schema::session::revoked.eq(false))
+            .set(schema::session::revoked.eq(true))
+            .execute(&mut conn)
+            .map_err(Error::from)
+    }
+
+    /// Revokes the sessions of a user but the one given.
+    pub fn revoke_other_session(&self, user_id: Uuid, session_id: Uuid) -> Result<usize, Error> {
+        let mut conn = self.pool.get()?;
+
+        diesel::update(schema::session::table)
+            .filter(schema::session::user_id.eq(user_id))
+            .filter(schema::session::id.ne(session_id))
+            .filter(schema::session::revoked.eq(false))
+            .set(schema::session::revoked.eq(true))
+            .execute(&mut conn)
+            .map_err(Error::from)
+    }
+
+    pub fn add_audit_event(&self, audit_event: AuditEvent) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        diesel::insert_into(schema::audit_event::table)
+            .values(&audit_event)
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Newest events first. A `kind` ending in `.` matches every kind with
+    /// that prefix.
+    pub fn get_audit_event(
+        &self,
+        filter: AuditEventFilter,
+        offset: i64,
+        limit: Option<i64>,
+    ) -> Result<Vec<AuditEvent>, Error> {
         let mut conn = self.pool.get()?;
 
-        schema::record::table
-            .filter(schema::record::commit_id.eq(commit_id))
-            .order_by(schema::record::sq)
-            .load::<Record>(&mut conn)
+        let mut query = schema::audit_event::table.into_boxed();
+        if let Some(kind) = filter.kind {
+            query = match kind.ends_with('.') {
+                true => query.filter(schema::audit_event::kind.like(format!(
+                    "{}%",
+                    kind.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+                ))),
+                false => query.filter(schema::audit_event::kind.eq(kind)),
+            };
+        }
+        if let Some(actor_id) = filter.actor_id {
+            query = query.filter(schema::audit_event::actor_id.eq(actor_id));
+        }
+        if let Some(since) = filter.since {
+            query = query.filter(schema::audit_event::created_at.ge(since));
+        }
+        if let Some(until) = filter.until {
+            query = query.filter(schema::audit_event::created_at.lt(until));
+        }
+        if let Some(limit) = limit {
+            query = query.limit(limit);
+        }
+
+        query
+            .order_by((
+                schema::audit_event::created_at.desc(),
+                schema::audit_event::id,
+            ))
+            .offset(offset)
+            .load::<AuditEvent>(&mut conn)
             .map_err(Error::from)
     }
+
+    pub fn get_project(&self, include_archived: bool) -> Result<Vec<Project>, Error> {
+        let mut conn = self.pool.get()?;
+
+        let mut query = schema::project::table.into_boxed();
+        if !include_archived {
+            query = query.filter(schema::project::is_archived.eq(false));
+        }
+
+        query.load::<Project>(&mut conn).map_err(Error::from)
+    }
+
+    pub fn get_project_by_id(&self, id: Uuid) -> Result<Project, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::project::table
+            .filter(schema::project::id.eq(id))
+            .first::<Project>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    /// Creates a project with `owner_id` as its first owner.
+    pub fn add_project(&self, project: Project, owner_id: Uuid) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction(|conn| {
+            diesel::insert_into(schema::project::table)
+                .values(&project)
+                .execute(conn)?;
+
+            diesel::insert_into(schema::project_member::table)
+                .values(ProjectMember {
+                    project_id: project.id,
+                    user_id: owner_id,
+                    role: String::from(ROLE_OWNER),
+                })
+                .execute(conn)
+        })?;
+
+        Ok(())
+    }
+
+    pub fn update_project_name(&self, id: Uuid, name: String) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        let count = diesel::update(schema::project::table)
+            .filter(schema::project::id.eq(id))
+            .set(schema::project::name.eq(name))
+            .execute(&mut conn)?;
+
+        match count {
+            0 => Err(Error::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails with `Error::Conflict` if the units of the project have commits
+    /// in languages a new list of target languages leaves out.
+    pub fn update_project(&self, id: Uuid, changeset: ProjectChangeset) -> Result<(), Error> {
+        if changeset.is_empty() {
+            return self.get_project_by_id(id).map(|_| ());
+        }
+
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            let target_language_list = changeset.target_language_list.clone();
+            let count = diesel::update(schema::project::table)
+                .filter(schema::project::id.eq(id))
+                .set(changeset)
+                .execute(conn)?;
+            if count == 0 {
+                return Err(Error::NotFound);
+            }
+
+            if let Some(target_language_list) = target_language_list {
+                if !target_language_list.is_empty() {
+                    let left_out = diesel::select(diesel::dsl::exists(
+                        schema::unit_head::table
+                            .inner_join(schema::unit::table)
+                            .filter(schema::unit::project_id.eq(id))
+                            .filter(schema::unit_head::language.ne_all(target_language_list)),
+                    ))
+                    .get_result::<bool>(conn)?;
+                    if left_out {
+                        return Err(Error::Conflict);
+                    }
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    pub fn update_project_is_archived(&self, id: Uuid, is_archived: bool) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        let count = diesel::update(schema::project::table)
+            .filter(schema::project::id.eq(id))
+            .set(schema::project::is_archived.eq(is_archived))
+            .execute(&mut conn)?;
+
+        match count {
+            0 => Err(Error::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    /// Deletes a project together with its units, sources, commits, records,
+    /// members and pending invitations.
+    pub fn delete_project(&self, id: Uuid) -> Result<ProjectDeletion, Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            let unit_id_list = schema::unit::table
+                .filter(schema::unit::project_id.eq(id))
+                .select(schema::unit::id);
+
+            let commit_count = schema::commit::table
+                .filter(schema::commit::unit_id.eq_any(unit_id_list))
+                .count()
+                .get_result::<i64>(conn)?;
+            let unit_count = schema::unit::table
+                .filter(schema::unit::project_id.eq(id))
+                .count()
+                .get_result::<i64>(conn)?;
+
+            // The remaining rows go with the foreign keys' ON DELETE CASCADE.
+            let count = diesel::delete(schema::project::table)
+                .filter(schema::project::id.eq(id))
+                .execute(conn)?;
+
+            match count {
+                0 => Err(Error::NotFound),
+                _ => Ok(ProjectDeletion {
+                    unit_count,
+                    commit_count,
+                }),
+            }
+        })
+    }
+
+    pub fn get_project_member(
+        &self,
+        project_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<ProjectMember, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::project_member::table
+            .filter(schema::project_member::project_id.eq(project_id))
+            .filter(schema::project_member::user_id.eq(user_id))
+            .first::<ProjectMember>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    pub fn get_project_id_by_member_id(&self, user_id: Uuid) -> Result<Vec<Uuid>, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::project_member::table
+            .filter(schema::project_member::user_id.eq(user_id))
+            .select(schema::project_member::project_id)
+            .load::<Uuid>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    pub fn add_invitation(&self, invitation: Invitation) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        diesel::insert_into(schema::invitation::table)
+            .values(&invitation)
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Creates the invited user and consumes an unused, unexpired invitation,
+    /// adding the user to the project the invitation is bound to.
+    pub fn accept_invitation(&self, token_hash: String, user: User) -> Result<Invitation, Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            diesel::insert_into(schema::user::table)
+                .values(&user)
+                .execute(conn)?;
+
+            let invitation = diesel::update(schema::invitation::table)
+                .filter(schema::invitation::hash.eq(token_hash))
+                .filter(schema::invitation::used_by.is_null())
+                .filter(schema::invitation::expires_at.gt(diesel::dsl::now))
+                .set(schema::invitation::used_by.eq(user.id))
+                .returning(Invitation::as_returning())
+                .get_result::<Invitation>(conn)?;
+
+            if let (Some(project_id), Some(role)) = (invitation.project_id, &invitation.role) {
+                diesel::insert_into(schema::project_member::table)
+                    .values(ProjectMember {
+                        project_id,
+                        user_id: user.id,
+                        role: role.clone(),
+                    })
+                    .execute(conn)?;
+            }
+
+            Ok(invitation)
+        })
+    }
+
+    pub fn get_unit_by_project_id(&self, project_id: Uuid) -> Result<Vec<Unit>, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::unit::table
+            .filter(schema::unit::project_id.eq(project_id))
+            .order_by((schema::unit::position, schema::unit::title))
+            .load::<Unit>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    pub fn get_unit_by_folder_id(&self, folder_id: Uuid) -> Result<Vec<Unit>, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::unit::table
+            .filter(schema::unit::folder_id.eq(folder_id))
+            .order_by((schema::unit::position, schema::unit::title))
+            .load::<Unit>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    pub fn get_unit_by_id(&self, id: Uuid) -> Result<Unit, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::unit::table
+            .filter(schema::unit::id.eq(id))
+            .first::<Unit>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    /// Adds the unit after the existing ones of its project, ignoring
+    /// `unit.position`.
+    pub fn add_unit(&self, mut unit: Unit, source_list: Vec<Source>) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction(|conn| {
+            unit.position = next_unit_position(conn, unit.project_id)?;
+
+            diesel::insert_into(schema::unit::table)
+                .values(unit)
+                .execute(conn)?;
+
+            diesel::insert_into(schema::source::table)
+                .values(source_list)
+                .execute(conn)
+        })?;
+
+        Ok(())
+    }
+
+    pub fn update_unit_title(&self, id: Uuid, title: String) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        let count = diesel::update(schema::unit::table)
+            .filter(schema::unit::id.eq(id))
+            .set(schema::unit::title.eq(title))
+            .execute(&mut conn)?;
+
+        match count {
+            0 => Err(Error::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    /// Orders the units of a project as listed. The list must hold every
+    /// unit of the project exactly once.
+    pub fn reorder_unit(&self, project_id: Uuid, unit_id_list: Vec<Uuid>) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            let mut existing_list = schema::unit::table
+                .filter(schema::unit::project_id.eq(project_id))
+                .select(schema::unit::id)
+                .for_update()
+                .load::<Uuid>(conn)?;
+            let mut requested_list = unit_id_list.clone();
+            existing_list.sort();
+            requested_list.sort();
+            if existing_list != requested_list {
+                return Err(Error::Conflict);
+            }
+
+            for (position, id) in unit_id_list.into_iter().enumerate() {
+                diesel::update(schema::unit::table)
+                    .filter(schema::unit::id.eq(id))
+                    .set(schema::unit::position.eq(position as i32))
+                    .execute(conn)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Moves a unit after the existing units of another project, at its root.
+    /// Fails with `Error::Conflict` if the unit has commits in languages the
+    /// project does not list as targets.
+    pub fn move_unit(&self, id: Uuid, project_id: Uuid) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            // Keeps the target languages from changing until the move is done.
+            let target_language_list = schema::project::table
+                .filter(schema::project::id.eq(project_id))
+                .select(schema::project::target_language_list)
+                .for_share()
+                .first::<Vec<String>>(conn)?;
+            if !target_language_list.is_empty() {
+                let language_list = schema::unit_head::table
+                    .filter(schema::unit_head::unit_id.eq(id))
+                    .select(schema::unit_head::language)
+                    .load::<String>(conn)?;
+                if language_list
+                    .iter()
+                    .any(|t| !target_language_list.contains(t))
+                {
+                    return Err(Error::Conflict);
+                }
+            }
+
+            let position = next_unit_position(conn, project_id)?;
+
+            let count = diesel::update(schema::unit::table)
+                .filter(schema::unit::id.eq(id))
+                .set((
+                    schema::unit::project_id.eq(project_id),
+                    schema::unit::position.eq(position),
+                    schema::unit::folder_id.eq(None::<Uuid>),
+                ))
+                .execute(conn)?;
+
+            match count {
+                0 => Err(Error::NotFound),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    /// Files a unit into a folder of its project, or at its root if
+    /// `folder_id` is `None`.
+    pub fn update_unit_folder_id(&self, id: Uuid, folder_id: Option<Uuid>) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        let count = diesel::update(schema::unit::table)
+            .filter(schema::unit::id.eq(id))
+            .set(schema::unit::folder_id.eq(folder_id))
+            .execute(&mut conn)?;
+
+        match count {
+            0 => Err(Error::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    /// Deletes a unit with its sources, commits and records.
+    pub fn delete_unit(&self, id: Uuid) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        // The foreign keys' ON DELETE CASCADE takes the rest.
+        let count = diesel::delete(schema::unit::table)
+            .filter(schema::unit::id.eq(id))
+            .execute(&mut conn)?;
+
+        match count {
+            0 => Err(Error::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts over the current sources and latest commits of the units of
+    /// the given projects, or of every project if `project_id_list` is
+    /// `None`, optionally narrowed to one unit.
+    pub fn get_unit_progress(
+        &self,
+        project_id_list: Option<&[Uuid]>,
+        unit_id: Option<Uuid>,
+    ) -> Result<Vec<UnitProgress>, Error> {
+        use diesel::sql_types;
+
+        let mut conn = self.pool.get()?;
+
+        let source_count_list = diesel::sql_query(
+            r#"
+SELECT "unit"."id" AS "unit_id", "unit"."project_id", "unit"."folder_id",
+    COUNT("source"."sq") AS "line_count",
+    COALESCE(SUM(word_count("source"."content")), 0) AS "word_count",
+    COALESCE(SUM(char_length("source"."content")), 0) AS "character_count"
+FROM "unit"
+LEFT JOIN "source" ON "source"."unit_id" = "unit"."id"
+    AND "source"."version" = "unit"."source_version"
+WHERE ($1 IS NULL OR "unit"."project_id" = ANY($1)) AND ($2 IS NULL OR "unit"."id" = $2)
+GROUP BY "unit"."id"
+"#,
+        )
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Uuid>>, _>(project_id_list)
+        .bind::<sql_types::Nullable<sql_types::Uuid>, _>(unit_id)
+        .load::<UnitSourceCount>(&mut conn)?;
+
+        // Only lines of the current source with a translation not flagged
+        // for retranslation count as translated.
+        let mut language_count_map: HashMap<Uuid, Vec<UnitLanguageCount>> = HashMap::new();
+        for language_count in diesel::sql_query(
+            r#"
+WITH "head" AS (
+    SELECT "unit_head".*
+    FROM "unit_head"
+    INNER JOIN "unit" ON "unit"."id" = "unit_head"."unit_id"
+    WHERE ($1 IS NULL OR "unit"."project_id" = ANY($1)) AND ($2 IS NULL OR "unit"."id" = $2)
+)
+SELECT "head"."unit_id", "head"."language",
+    "commit"."created_at" AS "last_activity_at",
+    COUNT("source"."sq") AS "translated_count",
+    COALESCE(SUM(word_count("source"."content")), 0) AS "translated_word_count",
+    COALESCE(SUM(char_length("source"."content")), 0) AS "translated_character_count",
+    COUNT("source"."sq") FILTER (WHERE "record"."is_reviewed") AS "reviewed_count"
+FROM "head"
+INNER JOIN "commit" ON "commit"."id" = "head"."commit_id"
+LEFT JOIN "commit_record_list"(ARRAY(SELECT "commit_id" FROM "head")) AS "record"
+    ON "record"."commit_id" = "commit"."id"
+    AND "record"."content" <> '' AND NOT "record"."needs_retranslation"
+LEFT JOIN "source" ON "source"."unit_id" = "commit"."unit_id"
+    AND "source"."version" = "commit"."source_version" AND "source"."sq" = "record"."sq"
+GROUP BY "head"."unit_id", "head"."language", "commit"."created_at"
+ORDER BY "head"."language"
+"#,
+        )
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Uuid>>, _>(project_id_list)
+        .bind::<sql_types::Nullable<sql_types::Uuid>, _>(unit_id)
+        .load::<UnitLanguageCount>(&mut conn)?
+        {
+            language_count_map
+                .entry(language_count.unit_id)
+                .or_default()
+                .push(language_count);
+        }
+
+        let mut query = schema::commit::table
+            .inner_join(schema::unit::table)
+            .select((schema::commit::unit_id, schema::commit::editor_id))
+            .distinct()
+            .into_boxed();
+        if let Some(project_id_list) = project_id_list {
+            query = query.filter(schema::unit::project_id.eq_any(project_id_list));
+        }
+        if let Some(unit_id) = unit_id {
+            query = query.filter(schema::unit::id.eq(unit_id));
+        }
+        let mut contributor_map: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for (unit_id, editor_id) in query.load::<(Uuid, Uuid)>(&mut conn)? {
+            contributor_map.entry(unit_id).or_default().push(editor_id);
+        }
+
+        Ok(source_count_list
+            .into_iter()
+            .map(|t| UnitProgress {
+                language_count_list: language_count_map.remove(&t.unit_id).unwrap_or_default(),
+                contributor_id_list: contributor_map.remove(&t.unit_id).unwrap_or_default(),
+                source_count: t,
+            })
+            .collect::<Vec<_>>())
+    }
+
+    pub fn get_folder_by_project_id(&self, project_id: Uuid) -> Result<Vec<Folder>, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::folder::table
+            .filter(schema::folder::project_id.eq(project_id))
+            .order_by((schema::folder::position, schema::folder::name))
+            .load::<Folder>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    pub fn get_folder_by_parent_id(&self, parent_id: Uuid) -> Result<Vec<Folder>, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::folder::table
+            .filter(schema::folder::parent_id.eq(parent_id))
+            .order_by((schema::folder::position, schema::folder::name))
+            .load::<Folder>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    pub fn get_folder_by_id(&self, id: Uuid) -> Result<Folder, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::folder::table
+            .filter(schema::folder::id.eq(id))
+            .first::<Folder>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    /// Adds the folder after its existing siblings, ignoring
+    /// `folder.position`.
+    pub fn add_folder(&self, mut folder: Folder) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction(|conn| {
+            folder.position = next_folder_position(conn, folder.project_id, folder.parent_id)?;
+
+            diesel::insert_into(schema::folder::table)
+                .values(folder)
+                .execute(conn)
+        })?;
+
+        Ok(())
+    }
+
+    pub fn update_folder_name(&self, id: Uuid, name: String) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        let count = diesel::update(schema::folder::table)
+            .filter(schema::folder::id.eq(id))
+            .set(schema::folder::name.eq(name))
+            .execute(&mut conn)?;
+
+        match count {
+            0 => Err(Error::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    /// Moves a folder with its content after the children of `parent_id`, or
+    /// of the project root if `None`. Fails with `Error::Conflict` if the
+    /// parent is the folder itself or one of its descendants.
+    pub fn move_folder(&self, id: Uuid, parent_id: Option<Uuid>) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            let project_id = schema::folder::table
+                .filter(schema::folder::id.eq(id))
+                .select(schema::folder::project_id)
+                .first::<Uuid>(conn)?;
+
+            // Keeps concurrent moves from forming a cycle together.
+            let parent_map = schema::folder::table
+                .filter(schema::folder::project_id.eq(project_id))
+                .select((schema::folder::id, schema::folder::parent_id))
+                .for_update()
+                .load::<(Uuid, Option<Uuid>)>(conn)?
+                .into_iter()
+                .collect::<HashMap<_, _>>();
+
+            let mut ancestor_id = parent_id;
+            while let Some(current_id) = ancestor_id {
+                if current_id == id {
+                    return Err(Error::Conflict);
+                }
+                // A parent outside the project fails on the foreign key.
+                ancestor_id = parent_map.get(&current_id).copied().flatten();
+            }
+
+            let position = next_folder_position(conn, project_id, parent_id)?;
+
+            diesel::update(schema::folder::table)
+                .filter(schema::folder::id.eq(id))
+                .set((
+                    schema::folder::parent_id.eq(parent_id),
+                    schema::folder::position.eq(position),
+                ))
+                .execute(conn)?;
+
+            Ok(())
+        })
+    }
+
+    /// Deletes an empty folder. Fails with `Error::Conflict` if it still holds
+    /// folders or units.
+    pub fn delete_folder(&self, id: Uuid) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            let folder_count = schema::folder::table
+                .filter(schema::folder::parent_id.eq(id))
+                .count()
+                .get_result::<i64>(conn)?;
+            let unit_count = schema::unit::table
+                .filter(schema::unit::folder_id.eq(id))
+                .count()
+                .get_result::<i64>(conn)?;
+            if folder_count + unit_count > 0 {
+                return Err(Error::Conflict);
+            }
+
+            let count = diesel::delete(schema::folder::table)
+                .filter(schema::folder::id.eq(id))
+                .execute(conn)?;
+
+            match count {
+                0 => Err(Error::NotFound),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    /// The current version of the source list.
+    pub fn get_source_by_unit_id(&self, unit_id: Uuid) -> Result<Vec<Source>, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::source::table
+            .inner_join(schema::unit::table)
+            .filter(schema::source::unit_id.eq(unit_id))
+            .filter(schema::source::version.eq(schema::unit::source_version))
+            .select(Source::as_select())
+            .order_by(schema::source::sq)
+            .load::<Source>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    /// Source lines in unit order, then line order.
+    pub fn get_source(&self, filter: SourceFilter) -> Result<Vec<Source>, Error> {
+        let mut conn = self.pool.get()?;
+
+        let mut query = schema::source::table
+            .inner_join(schema::unit::table)
+            .select(Source::as_select())
+            .into_boxed();
+        if let Some(project_id) = filter.project_id {
+            query = query.filter(schema::unit::project_id.eq(project_id));
+        }
+        if let Some(unit_id) = filter.unit_id {
+            query = query.filter(schema::source::unit_id.eq(unit_id));
+        }
+        query = match filter.version {
+            Some(version) => query.filter(schema::source::version.eq(version)),
+            None => query.filter(schema::source::version.eq(schema::unit::source_version)),
+        };
+        if let Some(meta) = filter.meta {
+            query = query.filter(schema::source::meta.contains(meta));
+        }
+
+        query
+            .order_by((schema::unit::position, schema::unit::id, schema::source::sq))
+            .load::<Source>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    /// Every version of the source lists of the units.
+    pub fn get_source_by_unit_id_list(&self, unit_id_list: &[Uuid]) -> Result<Vec<Source>, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::source::table
+            .filter(schema::source::unit_id.eq_any(unit_id_list))
+            .load::<Source>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    pub fn get_source_by_unit_id_version(
+        &self,
+        unit_id: Uuid,
+        version: i32,
+    ) -> Result<Vec<Source>, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::source::table
+            .filter(schema::source::unit_id.eq(unit_id))
+            .filter(schema::source::version.eq(version))
+            .order_by(schema::source::sq)
+            .load::<Source>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    /// Replaces the source list of a unit with a new version, expected to
+    /// follow `old_version`. The latest commit in each language is carried
+    /// over by a new commit from `editor_id`, with its records moved along
+    /// `remap_list` and flagged where the source changed. Records of removed
+    /// lines are dropped. Returns the new commits.
+    pub fn update_source(
+        &self,
+        unit_id: Uuid,
+        old_version: i32,
+        source_list: Vec<Source>,
+        remap_list: Vec<SqRemap>,
+        editor_id: Uuid,
+        created_at: NaiveDateTime,
+    ) -> Result<Vec<Commit>, Error> {
+        let mut conn = self.pool.get()?;
+        let new_version = old_version + 1;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            // Also waits for commits in progress on the unit.
+            let count = diesel::update(schema::unit::table)
+                .filter(schema::unit::id.eq(unit_id))
+                .filter(schema::unit::source_version.eq(old_version))
+                .set(schema::unit::source_version.eq(new_version))
+                .execute(conn)?;
+            if count == 0 {
+                return Err(Error::Conflict);
+            }
+
+            diesel::insert_into(schema::source::table)
+                .values(source_list)
+                .execute(conn)?;
+
+            let head_list = schema::unit_head::table
+                .filter(schema::unit_head::unit_id.eq(unit_id))
+                .load::<UnitHead>(conn)?;
+
+            let remap_map = remap_list
+                .into_iter()
+                .map(|t| (t.old_sq, t))
+                .collect::<HashMap<_, _>>();
+
+            let mut commit_list = Vec::new();
+            for head in head_list {
+                let commit = Commit {
+                    id: Uuid::new_v4(),
+                    unit_id,
+                    created_at,
+                    editor_id,
+                    language: head.language,
+                    source_version: new_version,
+                    propagated_from_id: None,
+                };
+                let record_list = load_record(conn, head.commit_id)?
+                    .into_iter()
+                    .filter_map(|t| {
+                        remap_map.get(&t.sq).map(|remap| Record {
+                            commit_id: commit.id,
+                            sq: remap.new_sq,
+                            content: t.content,
+                            needs_retranslation: t.needs_retranslation || remap.changed,
+                            is_reviewed: t.is_reviewed && !remap.changed,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                diesel::insert_into(schema::commit::table)
+                    .values(&commit)
+                    .execute(conn)?;
+                diesel::insert_into(schema::record::table)
+                    .values(record_list)
+                    .execute(conn)?;
+
+                commit_list.push(commit);
+            }
+
+            Ok(commit_list)
+        })
+    }
+
+    /// Lines of the current sources and latest commits matching the filter,
+    /// ordered by project, unit and line.
+    pub fn search(
+        &self,
+        filter: SearchFilter,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<SearchHit>, Error> {
+        use diesel::sql_types;
+
+        let mut conn = self.pool.get()?;
+
+        let (predicate, pattern, matcher) = match filter.mode {
+            SearchMode::FullText => (
+                "to_tsvector('simple', {}) @@ websearch_to_tsquery('simple', $2)",
+                filter.pattern,
+                None,
+            ),
+            SearchMode::Substring => (
+                match filter.case_sensitive {
+                    true => "{} LIKE $2",
+                    false => "{} ILIKE $2",
+                },
+                format!(
+                    "%{}%",
+                    filter
+                        .pattern
+                        .replace('\\', "\\\\")
+                        .replace('%', "\\%")
+                        .replace('_', "\\_")
+                ),
+                None,
+            ),
+            SearchMode::Regex(matcher) => ("TRUE", String::new(), Some(matcher)),
+        };
+
+        let mut branch_list = Vec::new();
+        for (enabled, field, column) in [
+            (
+                filter.in_source,
+                SEARCH_FIELD_SOURCE,
+                r#""source"."content""#,
+            ),
+            (
+                filter.in_meta,
+                SEARCH_FIELD_META,
+                r#"meta_text("source"."meta")"#,
+            ),
+        ] {
+            if enabled {
+                branch_list.push(format!(
+                    r#"
+SELECT "unit"."project_id", "unit"."id" AS "unit_id", "unit"."title" AS "unit_title",
+    "unit"."position" AS "unit_position", "source"."sq", '{}' AS "field",
+    NULL AS "language", {} AS "content"
+FROM "source"
+INNER JOIN "unit" ON "unit"."id" = "source"."unit_id"
+    AND "unit"."source_version" = "source"."version"
+WHERE ($1 IS NULL OR "unit"."project_id" = ANY($1)) AND {}
+"#,
+                    field,
+                    column,
+                    predicate.replace("{}", column),
+                ));
+            }
+        }
+        if filter.in_translation {
+            branch_list.push(format!(
+                r#"
+SELECT "unit"."project_id", "unit"."id" AS "unit_id", "unit"."title" AS "unit_title",
+    "unit"."position" AS "unit_position", "record"."sq", '{}' AS "field",
+    "unit_head"."language", "record"."content"
+FROM "commit_record_list"(ARRAY(
+    SELECT "unit_head"."commit_id"
+    FROM "unit_head"
+    INNER JOIN "unit" ON "unit"."id" = "unit_head"."unit_id"
+    WHERE ($1 IS NULL OR "unit"."project_id" = ANY($1))
+        AND ($3 IS NULL OR "unit_head"."language" = $3)
+)) AS "record"
+INNER JOIN "unit_head" ON "unit_head"."commit_id" = "record"."commit_id"
+INNER JOIN "unit" ON "unit"."id" = "unit_head"."unit_id"
+WHERE {}
+"#,
+                SEARCH_FIELD_TRANSLATION,
+                predicate.replace("{}", r#""record"."content""#),
+            ));
+        }
+        if branch_list.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let query = format!(
+            r#"
+SELECT "project_id", "unit_id", "unit_title", "sq", "field", "language", "content"
+FROM ({}) AS "hit"
+ORDER BY "project_id", "unit_position", "unit_id", "sq", "field", "language"
+OFFSET $4 LIMIT $5
+"#,
+            branch_list.join("UNION ALL"),
+        );
+
+        let query = diesel::sql_query(query)
+            .bind::<sql_types::Nullable<sql_types::Array<sql_types::Uuid>>, _>(
+                filter.project_id_list,
+            )
+            .bind::<sql_types::Text, _>(pattern)
+            .bind::<sql_types::Nullable<sql_types::Varchar>, _>(filter.language);
+        let Some(matcher) = matcher else {
+            return query
+                .bind::<sql_types::BigInt, _>(offset)
+                .bind::<sql_types::Nullable<sql_types::BigInt>, _>(Some(limit))
+                .load::<SearchHit>(&mut conn)
+                .map_err(Error::from);
+        };
+
+        // Every line in scope goes through the expression, so the page is
+        // cut here.
+        query
+            .bind::<sql_types::BigInt, _>(0)
+            .bind::<sql_types::Nullable<sql_types::BigInt>, _>(None::<i64>)
+            .load_iter::<SearchHit, diesel::connection::DefaultLoadingMode>(&mut conn)?
+            .filter(|t| t.as_ref().map_or(true, |t| matcher.is_match(&t.content)))
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect::<QueryResult<Vec<_>>>()
+            .map_err(Error::from)
+    }
+
+    pub fn get_unit_head_by_unit_id(&self, unit_id: Uuid) -> Result<Vec<UnitHead>, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::unit_head::table
+            .filter(schema::unit_head::unit_id.eq(unit_id))
+            .order_by(schema::unit_head::language)
+            .load::<UnitHead>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    pub fn get_unit_head_by_project_id(&self, project_id: Uuid) -> Result<Vec<UnitHead>, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::unit_head::table
+            .inner_join(schema::unit::table)
+            .filter(schema::unit::project_id.eq(project_id))
+            .select(UnitHead::as_select())
+            .order_by(schema::unit_head::language)
+            .load::<UnitHead>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    pub fn get_unit_head(&self, unit_id: Uuid, language: String) -> Result<UnitHead, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::unit_head::table
+            .filter(schema::unit_head::unit_id.eq(unit_id))
+            .filter(schema::unit_head::language.eq(language))
+            .first::<UnitHead>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    pub fn get_commit_by_unit_id(
+        &self,
+        unit_id: Uuid,
+        language: Option<String>,
+    ) -> Result<Vec<Commit>, Error> {
+        let mut conn = self.pool.get()?;
+
+        let mut query = schema::commit::table
+            .filter(schema::commit::unit_id.eq(unit_id))
+            .into_boxed();
+        if let Some(language) = language {
+            query = query.filter(schema::commit::language.eq(language));
+        }
+
+        query
+            .order_by(schema::commit::created_at)
+            .select(Commit::as_select())
+            .load::<Commit>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    /// Commits on the units of a project made in `[since, until)`, optionally
+    /// by one editor, oldest first. The parent may be older than `since`.
+    pub fn get_commit_with_parent(
+        &self,
+        project_id: Uuid,
+        editor_id: Option<Uuid>,
+        since: Option<NaiveDateTime>,
+        until: Option<NaiveDateTime>,
+    ) -> Result<Vec<CommitWithParent>, Error> {
+        use diesel::sql_types;
+
+        let mut conn = self.pool.get()?;
+
+        diesel::sql_query(
+            r#"
+SELECT * FROM (
+    SELECT "commit".*,
+        LAG("commit"."id") OVER "history" AS "parent_id",
+        LAG("commit"."source_version") OVER "history" AS "parent_source_version"
+    FROM "commit"
+    INNER JOIN "unit" ON "unit"."id" = "commit"."unit_id"
+    WHERE "unit"."project_id" = $1 AND ($4 IS NULL OR "commit"."created_at" < $4)
+    WINDOW "history" AS (
+        PARTITION BY "commit"."unit_id", "commit"."language"
+        ORDER BY "commit"."created_at", "commit"."id"
+    )
+) AS "commit"
+WHERE ($2 IS NULL OR "commit"."editor_id" = $2) AND ($3 IS NULL OR "commit"."created_at" >= $3)
+ORDER BY "commit"."created_at", "commit"."id"
+"#,
+        )
+        .bind::<sql_types::Uuid, _>(project_id)
+        .bind::<sql_types::Nullable<sql_types::Uuid>, _>(editor_id)
+        .bind::<sql_types::Nullable<sql_types::Timestamp>, _>(since)
+        .bind::<sql_types::Nullable<sql_types::Timestamp>, _>(until)
+        .load::<CommitWithParent>(&mut conn)
+        .map_err(Error::from)
+    }
+
+    pub fn get_commit_by_id(&self, id: Uuid) -> Result<Commit, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::commit::table
+            .filter(schema::commit::id.eq(id))
+            .select(Commit::as_select())
+            .first::<Commit>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    /// Fails with `Error::Conflict` if the commit was not written against the
+    /// current source version of the unit.
+    pub fn add_commit(&self, commit: Commit, record_list: Vec<Record>) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| insert_commit(conn, commit, record_list))
+    }
+
+    /// Adds all of the commits or none. Fails with `Error::Conflict` if one was
+    /// not written against the current source version of its unit, or if its
+    /// parent is no longer the latest commit in its language.
+    pub fn add_commit_list(&self, child_commit_list: Vec<ChildCommit>) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            for child_commit in child_commit_list {
+                insert_child_commit(conn, child_commit)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Unexpired locks on a unit, in every language unless one is given.
+    pub fn get_line_lock_by_unit_id(
+        &self,
+        unit_id: Uuid,
+        language: Option<String>,
+    ) -> Result<Vec<LineLock>, Error> {
+        let mut conn = self.pool.get()?;
+
+        let mut query = schema::line_lock::table
+            .filter(schema::line_lock::unit_id.eq(unit_id))
+            .filter(schema::line_lock::expires_at.gt(diesel::dsl::now))
+            .into_boxed();
+        if let Some(language) = language {
+            query = query.filter(schema::line_lock::language.eq(language));
+        }
+
+        query
+            .order_by((
+                schema::line_lock::language,
+                schema::line_lock::first_sq.asc().nulls_first(),
+                schema::line_lock::created_at,
+            ))
+            .load::<LineLock>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    /// The unexpired locks on any of the units in one language.
+    pub fn get_line_lock_by_unit_id_list(
+        &self,
+        unit_id_list: &[Uuid],
+        language: String,
+    ) -> Result<Vec<LineLock>, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::line_lock::table
+            .filter(schema::line_lock::unit_id.eq_any(unit_id_list))
+            .filter(schema::line_lock::language.eq(language))
+            .filter(schema::line_lock::expires_at.gt(diesel::dsl::now))
+            .order_by((
+                schema::line_lock::unit_id,
+                schema::line_lock::first_sq.asc().nulls_first(),
+                schema::line_lock::created_at,
+            ))
+            .load::<LineLock>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    /// Finds an unexpired lock.
+    pub fn get_line_lock_by_id(&self, id: Uuid) -> Result<LineLock, Error> {
+        let mut conn = self.pool.get()?;
+
+        schema::line_lock::table
+            .filter(schema::line_lock::id.eq(id))
+            .filter(schema::line_lock::expires_at.gt(diesel::dsl::now))
+            .first::<LineLock>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    /// Adds a lock, failing with `Error::Conflict` if it overlaps an
+    /// unexpired lock of another holder. Expired locks on the unit are
+    /// removed.
+    pub fn add_line_lock(&self, line_lock: LineLock) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            // Takes locks on a unit one at a time.
+            schema::unit::table
+                .filter(schema::unit::id.eq(line_lock.unit_id))
+                .select(schema::unit::id)
+                .for_update()
+                .first::<Uuid>(conn)?;
+
+            diesel::delete(schema::line_lock::table)
+                .filter(schema::line_lock::unit_id.eq(line_lock.unit_id))
+                .filter(schema::line_lock::expires_at.le(diesel::dsl::now))
+                .execute(conn)?;
+
+            let conflict = schema::line_lock::table
+                .filter(schema::line_lock::unit_id.eq(line_lock.unit_id))
+                .filter(schema::line_lock::language.eq(&line_lock.language))
+                .filter(schema::line_lock::holder_id.ne(line_lock.holder_id))
+                .load::<LineLock>(conn)?
+                .iter()
+                .any(|t| t.overlaps(&line_lock));
+            if conflict {
+                return Err(Error::Conflict);
+            }
+
+            diesel::insert_into(schema::line_lock::table)
+                .values(line_lock)
+                .execute(conn)?;
+
+            Ok(())
+        })
+    }
+
+    /// Extends an unexpired lock.
+    pub fn update_line_lock_expires_at(
+        &self,
+        id: Uuid,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        let count = diesel::update(schema::line_lock::table)
+            .filter(schema::line_lock::id.eq(id))
+            .filter(schema::line_lock::expires_at.gt(diesel::dsl::now))
+            .set(schema::line_lock::expires_at.eq(expires_at))
+            .execute(&mut conn)?;
+
+        match count {
+            0 => Err(Error::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn delete_line_lock(&self, id: Uuid) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        let count = diesel::delete(schema::line_lock::table)
+            .filter(schema::line_lock::id.eq(id))
+            .execute(&mut conn)?;
+
+        match count {
+            0 => Err(Error::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    /// Suggestions for the current source list of a unit, in every language
+    /// unless one is given.
+    pub fn get_suggestion_by_unit_id(
+        &self,
+        unit_id: Uuid,
+        language: Option<String>,
+    ) -> Result<Vec<Suggestion>, Error> {
+        let mut conn = self.pool.get()?;
+
+        let mut query = schema::suggestion::table
+            .inner_join(schema::unit::table)
+            .filter(schema::suggestion::unit_id.eq(unit_id))
+            .filter(schema::suggestion::source_version.eq(schema::unit::source_version))
+            .select(Suggestion::as_select())
+            .into_boxed();
+        if let Some(language) = language {
+            query = query.filter(schema::suggestion::language.eq(language));
+        }
+
+        query
+            .order_by((schema::suggestion::language, schema::suggestion::sq))
+            .load::<Suggestion>(&mut conn)
+            .map_err(Error::from)
+    }
+
+    /// Adds suggestions, replacing those of the same lines.
+    pub fn add_suggestion_list(&self, suggestion_list: Vec<Suggestion>) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            for suggestion in suggestion_list {
+                diesel::insert_into(schema::suggestion::table)
+                    .values(&suggestion)
+                    .on_conflict((
+                        schema::suggestion::unit_id,
+                        schema::suggestion::language,
+                        schema::suggestion::source_version,
+                        schema::suggestion::sq,
+                    ))
+                    .do_update()
+                    .set((
+                        schema::suggestion::content.eq(&suggestion.content),
+                        schema::suggestion::provider.eq(&suggestion.provider),
+                        schema::suggestion::created_at.eq(suggestion.created_at),
+                    ))
+                    .execute(conn)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Adds a commit taking in the suggestions of some lines, which are
+    /// removed once accepted.
+    pub fn accept_suggestion(
+        &self,
+        child_commit: ChildCommit,
+        sq_list: &[i32],
+    ) -> Result<(), Error> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            diesel::delete(schema::suggestion::table)
+                .filter(schema::suggestion::unit_id.eq(child_commit.commit.unit_id))
+                .filter(schema::suggestion::language.eq(&child_commit.commit.language))
+                .filter(schema::suggestion::source_version.eq(child_commit.commit.source_version))
+                .filter(schema::suggestion::sq.eq_any(sq_list))
+                .execute(conn)?;
+
+            insert_child_commit(conn, child_commit)
+        })
+    }
+
+    /// The records of each commit, resolved along its bases.
+    pub fn get_record_by_commit_id_list(
+        &self,
+        commit_id_list: &[Uuid],
+    ) -> Result<Vec<Record>, Error> {
+        use diesel::sql_types;
+
+        let mut conn = self.pool.get()?;
+
+        diesel::sql_query(
+            r#"
+SELECT * FROM "commit_record_list"($1)
+"#,
+        )
+        .bind::<sql_types::Array<sql_types::Uuid>, _>(commit_id_list)
+        .load::<Record>(&mut conn)
+        .map_err(Error::from)
+    }
+
+    /// The records of a commit, resolved along its bases.
+    pub fn get_record_by_commit_id(&self, commit_id: Uuid) -> Result<Vec<Record>, Error> {
+        let mut conn = self.pool.get()?;
+
+        load_record(&mut conn, commit_id).map_err(Error::from)
+    }
+}
+
+fn load_record(conn: &mut PgConnection, commit_id: Uuid) -> QueryResult<Vec<Record>> {
+    diesel::sql_query(r#"SELECT * FROM "commit_record"($1) ORDER BY "sq""#)
+        .bind::<diesel::sql_types::Uuid, _>(commit_id)
+        .load::<Record>(conn)
+}
+
+/// Inserts a commit, failing if another one became the latest in its
+/// language since it was made.
+fn insert_child_commit(conn: &mut PgConnection, child_commit: ChildCommit) -> Result<(), Error> {
+    let head_id = schema::unit_head::table
+        .filter(schema::unit_head::unit_id.eq(child_commit.commit.unit_id))
+        .filter(schema::unit_head::language.eq(&child_commit.commit.language))
+        .select(schema::unit_head::commit_id)
+        .for_update()
+        .first::<Uuid>(conn)
+        .optional()?;
+    if head_id != child_commit.parent_id {
+        return Err(Error::Conflict);
+    }
+
+    insert_commit(conn, child_commit.commit, child_commit.record_list)
+}
+
+fn insert_commit(
+    conn: &mut PgConnection,
+    commit: Commit,
+    record_list: Vec<Record>,
+) -> Result<(), Error> {
+    let source_version = schema::unit::table
+        .filter(schema::unit::id.eq(commit.unit_id))
+        .select(schema::unit::source_version)
+        .for_share()
+        .first::<i32>(conn)?;
+    if source_version != commit.source_version {
+        return Err(Error::Conflict);
+    }
+
+    // Only the records changed since the latest commit are stored, unless a
+    // line is dropped or the chain of deltas is long enough.
+    let base = schema::unit_head::table
+        .inner_join(schema::commit::table)
+        .filter(schema::unit_head::unit_id.eq(commit.unit_id))
+        .filter(schema::unit_head::language.eq(&commit.language))
+        .filter(schema::commit::source_version.eq(commit.source_version))
+        .filter(schema::commit::delta_depth.lt(MAX_DELTA_DEPTH))
+        .select((schema::commit::id, schema::commit::delta_depth))
+        .first::<(Uuid, i32)>(conn)
+        .optional()?;
+    let (base_id, delta_depth, record_list) = match base {
+        Some((base_id, delta_depth)) => {
+            let base_map = load_record(conn, base_id)?
+                .into_iter()
+                .map(|t| (t.sq, t))
+                .collect::<HashMap<_, _>>();
+            let sq_set = record_list.iter().map(|t| t.sq).collect::<HashSet<_>>();
+            match base_map.keys().all(|sq| sq_set.contains(sq)) {
+                true => (
+                    Some(base_id),
+                    delta_depth + 1,
+                    record_list
+                        .into_iter()
+                        .filter(|t| {
+                            base_map.get(&t.sq).is_none_or(|base| {
+                                base.content != t.content
+                                    || base.needs_retranslation != t.needs_retranslation
+                                    || base.is_reviewed != t.is_reviewed
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+                false => (None, 0, record_list),
+            }
+        }
+        None => (None, 0, record_list),
+    };
+
+    diesel::insert_into(schema::commit::table)
+        .values((
+            commit,
+            schema::commit::base_id.eq(base_id),
+            schema::commit::delta_depth.eq(delta_depth),
+        ))
+        .execute(conn)?;
+
+    diesel::insert_into(schema::record::table)
+        .values(record_list)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+fn next_unit_position(conn: &mut PgConnection, project_id: Uuid) -> QueryResult<i32> {
+    schema::unit::table
+        .filter(schema::unit::project_id.eq(project_id))
+        .select(diesel::dsl::max(schema::unit::position))
+        .first::<Option<i32>>(conn)
+        .map(|t| t.map_or(0, |t| t + 1))
+}
+
+fn next_folder_position(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    parent_id: Option<Uuid>,
+) -> QueryResult<i32> {
+    let mut query = schema::folder::table
+        .filter(schema::folder::project_id.eq(project_id))
+        .into_boxed();
+    query = match parent_id {
+        Some(parent_id) => query.filter(schema::folder::parent_id.eq(parent_id)),
+        None => query.filter(schema::folder::parent_id.is_null()),
+    };
+
+    query
+        .select(diesel::dsl::max(schema::folder::position))
+        .first::<Option<i32>>(conn)
+        .map(|t| t.map_or(0, |t| t + 1))
 }
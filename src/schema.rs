@@ -1,11 +1,78 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    audit_event (id) {
+        id -> Uuid,
+        created_at -> Timestamp,
+        #[max_length = 64]
+        kind -> Varchar,
+        actor_id -> Nullable<Uuid>,
+        #[max_length = 64]
+        ip -> Nullable<Varchar>,
+        detail -> Jsonb,
+    }
+}
+
 diesel::table! {
     commit (id) {
         id -> Uuid,
         unit_id -> Uuid,
         created_at -> Timestamp,
         editor_id -> Uuid,
+        #[max_length = 35]
+        language -> Varchar,
+        source_version -> Int4,
+        propagated_from_id -> Nullable<Uuid>,
+        base_id -> Nullable<Uuid>,
+        delta_depth -> Int4,
+    }
+}
+
+diesel::table! {
+    folder (id) {
+        id -> Uuid,
+        project_id -> Uuid,
+        parent_id -> Nullable<Uuid>,
+        #[max_length = 256]
+        name -> Varchar,
+        position -> Int4,
+    }
+}
+
+diesel::table! {
+    invitation (hash) {
+        hash -> Varchar,
+        created_by -> Uuid,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        project_id -> Nullable<Uuid>,
+        #[max_length = 16]
+        role -> Nullable<Varchar>,
+        used_by -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    line_lock (id) {
+        id -> Uuid,
+        unit_id -> Uuid,
+        #[max_length = 35]
+        language -> Varchar,
+        first_sq -> Nullable<Int4>,
+        last_sq -> Nullable<Int4>,
+        holder_id -> Uuid,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    password_reset (hash) {
+        hash -> Varchar,
+        user_id -> Uuid,
+        created_by -> Uuid,
+        expires_at -> Timestamp,
+        used -> Bool,
     }
 }
 
@@ -14,6 +81,23 @@ diesel::table! {
         id -> Uuid,
         #[max_length = 256]
         name -> Varchar,
+        is_archived -> Bool,
+        #[max_length = 35]
+        source_language -> Nullable<Varchar>,
+        target_language_list -> Array<Varchar>,
+        description -> Varchar,
+        #[max_length = 64]
+        qa_profile -> Nullable<Varchar>,
+        settings -> Jsonb,
+    }
+}
+
+diesel::table! {
+    project_member (project_id, user_id) {
+        project_id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 16]
+        role -> Varchar,
     }
 }
 
@@ -22,15 +106,50 @@ diesel::table! {
         commit_id -> Uuid,
         sq -> Int4,
         content -> Varchar,
+        needs_retranslation -> Bool,
+        is_reviewed -> Bool,
+    }
+}
+
+diesel::table! {
+    recovery_code (hash) {
+        hash -> Varchar,
+        user_id -> Uuid,
+        used -> Bool,
     }
 }
 
 diesel::table! {
-    source (unit_id, sq) {
+    session (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        revoked -> Bool,
+    }
+}
+
+diesel::table! {
+    source (unit_id, version, sq) {
         unit_id -> Uuid,
         sq -> Int4,
         content -> Varchar,
-        meta -> Varchar,
+        meta -> Jsonb,
+        version -> Int4,
+    }
+}
+
+diesel::table! {
+    suggestion (unit_id, language, source_version, sq) {
+        unit_id -> Uuid,
+        source_version -> Int4,
+        sq -> Int4,
+        #[max_length = 35]
+        language -> Varchar,
+        content -> Varchar,
+        #[max_length = 64]
+        provider -> Varchar,
+        created_at -> Timestamp,
     }
 }
 
@@ -40,7 +159,18 @@ diesel::table! {
         project_id -> Uuid,
         #[max_length = 256]
         title -> Varchar,
-        commit_id -> Nullable<Uuid>,
+        source_version -> Int4,
+        position -> Int4,
+        folder_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    unit_head (unit_id, language) {
+        unit_id -> Uuid,
+        #[max_length = 35]
+        language -> Varchar,
+        commit_id -> Uuid,
     }
 }
 
@@ -51,20 +181,49 @@ diesel::table! {
         name -> Varchar,
         hash -> Varchar,
         is_admin -> Bool,
+        is_active -> Bool,
+        totp_secret -> Nullable<Varchar>,
+        totp_enabled -> Bool,
+        totp_last_step -> Nullable<Int8>,
+        oidc_subject -> Nullable<Varchar>,
+        #[max_length = 16]
+        backend -> Varchar,
     }
 }
 
+diesel::joinable!(audit_event -> user (actor_id));
 diesel::joinable!(commit -> unit (unit_id));
 diesel::joinable!(commit -> user (editor_id));
+diesel::joinable!(folder -> project (project_id));
+diesel::joinable!(invitation -> project (project_id));
+diesel::joinable!(line_lock -> unit (unit_id));
+diesel::joinable!(line_lock -> user (holder_id));
+diesel::joinable!(project_member -> project (project_id));
+diesel::joinable!(project_member -> user (user_id));
 diesel::joinable!(record -> commit (commit_id));
+diesel::joinable!(recovery_code -> user (user_id));
+diesel::joinable!(session -> user (user_id));
 diesel::joinable!(source -> unit (unit_id));
+diesel::joinable!(suggestion -> unit (unit_id));
 diesel::joinable!(unit -> project (project_id));
+diesel::joinable!(unit_head -> commit (commit_id));
+diesel::joinable!(unit_head -> unit (unit_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    audit_event,
     commit,
+    folder,
+    invitation,
+    line_lock,
+    password_reset,
     project,
+    project_member,
     record,
+    recovery_code,
+    session,
     source,
+    suggestion,
     unit,
+    unit_head,
     user,
 );
@@ -0,0 +1,101 @@
+//! The audit log of security and administrative events.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{client, Api};
+
+#[tokio::test]
+async fn records_and_filters_events() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    let admin_id = create_user(database.repo.clone(), "admin", "adminpass1234", true).unwrap();
+    let alice_id = create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+
+    let response = client()
+        .post(format!("{}/api/auth/sign-in", app))
+        .json(&json!({ "name": "alice", "pass": "wrongpass1234" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let admin = Api::sign_in(&app, "admin", "adminpass1234").await;
+
+    // Only admins read the log.
+    let response = alice.get("/admin/audit").await;
+    assert_eq!(response.status(), 401);
+
+    let kind_list = |event_list: &Value| {
+        event_list
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| String::from(t["kind"].as_str().unwrap()))
+            .collect::<Vec<_>>()
+    };
+
+    // Newest first, a trailing dot matches a prefix.
+    let event_list: Value = admin.get_ok("/admin/audit?kind=sign-in.").await;
+    assert_eq!(
+        kind_list(&event_list),
+        vec!["sign-in.success", "sign-in.success", "sign-in.failure"]
+    );
+    assert_eq!(event_list[2]["ip"], "127.0.0.1");
+
+    let event_list: Value = admin
+        .get_ok(&format!("/admin/audit?actor-id={}", alice_id))
+        .await;
+    assert_eq!(
+        kind_list(&event_list),
+        vec!["project.create", "sign-in.success"]
+    );
+    assert_eq!(event_list[0]["detail"]["projectId"], json!(project_id));
+
+    let event_list: Value = admin
+        .get_ok(&format!(
+            "/admin/audit?actor-id={}&limit=1&offset=1",
+            alice_id
+        ))
+        .await;
+    assert_eq!(kind_list(&event_list), vec!["sign-in.success"]);
+
+    let event_list: Value = admin
+        .get_ok("/admin/audit?since=2000-01-01T00:00:00Z&until=2000-01-02T00:00:00Z")
+        .await;
+    assert_eq!(event_list, json!([]));
+
+    // The export is JSON lines of the same events.
+    let response = admin
+        .get(&format!("/admin/audit/export?actor-id={}", admin_id))
+        .await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+    let line_list = response
+        .text()
+        .await
+        .unwrap()
+        .lines()
+        .map(|t| serde_json::from_str::<Value>(t).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(kind_list(&json!(line_list)), vec!["sign-in.success"]);
+}
+
+#[tokio::test]
+async fn keeps_the_log_append_only() {
+    let Some(database) = common::TestDatabase::create() else {
+        return;
+    };
+    create_user(database.repo.clone(), "admin", "adminpass1234", true).unwrap();
+    mts_server::api::audit::record(&database.repo, "user.create", None, None, json!({}));
+
+    assert!(database
+        .execute(r#"UPDATE "audit_event" SET "kind" = 'changed'"#)
+        .is_err());
+    assert!(database.execute(r#"DELETE FROM "audit_event""#).is_err());
+}
@@ -0,0 +1,153 @@
+//! Commits of translations and their heads per target language.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::Api;
+
+#[tokio::test]
+async fn keeps_a_head_per_target_language() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice
+        .post_ok(
+            "/project",
+            json!({ "name": "Game", "targetLanguageList": ["de", "fr"] }),
+        )
+        .await;
+    let unit_id = common::add_unit(&alice, project_id, "Intro", &["Hello"]).await;
+
+    let de_id = common::add_commit(&alice, unit_id, "de", &["Hallo"]).await;
+    let fr_id = common::add_commit(&alice, unit_id, "FR", &["Bonjour"]).await;
+    let de_id_2 = common::add_commit(&alice, unit_id, "de", &["Hallo!"]).await;
+
+    // Languages the project is not translated into are refused.
+    for language in ["es", "en_US"] {
+        let response = alice
+            .post(
+                "/commit",
+                json!({
+                    "unitId": unit_id,
+                    "language": language,
+                    "recordList": [{ "sq": 1, "content": "Hola" }],
+                }),
+            )
+            .await;
+        assert_eq!(response.status(), 400, "{}", language);
+    }
+
+    let unit: Value = alice
+        .get_ok(&format!("/unit/by-id?id={}&language=DE", unit_id))
+        .await;
+    assert_eq!(unit["commitId"], json!(de_id_2));
+    let mut head_list = unit["headList"].as_array().unwrap().clone();
+    head_list.sort_by_key(|t| String::from(t["language"].as_str().unwrap()));
+    assert_eq!(
+        head_list,
+        vec![
+            json!({ "language": "de", "commitId": de_id_2 }),
+            json!({ "language": "fr", "commitId": fr_id }),
+        ]
+    );
+
+    let record_list: Value = alice
+        .get_ok(&format!(
+            "/commit/head/record?unit-id={}&language=fr",
+            unit_id
+        ))
+        .await;
+    assert_eq!(record_list[0]["content"], "Bonjour");
+
+    let commit_list: Vec<Value> = alice
+        .get_ok(&format!("/commit?unit-id={}&language=de", unit_id))
+        .await;
+    let mut id_list = commit_list
+        .iter()
+        .map(|t| t["id"].as_str().unwrap().parse::<Uuid>().unwrap())
+        .collect::<Vec<_>>();
+    id_list.sort();
+    let mut expected = vec![de_id, de_id_2];
+    expected.sort();
+    assert_eq!(id_list, expected);
+    assert!(commit_list.iter().all(|t| t["language"] == "de"));
+}
+
+#[tokio::test]
+async fn keeps_target_languages_that_have_translations() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice
+        .post_ok(
+            "/project",
+            json!({ "name": "Game", "targetLanguageList": ["de", "fr", "es"] }),
+        )
+        .await;
+    let unit_id = common::add_unit(&alice, project_id, "Intro", &["Hello"]).await;
+    common::add_commit(&alice, unit_id, "de", &["Hallo"]).await;
+    common::add_commit(&alice, unit_id, "fr", &["Bonjour"]).await;
+
+    // French has a translation, so it stays.
+    let response = alice
+        .post(
+            "/project/update",
+            json!({ "id": project_id, "targetLanguageList": ["de"] }),
+        )
+        .await;
+    assert_eq!(response.status(), 409);
+    let project: Value = alice
+        .get_ok(&format!("/project/by-id?id={}", project_id))
+        .await;
+    assert_eq!(project["targetLanguageList"], json!(["de", "fr", "es"]));
+
+    // Spanish has none.
+    let project: Value = alice
+        .post_ok(
+            "/project/update",
+            json!({ "id": project_id, "targetLanguageList": ["de", "fr"] }),
+        )
+        .await;
+    assert_eq!(project["targetLanguageList"], json!(["de", "fr"]));
+
+    // An empty list accepts any language, so it leaves nothing out.
+    let project: Value = alice
+        .post_ok(
+            "/project/update",
+            json!({ "id": project_id, "targetLanguageList": [] }),
+        )
+        .await;
+    assert_eq!(project["targetLanguageList"], json!([]));
+}
+
+#[tokio::test]
+async fn accepts_any_language_without_targets() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let unit_id = common::add_unit(&alice, project_id, "Intro", &["Hello"]).await;
+
+    common::add_commit(&alice, unit_id, "pt-br", &["Olá"]).await;
+    let head: Value = alice
+        .get_ok(&format!("/commit/head?unit-id={}&language=pt-BR", unit_id))
+        .await;
+    assert_eq!(head["language"], "pt-BR");
+
+    let response = alice
+        .get(&format!("/commit/head?unit-id={}&language=de", unit_id))
+        .await;
+    assert_eq!(response.status(), 404);
+}
@@ -0,0 +1,288 @@
+//! Shared setup of the integration tests. Tests needing a database run
+//! against scratch databases on the server of `TEST_DATABASE_URL`, and are
+//! skipped when it is not set.
+
+#![allow(dead_code)]
+
+use std::env;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use diesel::r2d2::ConnectionManager;
+use diesel::{Connection, PgConnection, RunQueryDsl};
+use mts_server::auth::backend::{AuthBackends, LocalBackend};
+use mts_server::auth::oidc::Oidc;
+use mts_server::auth::{AuthRwLock, ThrottleMutex};
+use mts_server::mt::MachineTranslation;
+use mts_server::{graphql, repo, run_migrations, AppState};
+use reqwest::{header, Url};
+use uuid::Uuid;
+
+/// A database of its own for a test, dropped along with it.
+pub struct TestDatabase {
+    pub repo: repo::Repo,
+    name: String,
+    url: String,
+    test_url: String,
+}
+
+impl TestDatabase {
+    pub fn create() -> Option<Self> {
+        let Ok(url) = env::var("TEST_DATABASE_URL") else {
+            eprintln!("TEST_DATABASE_URL is not set, skipping");
+            return None;
+        };
+        let name = format!("mts_test_{}", Uuid::new_v4().simple());
+
+        let mut conn = PgConnection::establish(&url).expect("connect to TEST_DATABASE_URL");
+        diesel::sql_query(format!(r#"CREATE DATABASE "{}""#, name))
+            .execute(&mut conn)
+            .expect("create test database");
+
+        let mut test_url = Url::parse(&url).expect("parse TEST_DATABASE_URL");
+        test_url.set_path(&name);
+        let pool = r2d2::Pool::builder()
+            .max_size(4)
+            .build(ConnectionManager::<PgConnection>::new(test_url.as_str()))
+            .expect("connect to test database");
+        run_migrations(&mut pool.get().unwrap()).expect("run migrations");
+
+        Some(TestDatabase {
+            repo: repo::Repo::new(pool),
+            name,
+            url,
+            test_url: test_url.into(),
+        })
+    }
+
+    /// A connection of its own, outside of the pool.
+    pub fn connect(&self) -> PgConnection {
+        PgConnection::establish(&self.test_url).expect("connect to test database")
+    }
+
+    /// Runs SQL the repository has no method for, such as ageing a row.
+    pub fn execute(&self, query: &str) -> diesel::QueryResult<usize> {
+        diesel::sql_query(query).execute(&mut self.connect())
+    }
+}
+
+impl Drop for TestDatabase {
+    fn drop(&mut self) {
+        if let Ok(mut conn) = PgConnection::establish(&self.url) {
+            let _ = diesel::sql_query(format!(r#"DROP DATABASE "{}" WITH (FORCE)"#, self.name))
+                .execute(&mut conn);
+        }
+    }
+}
+
+/// The state of a service with local sign-in only and no providers.
+pub fn app_state(repo: repo::Repo) -> AppState {
+    AppState {
+        repo,
+        auth: AuthRwLock::new(false),
+        backends: AuthBackends(Arc::new(vec![Box::new(LocalBackend)])),
+        throttle: ThrottleMutex::new(),
+        oidc: Oidc(None),
+        mt: MachineTranslation(None),
+        schema: graphql::create_schema(),
+    }
+}
+
+/// Serves the router on a free local port, returning its base URL.
+pub async fn spawn(router: axum::Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+
+    format!("http://{}", address)
+}
+
+/// A client leaving redirects and cookies to the test.
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+}
+
+/// The `name=value` pair of a cookie set by the response.
+pub fn cookie(response: &reqwest::Response, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|t| t.to_str().ok())
+        .filter_map(|t| t.split(';').next())
+        .find(|t| {
+            t.split_once('=')
+                .is_some_and(|(key, value)| key == name && !value.is_empty())
+        })
+        .map(String::from)
+}
+
+pub fn location(response: &reqwest::Response) -> String {
+    response
+        .headers()
+        .get(header::LOCATION)
+        .and_then(|t| t.to_str().ok())
+        .expect("redirect")
+        .into()
+}
+
+/// Signs a local user in with a password, returning the token cookie.
+pub async fn sign_in(client: &reqwest::Client, app: &str, name: &str, pass: &str) -> String {
+    let response = client
+        .post(format!("{}/api/auth/sign-in", app))
+        .json(&serde_json::json!({ "name": name, "pass": pass }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    cookie(&response, "token").expect("token cookie")
+}
+
+/// The id of the user signed in with the token cookie, if any.
+pub async fn claim_id(client: &reqwest::Client, app: &str, token: &str) -> Option<Uuid> {
+    let claim: serde_json::Value = client
+        .get(format!("{}/api/auth/claim", app))
+        .header(header::COOKIE, token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    claim["id"].as_str().and_then(|t| t.parse().ok())
+}
+
+/// A user signed in to the API of a spawned app.
+pub struct Api {
+    pub client: reqwest::Client,
+    pub app: String,
+    pub token: String,
+}
+
+impl Api {
+    pub async fn sign_in(app: &str, name: &str, pass: &str) -> Self {
+        let client = client();
+        let token = sign_in(&client, app, name, pass).await;
+
+        Api {
+            client,
+            app: app.into(),
+            token,
+        }
+    }
+
+    pub async fn get(&self, path: &str) -> reqwest::Response {
+        self.client
+            .get(format!("{}/api{}", self.app, path))
+            .header(header::COOKIE, &self.token)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post(&self, path: &str, body: serde_json::Value) -> reqwest::Response {
+        self.client
+            .post(format!("{}/api{}", self.app, path))
+            .header(header::COOKIE, &self.token)
+            .json(&body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    /// Posts and reads a successful JSON response.
+    pub async fn post_ok<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        body: serde_json::Value,
+    ) -> T {
+        let response = self.post(path, body).await;
+        assert_eq!(response.status(), 200, "POST {}", path);
+        response.json().await.unwrap()
+    }
+
+    /// Gets and reads a successful JSON response.
+    pub async fn get_ok<T: serde::de::DeserializeOwned>(&self, path: &str) -> T {
+        let response = self.get(path).await;
+        assert_eq!(response.status(), 200, "GET {}", path);
+        response.json().await.unwrap()
+    }
+}
+
+/// A scratch database with the app served on it.
+pub async fn setup() -> Option<(TestDatabase, String)> {
+    let database = TestDatabase::create()?;
+    let app = spawn(mts_server::build_app(app_state(database.repo.clone()))).await;
+
+    Some((database, app))
+}
+
+/// Adds a unit whose lines are numbered from 1.
+pub async fn add_unit(api: &Api, project_id: Uuid, title: &str, line_list: &[&str]) -> Uuid {
+    let source_list = line_list
+        .iter()
+        .enumerate()
+        .map(|(i, t)| serde_json::json!({ "sq": i + 1, "content": t }))
+        .collect::<Vec<_>>();
+
+    api.post_ok(
+        "/unit",
+        serde_json::json!({ "projectId": project_id, "title": title, "sourceList": source_list }),
+    )
+    .await
+}
+
+/// Commits every line of a unit, numbered from 1, returning the commit id.
+pub async fn add_commit(api: &Api, unit_id: Uuid, language: &str, line_list: &[&str]) -> Uuid {
+    let record_list = line_list
+        .iter()
+        .enumerate()
+        .map(|(i, t)| serde_json::json!({ "sq": i + 1, "content": t }))
+        .collect::<Vec<_>>();
+
+    let result: serde_json::Value = api
+        .post_ok(
+            "/commit",
+            serde_json::json!({ "unitId": unit_id, "language": language, "recordList": record_list }),
+        )
+        .await;
+
+    result["id"].as_str().unwrap().parse().unwrap()
+}
+
+/// Joins a project as a translator through an invitation of its owner.
+pub async fn join(app: &str, owner: &Api, project_id: Uuid, name: &str) -> (Uuid, Api) {
+    let invitation: serde_json::Value = owner
+        .post_ok(
+            "/auth/invite",
+            serde_json::json!({ "projectId": project_id, "role": "translator" }),
+        )
+        .await;
+    let user_id = client()
+        .post(format!("{}/api/auth/accept-invite", app))
+        .json(&serde_json::json!({
+            "token": invitation["token"],
+            "name": name,
+            "pass": "newpass12345",
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json::<Uuid>()
+        .await
+        .unwrap();
+
+    (user_id, Api::sign_in(app, name, "newpass12345").await)
+}
@@ -0,0 +1,220 @@
+//! Commits stored as deltas on the commit before them.
+
+mod common;
+
+use std::collections::HashMap;
+
+use diesel::sql_types::{BigInt, Integer, Nullable};
+use diesel::{QueryableByName, RunQueryDsl};
+use diesel_migrations::MigrationHarness;
+use mts_server::auth::service::create_user;
+use serde_json::json;
+use uuid::Uuid;
+
+use common::{Api, TestDatabase};
+
+/// Mirrors the limit of the repository.
+const MAX_DELTA_DEPTH: i32 = 32;
+
+#[derive(QueryableByName)]
+struct StoredCommit {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    id: Uuid,
+    #[diesel(sql_type = Nullable<diesel::sql_types::Uuid>)]
+    base_id: Option<Uuid>,
+    #[diesel(sql_type = Integer)]
+    delta_depth: i32,
+    #[diesel(sql_type = BigInt)]
+    record_count: i64,
+}
+
+/// The base, depth and number of records stored of each commit.
+fn stored_commit_map(database: &TestDatabase) -> HashMap<Uuid, StoredCommit> {
+    diesel::sql_query(
+        r#"
+SELECT "commit"."id", "commit"."base_id", "commit"."delta_depth",
+    COUNT("record"."sq") AS "record_count"
+FROM "commit"
+LEFT JOIN "record" ON "record"."commit_id" = "commit"."id"
+GROUP BY "commit"."id"
+"#,
+    )
+    .load::<StoredCommit>(&mut database.connect())
+    .unwrap()
+    .into_iter()
+    .map(|t| (t.id, t))
+    .collect::<HashMap<_, _>>()
+}
+
+type Snapshot = Vec<(i32, String)>;
+
+fn resolved_snapshot(database: &TestDatabase, commit_id: Uuid) -> Snapshot {
+    database
+        .repo
+        .get_record_by_commit_id(commit_id)
+        .unwrap()
+        .into_iter()
+        .map(|t| (t.sq, t.content))
+        .collect::<Vec<_>>()
+}
+
+/// Commits every line of a unit of three lines `count` times, changing one
+/// line each time, and returns each commit with the lines it holds.
+async fn add_history(api: &Api, unit_id: Uuid, count: usize) -> Vec<(Uuid, Snapshot)> {
+    let mut line_list = vec![String::new(); 3];
+    let mut history = Vec::new();
+    for i in 0..count {
+        line_list[i % 3] = format!("Version {}", i);
+        let commit_id = common::add_commit(
+            api,
+            unit_id,
+            "de",
+            &line_list.iter().map(String::as_str).collect::<Vec<_>>(),
+        )
+        .await;
+        history.push((
+            commit_id,
+            (1..).zip(line_list.iter().cloned()).collect::<Vec<_>>(),
+        ));
+    }
+
+    history
+}
+
+async fn sign_in(database: &TestDatabase, app: &str) -> (Api, Uuid) {
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(app, "alice", "alicepass1234").await;
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+
+    (alice, project_id)
+}
+
+#[tokio::test]
+async fn resolves_chains_like_full_snapshots() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    let (alice, project_id) = sign_in(&database, &app).await;
+    let unit_id = common::add_unit(&alice, project_id, "Intro", &["A", "B", "C"]).await;
+
+    let history = add_history(&alice, unit_id, MAX_DELTA_DEPTH as usize + 4).await;
+
+    // A full commit starts the chain again once it is as long as allowed.
+    let stored_map = stored_commit_map(&database);
+    for (i, (commit_id, snapshot)) in history.iter().enumerate() {
+        let stored = &stored_map[commit_id];
+        let delta_depth = i as i32 % (MAX_DELTA_DEPTH + 1);
+        assert_eq!(stored.delta_depth, delta_depth, "commit {}", i);
+        match delta_depth {
+            0 => {
+                assert_eq!(stored.base_id, None);
+                assert_eq!(stored.record_count, 3);
+            }
+            _ => {
+                assert_eq!(stored.base_id, Some(history[i - 1].0));
+                assert_eq!(stored.record_count, 1);
+            }
+        }
+
+        assert_eq!(&resolved_snapshot(&database, *commit_id), snapshot);
+    }
+
+    // Resolving many commits at once gives the same records.
+    let mut resolved_map: HashMap<Uuid, Snapshot> = HashMap::new();
+    for record in database
+        .repo
+        .get_record_by_commit_id_list(&history.iter().map(|t| t.0).collect::<Vec<_>>())
+        .unwrap()
+    {
+        resolved_map
+            .entry(record.commit_id)
+            .or_default()
+            .push((record.sq, record.content));
+    }
+    for (commit_id, snapshot) in history.iter() {
+        let resolved = resolved_map.get_mut(commit_id).unwrap();
+        resolved.sort();
+        assert_eq!(resolved, snapshot);
+    }
+}
+
+#[tokio::test]
+async fn stores_commits_dropping_lines_in_full() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    let (alice, project_id) = sign_in(&database, &app).await;
+    let unit_id = common::add_unit(&alice, project_id, "Intro", &["A", "B", "C"]).await;
+
+    common::add_commit(&alice, unit_id, "de", &["A", "B", "C"]).await;
+    let dropping_id = common::add_commit(&alice, unit_id, "de", &["A", "B"]).await;
+    let next_id = common::add_commit(&alice, unit_id, "de", &["A", "X"]).await;
+
+    let stored_map = stored_commit_map(&database);
+    let dropping = &stored_map[&dropping_id];
+    assert_eq!(dropping.base_id, None);
+    assert_eq!(dropping.delta_depth, 0);
+    assert_eq!(dropping.record_count, 2);
+    assert_eq!(
+        resolved_snapshot(&database, dropping_id),
+        vec![(1, String::from("A")), (2, String::from("B"))]
+    );
+
+    let next = &stored_map[&next_id];
+    assert_eq!(next.base_id, Some(dropping_id));
+    assert_eq!(next.record_count, 1);
+    assert_eq!(
+        resolved_snapshot(&database, next_id),
+        vec![(1, String::from("A")), (2, String::from("X"))]
+    );
+}
+
+#[tokio::test]
+async fn backfills_deltas_of_existing_commits() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    let (alice, project_id) = sign_in(&database, &app).await;
+    let unit_id = common::add_unit(&alice, project_id, "Intro", &["A", "B", "C"]).await;
+
+    let mut history = add_history(&alice, unit_id, MAX_DELTA_DEPTH as usize + 3).await;
+    let dropping_id = common::add_commit(&alice, unit_id, "de", &["A", "B"]).await;
+    history.push((
+        dropping_id,
+        vec![(1, String::from("A")), (2, String::from("B"))],
+    ));
+
+    // Goes back to every commit storing all of its records.
+    let mut conn = database.connect();
+    for _ in 0..2 {
+        conn.revert_last_migration(mts_server::MIGRATIONS).unwrap();
+    }
+    let full_count = diesel::sql_query(r#"SELECT * FROM "record""#)
+        .execute(&mut conn)
+        .unwrap();
+    assert_eq!(full_count, history.iter().map(|t| t.1.len()).sum::<usize>());
+
+    mts_server::run_migrations(&mut conn).unwrap();
+
+    let stored_map = stored_commit_map(&database);
+    for (i, (commit_id, snapshot)) in history.iter().enumerate() {
+        let stored = &stored_map[commit_id];
+        let delta_depth = match *commit_id == dropping_id {
+            true => 0,
+            false => i as i32 % (MAX_DELTA_DEPTH + 1),
+        };
+        assert_eq!(stored.delta_depth, delta_depth, "commit {}", i);
+        match delta_depth {
+            0 => {
+                assert_eq!(stored.base_id, None);
+                assert_eq!(stored.record_count, snapshot.len() as i64);
+            }
+            _ => {
+                assert_eq!(stored.base_id, Some(history[i - 1].0));
+                assert_eq!(stored.record_count, 1);
+            }
+        }
+
+        assert_eq!(&resolved_snapshot(&database, *commit_id), snapshot);
+    }
+}
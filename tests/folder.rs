@@ -0,0 +1,148 @@
+//! Nested folders holding units, with progress rolled up the tree.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::Api;
+
+#[tokio::test]
+async fn builds_the_tree_with_progress() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let act_id: Uuid = alice
+        .post_ok(
+            "/folder",
+            json!({ "projectId": project_id, "name": "Act 1" }),
+        )
+        .await;
+    let scene_id: Uuid = alice
+        .post_ok(
+            "/folder",
+            json!({ "projectId": project_id, "parentId": act_id, "name": "Scene 1" }),
+        )
+        .await;
+    let unit_id: Uuid = alice
+        .post_ok(
+            "/unit",
+            json!({
+                "projectId": project_id,
+                "folderId": scene_id,
+                "title": "Intro",
+                "sourceList": [
+                    { "sq": 1, "content": "Hello there" },
+                    { "sq": 2, "content": "Bye" },
+                ],
+            }),
+        )
+        .await;
+    common::add_unit(&alice, project_id, "Credits", &["Thanks"]).await;
+    common::add_commit(&alice, unit_id, "de", &["Hallo", ""]).await;
+
+    let tree: Value = alice
+        .get_ok(&format!("/folder/tree?project-id={}", project_id))
+        .await;
+    assert_eq!(tree["progress"]["unitCount"], 2);
+    assert_eq!(tree["progress"]["lineCount"], 3);
+    assert_eq!(tree["unitList"][0]["title"], "Credits");
+
+    let act = &tree["folderList"][0];
+    assert_eq!(act["name"], "Act 1");
+    assert_eq!(act["unitList"], json!([]));
+    let scene = &act["folderList"][0];
+    assert_eq!(scene["name"], "Scene 1");
+    assert_eq!(scene["unitList"][0]["title"], "Intro");
+
+    // Folders count the units at any depth below them.
+    for progress in [&act["progress"], &scene["progress"]] {
+        assert_eq!(progress["unitCount"], 1);
+        assert_eq!(progress["lineCount"], 2);
+        assert_eq!(progress["wordCount"], 3);
+        assert_eq!(progress["languageList"][0]["language"], "de");
+        assert_eq!(progress["languageList"][0]["translatedCount"], 1);
+        assert_eq!(progress["languageList"][0]["translatedWordCount"], 2);
+    }
+}
+
+#[tokio::test]
+async fn moves_and_deletes_folders() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let other_id: Uuid = alice.post_ok("/project", json!({ "name": "Other" })).await;
+    let a_id: Uuid = alice
+        .post_ok("/folder", json!({ "projectId": project_id, "name": "A" }))
+        .await;
+    let b_id: Uuid = alice
+        .post_ok(
+            "/folder",
+            json!({ "projectId": project_id, "parentId": a_id, "name": "B" }),
+        )
+        .await;
+    let other_folder_id: Uuid = alice
+        .post_ok("/folder", json!({ "projectId": other_id, "name": "C" }))
+        .await;
+
+    // No cycles, and no parents from other projects.
+    for parent_id in [a_id, b_id] {
+        let response = alice
+            .post("/folder/move", json!({ "id": a_id, "parentId": parent_id }))
+            .await;
+        assert_eq!(response.status(), 409);
+    }
+    let response = alice
+        .post(
+            "/folder/move",
+            json!({ "id": a_id, "parentId": other_folder_id }),
+        )
+        .await;
+    assert_eq!(response.status(), 400);
+
+    let response = alice
+        .post("/folder/move", json!({ "id": b_id, "parentId": null }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let response = alice
+        .post("/folder/rename", json!({ "id": b_id, "name": "Bee" }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    let folder_list: Vec<Value> = alice
+        .get_ok(&format!("/folder?project-id={}", project_id))
+        .await;
+    assert_eq!(
+        folder_list
+            .iter()
+            .map(|t| (t["name"].as_str().unwrap(), t["parentId"].clone()))
+            .collect::<Vec<_>>(),
+        vec![("A", Value::Null), ("Bee", Value::Null)]
+    );
+
+    // Only empty folders are deleted.
+    let unit_id = common::add_unit(&alice, project_id, "Intro", &[]).await;
+    let response = alice
+        .post("/unit/folder", json!({ "id": unit_id, "folderId": a_id }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let response = alice.post("/folder/delete", json!({ "id": a_id })).await;
+    assert_eq!(response.status(), 409);
+
+    let response = alice
+        .post("/unit/folder", json!({ "id": unit_id, "folderId": null }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let response = alice.post("/folder/delete", json!({ "id": a_id })).await;
+    assert_eq!(response.status(), 200);
+    assert!(database.repo.get_folder_by_id(a_id).is_err());
+}
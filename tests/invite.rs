@@ -0,0 +1,122 @@
+//! Invitation links to the server and to projects.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use mts_server::repo;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{client, Api};
+
+async fn accept(app: &str, token: &str, name: &str) -> reqwest::Response {
+    client()
+        .post(format!("{}/api/auth/accept-invite", app))
+        .json(&json!({ "token": token, "name": name, "pass": "newpass12345" }))
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn invites_to_a_project_with_a_role() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let invitation: Value = alice
+        .post_ok(
+            "/auth/invite",
+            json!({ "projectId": project_id, "role": "reviewer" }),
+        )
+        .await;
+    let token = invitation["token"].as_str().unwrap();
+
+    let response = accept(&app, token, "carol").await;
+    assert_eq!(response.status(), 200);
+    let user_id: Uuid = response.json().await.unwrap();
+    let member = database
+        .repo
+        .get_project_member(project_id, user_id)
+        .unwrap();
+    assert_eq!(member.role, repo::ROLE_REVIEWER);
+
+    // The new user can sign in, and the link is used up.
+    Api::sign_in(&app, "carol", "newpass12345").await;
+    let response = accept(&app, token, "dave").await;
+    assert_eq!(response.status(), 401);
+    assert!(database
+        .repo
+        .get_user_by_name(String::from("dave"))
+        .is_err());
+}
+
+#[tokio::test]
+async fn limits_who_can_invite() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    create_user(database.repo.clone(), "bob", "bobpass12345", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+    let bob = Api::sign_in(&app, "bob", "bobpass12345").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+
+    // Only admins invite to the server, only owners to a project.
+    let response = alice.post("/auth/invite", json!({})).await;
+    assert_eq!(response.status(), 401);
+    let response = bob
+        .post("/auth/invite", json!({ "projectId": project_id }))
+        .await;
+    assert_eq!(response.status(), 401);
+
+    let response = alice
+        .post(
+            "/auth/invite",
+            json!({ "projectId": project_id, "role": "boss" }),
+        )
+        .await;
+    assert_eq!(response.status(), 400);
+
+    create_user(database.repo.clone(), "admin", "adminpass1234", true).unwrap();
+    let admin = Api::sign_in(&app, "admin", "adminpass1234").await;
+    let response = admin.post("/auth/invite", json!({ "role": "owner" })).await;
+    assert_eq!(response.status(), 400);
+    let invitation: Value = admin.post_ok("/auth/invite", json!({})).await;
+
+    // A server invitation adds the user to no project.
+    let response = accept(&app, invitation["token"].as_str().unwrap(), "carol").await;
+    assert_eq!(response.status(), 200);
+    let user_id: Uuid = response.json().await.unwrap();
+    assert!(matches!(
+        database.repo.get_project_member(project_id, user_id),
+        Err(mts_server::repo::Error::NotFound)
+    ));
+}
+
+#[tokio::test]
+async fn rejects_expired_invitations() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "admin", "adminpass1234", true).unwrap();
+    let admin = Api::sign_in(&app, "admin", "adminpass1234").await;
+
+    let invitation: Value = admin.post_ok("/auth/invite", json!({})).await;
+    database
+        .execute(r#"UPDATE "invitation" SET "expires_at" = now() - INTERVAL '1 minute'"#)
+        .unwrap();
+
+    let response = accept(&app, invitation["token"].as_str().unwrap(), "carol").await;
+    assert_eq!(response.status(), 401);
+
+    // Nor is the user created by a failed acceptance.
+    assert!(database
+        .repo
+        .get_user_by_name(String::from("carol"))
+        .is_err());
+}
@@ -0,0 +1,364 @@
+//! Sign-in against a stand-in LDAP directory speaking just enough of the
+//! protocol for simple binds and the admin group lookup.
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bytes::BytesMut;
+use lber::common::TagClass;
+use lber::structure::{StructureTag, PL};
+use mts_server::auth::backend::{AuthBackends, LocalBackend};
+use mts_server::auth::ldap::LdapBackend;
+use mts_server::auth::service::create_user;
+use mts_server::repo::AuditEventFilter;
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use common::{client, cookie, TestDatabase};
+
+const USER_DN: &str = "uid={name},ou=people,dc=example,dc=org";
+const ADMIN_GROUP_DN: &str = "cn=admins,ou=groups,dc=example,dc=org";
+
+// Universal tags and LDAP application tags of the messages served.
+const OCTET_STRING: u64 = 4;
+const ENUMERATED: u64 = 10;
+const SEQUENCE: u64 = 16;
+const BIND_REQUEST: u64 = 0;
+const BIND_RESPONSE: u64 = 1;
+const UNBIND_REQUEST: u64 = 2;
+const SEARCH_REQUEST: u64 = 3;
+const SEARCH_RESULT_ENTRY: u64 = 4;
+const SEARCH_RESULT_DONE: u64 = 5;
+// Context tag of an equality match filter.
+const EQUALITY_MATCH: u64 = 3;
+
+const SUCCESS: u8 = 0;
+const INVALID_CREDENTIALS: u8 = 49;
+
+/// Entries of the directory: passwords by DN, and the DNs of the admin group.
+#[derive(Default)]
+struct Directory {
+    passwords: HashMap<String, String>,
+    admins: Vec<String>,
+}
+
+fn primitive(class: TagClass, id: u64, value: &[u8]) -> StructureTag {
+    StructureTag {
+        class,
+        id,
+        payload: PL::P(value.to_vec()),
+    }
+}
+
+fn constructed(class: TagClass, id: u64, inner: Vec<StructureTag>) -> StructureTag {
+    StructureTag {
+        class,
+        id,
+        payload: PL::C(inner),
+    }
+}
+
+fn octets(tag: &StructureTag) -> String {
+    match &tag.payload {
+        PL::P(value) => String::from_utf8_lossy(value).into_owned(),
+        PL::C(_) => String::new(),
+    }
+}
+
+fn result(id: u64, code: u8) -> StructureTag {
+    constructed(
+        TagClass::Application,
+        id,
+        vec![
+            primitive(TagClass::Universal, ENUMERATED, &[code]),
+            primitive(TagClass::Universal, OCTET_STRING, b""),
+            primitive(TagClass::Universal, OCTET_STRING, b""),
+        ],
+    )
+}
+
+/// Values of the equality matches anywhere in a filter.
+fn equality_values(filter: &StructureTag, values: &mut Vec<String>) {
+    if let PL::C(inner) = &filter.payload {
+        match (filter.class, filter.id, inner.as_slice()) {
+            (TagClass::Context, EQUALITY_MATCH, [_, value]) => values.push(octets(value)),
+            _ => inner.iter().for_each(|t| equality_values(t, values)),
+        }
+    }
+}
+
+async fn serve(mut stream: TcpStream, directory: Arc<Directory>) {
+    let mut buffer = Vec::new();
+    let mut bound = None;
+
+    loop {
+        let (message, rest) = match lber::parse::parse_tag(&buffer) {
+            Ok((rest, message)) => (message, rest.len()),
+            Err(_) => {
+                let mut chunk = [0; 4096];
+                match stream.read(&mut chunk).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+                }
+                continue;
+            }
+        };
+        buffer.drain(..buffer.len() - rest);
+
+        let PL::C(parts) = message.payload else {
+            return;
+        };
+        let [message_id, operation, ..] = parts.as_slice() else {
+            return;
+        };
+        let PL::C(fields) = &operation.payload else {
+            // The unbind request is the only primitive operation.
+            assert_eq!(operation.id, UNBIND_REQUEST);
+            return;
+        };
+
+        let responses = match operation.id {
+            BIND_REQUEST => {
+                let dn = octets(&fields[1]);
+                let pass = octets(&fields[2]);
+                let code = match directory.passwords.get(&dn) {
+                    Some(expected) if *expected == pass => {
+                        bound = Some(dn);
+                        SUCCESS
+                    }
+                    _ => INVALID_CREDENTIALS,
+                };
+                vec![result(BIND_RESPONSE, code)]
+            }
+            SEARCH_REQUEST => {
+                let base = octets(&fields[0]);
+                let mut values = Vec::new();
+                equality_values(&fields[6], &mut values);
+
+                // Only the bound user may look up their own membership.
+                let is_member = base == ADMIN_GROUP_DN
+                    && bound
+                        .as_ref()
+                        .is_some_and(|dn| values.contains(dn) && directory.admins.contains(dn));
+                let mut responses = Vec::new();
+                if is_member {
+                    responses.push(constructed(
+                        TagClass::Application,
+                        SEARCH_RESULT_ENTRY,
+                        vec![
+                            primitive(TagClass::Universal, OCTET_STRING, base.as_bytes()),
+                            constructed(TagClass::Universal, SEQUENCE, Vec::new()),
+                        ],
+                    ));
+                }
+                responses.push(result(SEARCH_RESULT_DONE, SUCCESS));
+                responses
+            }
+            id => panic!("Unexpected LDAP operation {}", id),
+        };
+
+        let mut output = BytesMut::new();
+        for response in responses {
+            let envelope = constructed(
+                TagClass::Universal,
+                SEQUENCE,
+                vec![message_id.clone(), response],
+            );
+            lber::write::encode_into(&mut output, envelope).unwrap();
+        }
+        if stream.write_all(&output).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Serves the directory on a free local port, returning its URL.
+async fn spawn_directory(directory: Directory) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let directory = Arc::new(directory);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve(stream, directory.clone()));
+        }
+    });
+
+    format!("ldap://{}", address)
+}
+
+fn dn(name: &str) -> String {
+    USER_DN.replace("{name}", name)
+}
+
+async fn setup(database: &TestDatabase, url: &str) -> String {
+    let mut backend = LdapBackend::new(url, USER_DN);
+    backend.admin_group_dn = Some(String::from(ADMIN_GROUP_DN));
+
+    let mut app_state = common::app_state(database.repo.clone());
+    app_state.backends = AuthBackends(Arc::new(vec![Box::new(LocalBackend), Box::new(backend)]));
+    common::spawn(mts_server::build_app(app_state)).await
+}
+
+async fn sign_in(client: &reqwest::Client, app: &str, name: &str, pass: &str) -> reqwest::Response {
+    client
+        .post(format!("{}/api/auth/sign-in", app))
+        .json(&json!({ "name": name, "pass": pass }))
+        .send()
+        .await
+        .unwrap()
+}
+
+fn directory() -> Directory {
+    let mut directory = Directory::default();
+    directory
+        .passwords
+        .insert(dn("alice"), String::from("alice-secret"));
+    directory
+        .passwords
+        .insert(dn("root"), String::from("root-secret"));
+    directory.admins.push(dn("root"));
+    directory
+}
+
+#[tokio::test]
+async fn provisions_directory_users() {
+    let Some(database) = TestDatabase::create() else {
+        return;
+    };
+    let url = spawn_directory(directory()).await;
+    let app = setup(&database, &url).await;
+    let client = client();
+
+    let response = sign_in(&client, &app, "alice", "alice-secret").await;
+    assert_eq!(response.status(), 200);
+    let token = cookie(&response, "token").expect("token cookie");
+
+    let user = database
+        .repo
+        .get_user_by_name(String::from("alice"))
+        .unwrap();
+    assert_eq!(user.backend, "ldap");
+    assert!(!user.is_admin);
+    assert_eq!(common::claim_id(&client, &app, &token).await, Some(user.id));
+
+    // The next sign-in finds the same user.
+    let response = sign_in(&client, &app, "alice", "alice-secret").await;
+    assert_eq!(response.status(), 200);
+    let token = cookie(&response, "token").expect("token cookie");
+    assert_eq!(common::claim_id(&client, &app, &token).await, Some(user.id));
+}
+
+#[tokio::test]
+async fn rejects_wrong_passwords() {
+    let Some(database) = TestDatabase::create() else {
+        return;
+    };
+    let url = spawn_directory(directory()).await;
+    let app = setup(&database, &url).await;
+    let client = client();
+
+    assert_eq!(sign_in(&client, &app, "alice", "wrong").await.status(), 401);
+    assert_eq!(
+        sign_in(&client, &app, "nobody", "wrong").await.status(),
+        401
+    );
+    assert!(database
+        .repo
+        .get_user_by_name(String::from("alice"))
+        .is_err());
+
+    // Local users are not looked up in the directory.
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    assert_eq!(
+        sign_in(&client, &app, "alice", "alice-secret")
+            .await
+            .status(),
+        401
+    );
+    assert_eq!(
+        sign_in(&client, &app, "alice", "alicepass1234")
+            .await
+            .status(),
+        200
+    );
+}
+
+#[tokio::test]
+async fn maps_the_admin_group() {
+    let Some(database) = TestDatabase::create() else {
+        return;
+    };
+    let url = spawn_directory(directory()).await;
+    let app = setup(&database, &url).await;
+    let client = client();
+
+    assert_eq!(
+        sign_in(&client, &app, "root", "root-secret").await.status(),
+        200
+    );
+    assert!(
+        database
+            .repo
+            .get_user_by_name(String::from("root"))
+            .unwrap()
+            .is_admin
+    );
+
+    assert_eq!(
+        sign_in(&client, &app, "alice", "alice-secret")
+            .await
+            .status(),
+        200
+    );
+    assert!(
+        !database
+            .repo
+            .get_user_by_name(String::from("alice"))
+            .unwrap()
+            .is_admin
+    );
+}
+
+#[tokio::test]
+async fn throttles_while_the_directory_is_down() {
+    let Some(database) = TestDatabase::create() else {
+        return;
+    };
+    // A port nothing listens on any more.
+    let url = {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        format!("ldap://{}", listener.local_addr().unwrap())
+    };
+    let app = setup(&database, &url).await;
+    let client = client();
+
+    let mut statuses = Vec::new();
+    for _ in 0..7 {
+        statuses.push(
+            sign_in(&client, &app, "alice", "alice-secret")
+                .await
+                .status(),
+        );
+    }
+    assert!(statuses[0] == 401);
+    assert!(statuses.contains(&reqwest::StatusCode::TOO_MANY_REQUESTS));
+
+    let failures = database
+        .repo
+        .get_audit_event(
+            AuditEventFilter {
+                kind: Some(String::from("sign-in.failure")),
+                actor_id: None,
+                since: None,
+                until: None,
+            },
+            0,
+            None,
+        )
+        .unwrap();
+    assert!(!failures.is_empty());
+    assert!(failures.iter().all(|t| t.detail["error"].is_string()));
+}
@@ -0,0 +1,113 @@
+//! Length limits of the project and of each line on commits.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::Api;
+
+#[tokio::test]
+async fn reports_or_rejects_lines_over_their_limits() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice
+        .post_ok(
+            "/project",
+            json!({
+                "name": "Game",
+                "settings": { "lengthLimit": { "maxByteLength": 6, "encoding": "shift_jis" } },
+            }),
+        )
+        .await;
+    let unit_id: Uuid = alice
+        .post_ok(
+            "/unit",
+            json!({
+                "projectId": project_id,
+                "title": "Intro",
+                "sourceList": [
+                    { "sq": 1, "content": "Yes" },
+                    { "sq": 2, "content": "No", "meta": { "maxByteLength": 10, "maxLineCount": 1 } },
+                ],
+            }),
+        )
+        .await;
+
+    let commit = |record_list: Value, strict: bool| {
+        alice.post(
+            "/commit",
+            json!({
+                "unitId": unit_id,
+                "language": "ja",
+                "recordList": record_list,
+                "strict": strict,
+            }),
+        )
+    };
+
+    // The meta of the second line raises the project limit.
+    let record_list = json!([
+        { "sq": 1, "content": "はいはい" },
+        { "sq": 2, "content": "いいえいいえ\nいいえ" },
+    ]);
+    let response = commit(record_list.clone(), true).await;
+    assert_eq!(response.status(), 422);
+    let result: Value = response.json().await.unwrap();
+    assert_eq!(result.get("id"), None);
+    assert_eq!(
+        result["violationList"],
+        json!([
+            { "sq": 1, "kind": "byte-length", "limit": 6, "actual": 8 },
+            { "sq": 2, "kind": "line-count", "limit": 1, "actual": 2 },
+            { "sq": 2, "kind": "byte-length", "limit": 10, "actual": 19 },
+        ])
+    );
+    let response = alice
+        .get(&format!("/commit/head?unit-id={}&language=ja", unit_id))
+        .await;
+    assert_eq!(response.status(), 404);
+
+    // Without strict, the commit is made and the violations reported.
+    let response = commit(record_list, false).await;
+    assert_eq!(response.status(), 200);
+    let result: Value = response.json().await.unwrap();
+    assert!(result["id"].is_string());
+    assert_eq!(result["violationList"].as_array().unwrap().len(), 3);
+
+    let response = commit(
+        json!([{ "sq": 1, "content": "はい" }, { "sq": 2, "content": "いいえ" }]),
+        true,
+    )
+    .await;
+    assert_eq!(response.status(), 200);
+    let result: Value = response.json().await.unwrap();
+    assert_eq!(result["violationList"], json!([]));
+}
+
+#[tokio::test]
+async fn rejects_unknown_encodings_in_settings() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    for length_limit in [
+        json!({ "encoding": "klingon" }),
+        json!({ "maxLength": "ten" }),
+    ] {
+        let response = alice
+            .post(
+                "/project",
+                json!({ "name": "Game", "settings": { "lengthLimit": length_limit } }),
+            )
+            .await;
+        assert_eq!(response.status(), 400, "{}", length_limit);
+    }
+}
@@ -0,0 +1,226 @@
+//! Soft locks on lines and the writes they hold back.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use mts_server::repo;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::Api;
+
+async fn lock(
+    api: &Api,
+    unit_id: Uuid,
+    first_sq: Option<i32>,
+    last_sq: Option<i32>,
+) -> reqwest::Response {
+    api.post(
+        "/lock",
+        json!({ "unitId": unit_id, "language": "de", "firstSq": first_sq, "lastSq": last_sq }),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn acquires_and_releases_locks() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    create_user(database.repo.clone(), "admin", "adminpass1234", true).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+    let admin = Api::sign_in(&app, "admin", "adminpass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let (_, bob) = common::join(&app, &alice, project_id, "bob").await;
+    let unit_id = common::add_unit(&alice, project_id, "Intro", &["A", "B", "C", "D"]).await;
+
+    let response = lock(&alice, unit_id, Some(1), Some(2)).await;
+    assert_eq!(response.status(), 200);
+    let alice_lock: Value = response.json().await.unwrap();
+    assert_eq!(alice_lock["holderName"], "alice");
+
+    // Locks of one holder may overlap, those of others may not.
+    assert_eq!(lock(&alice, unit_id, None, None).await.status(), 200);
+    let response = lock(&bob, unit_id, Some(2), Some(3)).await;
+    assert_eq!(response.status(), 409);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "The lines are locked by alice"
+    );
+    assert_eq!(lock(&bob, unit_id, Some(3), Some(2)).await.status(), 400);
+    assert_eq!(lock(&bob, unit_id, Some(3), None).await.status(), 400);
+
+    let lock_list: Vec<Value> = bob
+        .get_ok(&format!("/lock?unit-id={}&language=de", unit_id))
+        .await;
+    assert_eq!(lock_list.len(), 2);
+    let whole_lock = &lock_list[0];
+    assert_eq!(whole_lock["firstSq"], json!(null));
+
+    // Only the holder keeps a lock alive or releases it.
+    let response = bob
+        .post("/lock/heartbeat", json!({ "id": alice_lock["id"] }))
+        .await;
+    assert_eq!(response.status(), 409);
+    let heartbeat: Value = alice
+        .post_ok(
+            "/lock/heartbeat",
+            json!({ "id": alice_lock["id"], "ttl": 600 }),
+        )
+        .await;
+    assert!(heartbeat["expiresAt"].as_str() > alice_lock["expiresAt"].as_str());
+    let response = bob
+        .post("/lock/release", json!({ "id": alice_lock["id"] }))
+        .await;
+    assert_eq!(response.status(), 409);
+    let response = alice
+        .post("/lock/release", json!({ "id": alice_lock["id"] }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    // Admins break locks of anyone, on the record.
+    let response = bob
+        .post("/lock/break", json!({ "id": whole_lock["id"] }))
+        .await;
+    assert_eq!(response.status(), 401);
+    let response = admin
+        .post("/lock/break", json!({ "id": whole_lock["id"] }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let event_list: Value = admin.get_ok("/admin/audit?kind=lock.break").await;
+    assert_eq!(event_list[0]["detail"]["lockId"], whole_lock["id"]);
+
+    assert_eq!(lock(&bob, unit_id, Some(2), Some(3)).await.status(), 200);
+}
+
+#[tokio::test]
+async fn ignores_expired_locks() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let (_, bob) = common::join(&app, &alice, project_id, "bob").await;
+    let unit_id = common::add_unit(&alice, project_id, "Intro", &["A"]).await;
+
+    let request = json!({ "unitId": unit_id, "language": "de" });
+    let _: Value = alice.post_ok("/lock", request.clone()).await;
+    database
+        .execute(r#"UPDATE "line_lock" SET "expires_at" = now() - INTERVAL '1 second'"#)
+        .unwrap();
+
+    let lock_list: Vec<Value> = bob.get_ok(&format!("/lock?unit-id={}", unit_id)).await;
+    assert!(lock_list.is_empty());
+    common::add_commit(&bob, unit_id, "de", &["A"]).await;
+    let _: Value = bob.post_ok("/lock", request).await;
+}
+
+#[tokio::test]
+async fn holds_back_writes_to_locked_lines() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    create_user(database.repo.clone(), "admin", "adminpass1234", true).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+    let admin = Api::sign_in(&app, "admin", "adminpass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let (_, bob) = common::join(&app, &alice, project_id, "bob").await;
+    let intro_id = common::add_unit(&alice, project_id, "Intro", &["Hi", "Bye"]).await;
+    let outro_id = common::add_unit(&alice, project_id, "Outro", &["Hi", "Bye"]).await;
+    common::add_commit(&alice, intro_id, "de", &["", ""]).await;
+
+    // Alice edits the first line of the outro. Bob's commits may touch its
+    // other lines, but not fill hers.
+    assert_eq!(lock(&alice, outro_id, Some(1), Some(1)).await.status(), 200);
+    common::add_commit(&bob, outro_id, "de", &["", "Tschüss"]).await;
+
+    let mut commit = json!({
+        "unitId": intro_id,
+        "language": "de",
+        "recordList": [{ "sq": 1, "content": "Hallo" }, { "sq": 2, "content": "" }],
+        "propagate": { "scope": "project" },
+    });
+    let response = bob.post("/commit", commit.clone()).await;
+    assert_eq!(response.status(), 409);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "The lines are locked by alice"
+    );
+
+    // Forcing goes through and records which locks were overridden.
+    commit["force"] = json!(true);
+    let result: Value = bob.post_ok("/commit", commit).await;
+    assert_eq!(result["propagatedList"][0]["unitId"], json!(outro_id));
+
+    let mut replacement = json!({
+        "projectId": project_id,
+        "language": "de",
+        "pattern": "Hallo",
+        "replacement": "Moin",
+    });
+    let response = bob.post("/replace", replacement.clone()).await;
+    assert_eq!(response.status(), 409);
+    let result: Value = bob
+        .post_ok(
+            "/replace",
+            json!({
+                "projectId": project_id,
+                "language": "de",
+                "pattern": "Tschüss",
+                "replacement": "Ciao",
+            }),
+        )
+        .await;
+    assert_eq!(result["commitList"].as_array().unwrap().len(), 1);
+    replacement["force"] = json!(true);
+    let result: Value = bob.post_ok("/replace", replacement).await;
+    assert_eq!(result["commitList"].as_array().unwrap().len(), 2);
+
+    database
+        .repo
+        .add_suggestion_list(vec![repo::Suggestion {
+            unit_id: outro_id,
+            source_version: 1,
+            sq: 1,
+            language: String::from("de"),
+            content: String::from("Grüß Gott"),
+            provider: String::from("test"),
+            created_at: chrono::Utc::now().naive_utc(),
+        }])
+        .unwrap();
+    let mut acceptance = json!({ "unitId": outro_id, "language": "de", "sqList": [1] });
+    let response = bob.post("/suggestion/accept", acceptance.clone()).await;
+    assert_eq!(response.status(), 409);
+    acceptance["force"] = json!(true);
+    let _: Value = bob.post_ok("/suggestion/accept", acceptance).await;
+
+    // Forcing is for members only.
+    create_user(database.repo.clone(), "carol", "carolpass1234", false).unwrap();
+    let carol = Api::sign_in(&app, "carol", "carolpass1234").await;
+    let response = carol
+        .post(
+            "/commit",
+            json!({
+                "unitId": outro_id,
+                "language": "de",
+                "recordList": [{ "sq": 1, "content": "Hey" }, { "sq": 2, "content": "" }],
+                "force": true,
+            }),
+        )
+        .await;
+    assert_eq!(response.status(), 401);
+
+    let event_list: Value = admin.get_ok("/admin/audit?kind=lock.override").await;
+    let event_list = event_list.as_array().unwrap();
+    assert_eq!(event_list.len(), 3);
+    for event in event_list {
+        assert_eq!(event["detail"]["unitId"], json!(outro_id));
+        assert_eq!(event["detail"]["lockIdList"].as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,102 @@
+//! The meta of source lines and filtering lines by it.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::Api;
+
+#[tokio::test]
+async fn filters_lines_by_meta() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let unit_id: Uuid = alice
+        .post_ok(
+            "/unit",
+            json!({
+                "projectId": project_id,
+                "title": "Intro",
+                "sourceList": [
+                    { "sq": 1, "content": "Hi", "meta": { "speaker": "Mira", "context": "menu" } },
+                    { "sq": 2, "content": "Hello", "meta": { "speaker": "Mira" } },
+                    { "sq": 3, "content": "Bye", "meta": { "speaker": "Tom", "context": "menu" } },
+                    { "sq": 4, "content": "Welcome" },
+                ],
+            }),
+        )
+        .await;
+
+    let sq_list = |source_list: Value| {
+        source_list
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["sq"].as_i64().unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    // A missing meta is kept as an empty object.
+    let source_list: Value = alice.get_ok(&format!("/unit/source?id={}", unit_id)).await;
+    assert_eq!(source_list[3]["meta"], json!({}));
+
+    let source_list: Value = alice
+        .get_ok(&format!("/unit/source?id={}&speaker=Mira", unit_id))
+        .await;
+    assert_eq!(sq_list(source_list), vec![1, 2]);
+
+    let source_list: Value = alice
+        .get_ok(&format!(
+            "/unit/source?id={}&meta=%7B%22context%22%3A%22menu%22%7D",
+            unit_id
+        ))
+        .await;
+    assert_eq!(sq_list(source_list), vec![1, 3]);
+
+    let source_list: Value = alice
+        .get_ok(&format!(
+            "/unit/source?id={}&speaker=Tom&meta=%7B%22context%22%3A%22menu%22%7D",
+            unit_id
+        ))
+        .await;
+    assert_eq!(sq_list(source_list), vec![3]);
+
+    let response = alice
+        .get(&format!("/unit/source?id={}&meta=menu", unit_id))
+        .await;
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn rejects_invalid_meta() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    for meta in [
+        json!("Mira"),
+        json!({ "maxLength": -1 }),
+        json!({ "encoding": "klingon" }),
+    ] {
+        let response = alice
+            .post(
+                "/unit",
+                json!({
+                    "projectId": project_id,
+                    "title": "Intro",
+                    "sourceList": [{ "sq": 1, "content": "Hi", "meta": meta }],
+                }),
+            )
+            .await;
+        assert_eq!(response.status(), 400, "{}", meta);
+    }
+}
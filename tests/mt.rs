@@ -0,0 +1,262 @@
+//! Machine translation through the HTTP provider against a mock translation
+//! API.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::{routing, Json, Router};
+use mts_server::auth::service::create_user;
+use mts_server::mt::http::HttpProvider;
+use mts_server::mt::{MachineTranslation, Provider};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{client, TestDatabase};
+
+/// Whether the mock fails or drops the last translation, and the requests
+/// it got with their authorization header.
+#[derive(Default)]
+struct Mock {
+    fail: bool,
+    drop_last: bool,
+    requests: Vec<(Option<String>, Value)>,
+}
+
+type MockState = Arc<Mutex<Mock>>;
+
+/// Translates each text in `q` to `<to>:<text>`, answering in the shape
+/// `{"data": {"items": [{"t": ...}]}}`.
+async fn translate(
+    State(mock): State<MockState>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> axum::response::Response {
+    let mut mock = mock.lock().unwrap();
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|t| t.to_str().ok())
+        .map(String::from);
+    mock.requests.push((authorization, body.clone()));
+    if mock.fail {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "overloaded").into_response();
+    }
+
+    let to = body["to"].as_str().unwrap_or_default();
+    let mut items = body["q"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| json!({ "t": format!("{}:{}", to, t.as_str().unwrap()) }))
+        .collect::<Vec<_>>();
+    if mock.drop_last {
+        items.pop();
+    }
+
+    Json(json!({ "data": { "items": items } })).into_response()
+}
+
+async fn spawn_mock() -> (MockState, HttpProvider) {
+    let mock = MockState::default();
+    let url = common::spawn(
+        Router::new()
+            .route("/translate", routing::post(translate))
+            .with_state(mock.clone()),
+    )
+    .await;
+
+    let mut provider = HttpProvider::new(&format!("{}/translate", url));
+    provider.api_key = Some(String::from("secret"));
+    provider.text_key = String::from("q");
+    provider.source_key = String::from("from");
+    provider.target_key = String::from("to");
+    provider.response_pointer = String::from("/data/items");
+    provider.text_pointer = Some(String::from("/t"));
+
+    (mock, provider)
+}
+
+fn texts(list: &[&str]) -> Vec<String> {
+    list.iter().map(|&t| String::from(t)).collect()
+}
+
+#[tokio::test]
+async fn uses_the_configured_keys_and_pointers() {
+    let (mock, provider) = spawn_mock().await;
+
+    let translation_list = provider
+        .translate(Some("en"), "de", &texts(&["one", "two"]))
+        .await
+        .unwrap();
+    assert_eq!(translation_list, texts(&["de:one", "de:two"]));
+
+    // Without a source language the key is left out.
+    provider
+        .translate(None, "fr", &texts(&["three"]))
+        .await
+        .unwrap();
+
+    let requests = &mock.lock().unwrap().requests;
+    assert_eq!(
+        requests[0],
+        (
+            Some(String::from("Bearer secret")),
+            json!({ "q": ["one", "two"], "from": "en", "to": "de" })
+        )
+    );
+    assert_eq!(requests[1].1, json!({ "q": ["three"], "to": "fr" }));
+}
+
+#[tokio::test]
+async fn sends_batches_of_the_batch_size() {
+    let (mock, mut provider) = spawn_mock().await;
+    provider.batch_size = 2;
+
+    let text_list = texts(&["a", "b", "c", "d", "e"]);
+    let translation_list = provider.translate(None, "de", &text_list).await.unwrap();
+    assert_eq!(
+        translation_list,
+        texts(&["de:a", "de:b", "de:c", "de:d", "de:e"])
+    );
+
+    let batch_list = mock
+        .lock()
+        .unwrap()
+        .requests
+        .iter()
+        .map(|(_, body)| body["q"].as_array().unwrap().len())
+        .collect::<Vec<_>>();
+    assert_eq!(batch_list, vec![2, 2, 1]);
+}
+
+#[tokio::test]
+async fn rejects_a_translation_count_mismatch() {
+    let (mock, provider) = spawn_mock().await;
+    mock.lock().unwrap().drop_last = true;
+
+    let error = provider
+        .translate(None, "de", &texts(&["a", "b"]))
+        .await
+        .unwrap_err();
+    assert_eq!(error.into_response().status(), StatusCode::BAD_GATEWAY);
+}
+
+#[tokio::test]
+async fn reports_service_errors() {
+    let (mock, provider) = spawn_mock().await;
+    mock.lock().unwrap().fail = true;
+
+    let error = provider
+        .translate(None, "de", &texts(&["a"]))
+        .await
+        .unwrap_err();
+    assert_eq!(error.into_response().status(), StatusCode::BAD_GATEWAY);
+}
+
+#[tokio::test]
+async fn fills_suggestions_for_untranslated_lines() {
+    let Some(database) = TestDatabase::create() else {
+        return;
+    };
+    let (mock, mut provider) = spawn_mock().await;
+    provider.batch_size = 2;
+
+    let mut app_state = common::app_state(database.repo.clone());
+    app_state.mt = MachineTranslation(Some(Arc::new(provider)));
+    let app = common::spawn(mts_server::build_app(app_state)).await;
+    let client = client();
+
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let token = common::sign_in(&client, &app, "alice", "alicepass1234").await;
+
+    let post = |path: &str, body: Value| {
+        client
+            .post(format!("{}/api{}", app, path))
+            .header(header::COOKIE, &token)
+            .json(&body)
+            .send()
+    };
+
+    let project_id: Uuid = post(
+        "/project",
+        json!({ "name": "Game", "sourceLanguage": "en", "targetLanguageList": ["de"] }),
+    )
+    .await
+    .unwrap()
+    .json()
+    .await
+    .unwrap();
+    let unit_id: Uuid = post(
+        "/unit",
+        json!({
+            "projectId": project_id,
+            "title": "Intro",
+            "sourceList": [
+                { "sq": 1, "content": "Hello" },
+                { "sq": 2, "content": " " },
+                { "sq": 3, "content": "Bye" },
+                { "sq": 4, "content": "Again" },
+            ],
+        }),
+    )
+    .await
+    .unwrap()
+    .json()
+    .await
+    .unwrap();
+
+    let response = post(
+        "/suggestion/fill",
+        json!({ "unitId": unit_id, "language": "de" }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 200);
+    let result: Value = response.json().await.unwrap();
+    assert_eq!(result, json!({ "unitCount": 1, "lineCount": 3 }));
+
+    // Blank lines are not sent, the rest goes in batches.
+    let batch_list = mock
+        .lock()
+        .unwrap()
+        .requests
+        .iter()
+        .map(|(_, body)| body.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        batch_list,
+        vec![
+            json!({ "q": ["Hello", "Bye"], "from": "en", "to": "de" }),
+            json!({ "q": ["Again"], "from": "en", "to": "de" }),
+        ]
+    );
+
+    let suggestion_list = database
+        .repo
+        .get_suggestion_by_unit_id(unit_id, None)
+        .unwrap()
+        .into_iter()
+        .map(|t| (t.sq, t.content, t.provider))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        suggestion_list,
+        vec![
+            (1, String::from("de:Hello"), String::from("http")),
+            (3, String::from("de:Bye"), String::from("http")),
+            (4, String::from("de:Again"), String::from("http")),
+        ]
+    );
+
+    // Lines with a suggestion are skipped the next time.
+    let response = post(
+        "/suggestion/fill",
+        json!({ "unitId": unit_id, "language": "de" }),
+    )
+    .await
+    .unwrap();
+    let result: Value = response.json().await.unwrap();
+    assert_eq!(result, json!({ "unitCount": 0, "lineCount": 0 }));
+}
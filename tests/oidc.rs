@@ -0,0 +1,449 @@
+//! Single sign-on against a mock OpenID Connect provider serving discovery,
+//! token and userinfo endpoints.
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::extract::{Form, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::{routing, Json, Router};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use hmac::{Mac, SimpleHmac};
+use mts_server::auth::oidc::{Oidc, OidcConfig};
+use mts_server::auth::service::create_user;
+use reqwest::{header, Url};
+use serde_json::{json, Value};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use common::{client, cookie, location, TestDatabase};
+
+const CLIENT_ID: &str = "mts";
+const ADMIN_GROUP: &str = "mts-admins";
+
+/// What the provider hands out for an authorization code.
+struct Grant {
+    challenge: String,
+    id_claims: Value,
+    userinfo: Value,
+}
+
+#[derive(Default)]
+struct Provider {
+    issuer: String,
+    grants: HashMap<String, Grant>,
+    userinfo: HashMap<String, Value>,
+}
+
+type ProviderState = Arc<Mutex<Provider>>;
+
+async fn discovery(State(provider): State<ProviderState>) -> Json<Value> {
+    let issuer = provider.lock().unwrap().issuer.clone();
+
+    Json(json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{}/authorize", issuer),
+        "token_endpoint": format!("{}/token", issuer),
+        "userinfo_endpoint": format!("{}/userinfo", issuer),
+    }))
+}
+
+async fn token(
+    State(provider): State<ProviderState>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Response {
+    let mut provider = provider.lock().unwrap();
+    let Some(grant) = form.get("code").and_then(|t| provider.grants.remove(t)) else {
+        return (StatusCode::BAD_REQUEST, "invalid_grant").into_response();
+    };
+
+    // PKCE: the verifier must hash to the challenge sent to /authorize.
+    let verifier = form.get("code_verifier").cloned().unwrap_or_default();
+    if URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())) != grant.challenge
+        || form.get("client_id").map(String::as_str) != Some(CLIENT_ID)
+        || form.get("grant_type").map(String::as_str) != Some("authorization_code")
+    {
+        return (StatusCode::BAD_REQUEST, "invalid_grant").into_response();
+    }
+
+    let access_token = Uuid::new_v4().to_string();
+    provider
+        .userinfo
+        .insert(access_token.clone(), grant.userinfo);
+    let id_token = format!(
+        "{}.{}.",
+        URL_SAFE_NO_PAD.encode(br#"{"alg":"none"}"#),
+        URL_SAFE_NO_PAD.encode(grant.id_claims.to_string())
+    );
+
+    Json(json!({
+        "id_token": id_token,
+        "access_token": access_token,
+        "token_type": "Bearer",
+    }))
+    .into_response()
+}
+
+async fn userinfo(State(provider): State<ProviderState>, headers: HeaderMap) -> Response {
+    let access_token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|t| t.to_str().ok())
+        .and_then(|t| t.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    match provider.lock().unwrap().userinfo.get(access_token) {
+        Some(userinfo) => Json(userinfo.clone()).into_response(),
+        None => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+struct Setup {
+    database: TestDatabase,
+    provider: ProviderState,
+    app: String,
+}
+
+async fn setup(skip_totp: bool) -> Option<Setup> {
+    let database = TestDatabase::create()?;
+
+    let provider = ProviderState::default();
+    let issuer = common::spawn(
+        Router::new()
+            .route("/.well-known/openid-configuration", routing::get(discovery))
+            .route("/token", routing::post(token))
+            .route("/userinfo", routing::get(userinfo))
+            .with_state(provider.clone()),
+    )
+    .await;
+    provider.lock().unwrap().issuer = issuer.clone();
+
+    let mut config = OidcConfig::new(&issuer, CLIENT_ID, "http://app/api/auth/oidc/callback");
+    config.admin_group = Some(String::from(ADMIN_GROUP));
+    config.skip_totp = skip_totp;
+
+    let mut app_state = common::app_state(database.repo.clone());
+    app_state.oidc = Oidc(Some(Arc::new(config)));
+    let app = common::spawn(mts_server::build_app(app_state)).await;
+
+    Some(Setup {
+        database,
+        provider,
+        app,
+    })
+}
+
+/// A user of the provider. Groups are only given through userinfo.
+struct Identity<'a> {
+    sub: &'a str,
+    username: &'a str,
+    groups: &'a [&'a str],
+}
+
+impl Setup {
+    /// Goes from `/login` through the provider to `/callback`, returning the
+    /// response of the callback. `token` is the cookie of a signed in user
+    /// linking their account.
+    async fn sign_in(&self, identity: &Identity<'_>, token: Option<&str>) -> reqwest::Response {
+        let client = client();
+
+        let mut request = client.get(format!(
+            "{}/api/auth/oidc/login?redirect=/done&link={}",
+            self.app,
+            token.is_some()
+        ));
+        if let Some(token) = token {
+            request = request.header(header::COOKIE, token);
+        }
+        let response = request.send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        let state_cookie = cookie(&response, "oidc").expect("state cookie");
+
+        let authorize = Url::parse(&location(&response)).unwrap();
+        assert!(authorize
+            .as_str()
+            .starts_with(&self.provider.lock().unwrap().issuer));
+        let query = authorize
+            .query_pairs()
+            .into_owned()
+            .collect::<HashMap<_, _>>();
+        assert_eq!(query["client_id"], CLIENT_ID);
+        assert_eq!(query["code_challenge_method"], "S256");
+
+        let code = Uuid::new_v4().to_string();
+        let issuer = self.provider.lock().unwrap().issuer.clone();
+        self.provider.lock().unwrap().grants.insert(
+            code.clone(),
+            Grant {
+                challenge: query["code_challenge"].clone(),
+                id_claims: json!({
+                    "iss": issuer,
+                    "sub": identity.sub,
+                    "aud": CLIENT_ID,
+                    "exp": chrono::Utc::now().timestamp() + 300,
+                    "nonce": query["nonce"],
+                    "preferred_username": identity.username,
+                }),
+                userinfo: json!({ "sub": identity.sub, "groups": identity.groups }),
+            },
+        );
+
+        client
+            .get(format!("{}/api/auth/oidc/callback", self.app))
+            .query(&[("code", code.as_str()), ("state", &query["state"])])
+            .header(header::COOKIE, state_cookie)
+            .send()
+            .await
+            .unwrap()
+    }
+}
+
+#[tokio::test]
+async fn provisions_new_users() {
+    let Some(setup) = setup(false).await else {
+        return;
+    };
+    let identity = Identity {
+        sub: "subject-alice",
+        username: "alice",
+        groups: &[],
+    };
+
+    let response = setup.sign_in(&identity, None).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(location(&response), "/done");
+    let token = cookie(&response, "token").expect("token cookie");
+
+    let user = setup
+        .database
+        .repo
+        .get_user_by_name(String::from("alice"))
+        .unwrap();
+    assert_eq!(user.oidc_subject.as_deref(), Some("subject-alice"));
+    assert!(!user.is_admin);
+    assert_eq!(
+        common::claim_id(&client(), &setup.app, &token).await,
+        Some(user.id)
+    );
+
+    // Signing in again finds the same user.
+    let response = setup.sign_in(&identity, None).await;
+    let token = cookie(&response, "token").expect("token cookie");
+    assert_eq!(
+        common::claim_id(&client(), &setup.app, &token).await,
+        Some(user.id)
+    );
+}
+
+#[tokio::test]
+async fn rejects_a_wrong_state() {
+    let Some(setup) = setup(false).await else {
+        return;
+    };
+
+    let response = client()
+        .get(format!(
+            "{}/api/auth/oidc/callback?code=x&state=y",
+            setup.app
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(cookie(&response, "token").is_none());
+}
+
+#[tokio::test]
+async fn links_existing_users() {
+    let Some(setup) = setup(false).await else {
+        return;
+    };
+    let user_id = create_user(setup.database.repo.clone(), "bob", "bobpass123456", false).unwrap();
+    let token = common::sign_in(&client(), &setup.app, "bob", "bobpass123456").await;
+
+    let identity = Identity {
+        sub: "subject-bob",
+        username: "robert",
+        groups: &[],
+    };
+    let response = setup.sign_in(&identity, Some(&token)).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(cookie(&response, "token").is_none());
+    assert_eq!(
+        setup
+            .database
+            .repo
+            .get_user_by_id(user_id)
+            .unwrap()
+            .oidc_subject
+            .as_deref(),
+        Some("subject-bob")
+    );
+
+    // The identity now signs in as bob instead of provisioning "robert".
+    let response = setup.sign_in(&identity, None).await;
+    let token = cookie(&response, "token").expect("token cookie");
+    assert_eq!(
+        common::claim_id(&client(), &setup.app, &token).await,
+        Some(user_id)
+    );
+    assert!(setup
+        .database
+        .repo
+        .get_user_by_name(String::from("robert"))
+        .is_err());
+
+    // Another account cannot take the identity over.
+    create_user(setup.database.repo.clone(), "carol", "carolpass1234", false).unwrap();
+    let token = common::sign_in(&client(), &setup.app, "carol", "carolpass1234").await;
+    let response = setup.sign_in(&identity, Some(&token)).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn maps_the_admin_group() {
+    let Some(setup) = setup(false).await else {
+        return;
+    };
+    let repo = &setup.database.repo;
+
+    let response = setup
+        .sign_in(
+            &Identity {
+                sub: "subject-dave",
+                username: "dave",
+                groups: &["staff", ADMIN_GROUP],
+            },
+            None,
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(
+        repo.get_user_by_name(String::from("dave"))
+            .unwrap()
+            .is_admin
+    );
+
+    // Leaving the group revokes admin on the next sign-in.
+    setup
+        .sign_in(
+            &Identity {
+                sub: "subject-dave",
+                username: "dave",
+                groups: &["staff"],
+            },
+            None,
+        )
+        .await;
+    assert!(
+        !repo
+            .get_user_by_name(String::from("dave"))
+            .unwrap()
+            .is_admin
+    );
+}
+
+// The RFC 6238 seed, "12345678901234567890".
+const TOTP_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+fn totp_code(step: u64) -> u32 {
+    let mut mac = SimpleHmac::<Sha1>::new_from_slice(b"12345678901234567890").unwrap();
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[19] & 0x0f) as usize;
+
+    (u32::from_be_bytes(digest[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff) % 1_000_000
+}
+
+async fn enable_totp(setup: &Setup, sub: &str) -> Uuid {
+    let repo = &setup.database.repo;
+    let user_id = create_user(repo.clone(), "erin", "erinpass1234", false).unwrap();
+    repo.update_user_oidc_subject(user_id, Some(String::from(sub)))
+        .unwrap();
+    repo.update_user_totp_secret(user_id, String::from(TOTP_SECRET))
+        .unwrap();
+    repo.enable_user_totp(user_id, 0, Vec::new()).unwrap();
+
+    user_id
+}
+
+#[tokio::test]
+async fn keeps_the_second_factor() {
+    let Some(setup) = setup(false).await else {
+        return;
+    };
+    let user_id = enable_totp(&setup, "subject-erin").await;
+
+    let response = setup
+        .sign_in(
+            &Identity {
+                sub: "subject-erin",
+                username: "erin",
+                groups: &[],
+            },
+            None,
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(cookie(&response, "token").is_none());
+
+    let target = location(&response);
+    let pre_auth_token = target
+        .strip_prefix("/done#pre-auth-token=")
+        .expect("pre-auth token");
+    let pre_auth_token = pre_auth_token
+        .replace("%2B", "+")
+        .replace("%2F", "/")
+        .replace("%3D", "=");
+
+    // The token is only good together with a code.
+    let step = chrono::Utc::now().timestamp() as u64 / 30;
+    let valid = (step - 1..=step + 1).map(totp_code).collect::<Vec<_>>();
+    let wrong = (0..).find(|t| !valid.contains(t)).unwrap();
+    let response = client()
+        .post(format!("{}/api/auth/sign-in/totp", setup.app))
+        .json(&json!({ "preAuthToken": pre_auth_token, "code": format!("{:06}", wrong) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = client()
+        .post(format!("{}/api/auth/sign-in/totp", setup.app))
+        .json(&json!({ "preAuthToken": pre_auth_token, "code": format!("{:06}", totp_code(step)) }))
+        .send()
+        .await
+        .unwrap();
+    let token = cookie(&response, "token").expect("token cookie");
+    assert_eq!(
+        common::claim_id(&client(), &setup.app, &token).await,
+        Some(user_id)
+    );
+}
+
+#[tokio::test]
+async fn skips_the_second_factor_if_configured() {
+    let Some(setup) = setup(true).await else {
+        return;
+    };
+    let user_id = enable_totp(&setup, "subject-erin").await;
+
+    let response = setup
+        .sign_in(
+            &Identity {
+                sub: "subject-erin",
+                username: "erin",
+                groups: &[],
+            },
+            None,
+        )
+        .await;
+    let token = cookie(&response, "token").expect("token cookie");
+    assert_eq!(
+        common::claim_id(&client(), &setup.app, &token).await,
+        Some(user_id)
+    );
+}
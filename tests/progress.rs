@@ -0,0 +1,92 @@
+//! Progress of units and projects over their latest commits.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::Api;
+
+#[tokio::test]
+async fn counts_the_latest_commit_of_each_language() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let unit_id = common::add_unit(
+        &alice,
+        project_id,
+        "Intro",
+        &["Hello there", "Goodbye", "See you"],
+    )
+    .await;
+
+    // Later commits only store what changed, and still count in full.
+    common::add_commit(&alice, unit_id, "de", &["Hallo", "", ""]).await;
+    common::add_commit(&alice, unit_id, "de", &["Hallo", "Tschüss", ""]).await;
+    common::add_commit(&alice, unit_id, "fr", &["Bonjour", "", ""]).await;
+
+    let unit: Value = alice.get_ok(&format!("/unit/by-id?id={}", unit_id)).await;
+    let progress = &unit["progress"];
+    assert_eq!(progress["unitCount"], 1);
+    assert_eq!(progress["lineCount"], 3);
+    assert_eq!(progress["wordCount"], 5);
+    assert_eq!(progress["languageList"][0]["language"], "de");
+    assert_eq!(progress["languageList"][0]["translatedCount"], 2);
+    assert_eq!(progress["languageList"][0]["translatedWordCount"], 3);
+    assert_eq!(progress["languageList"][1]["language"], "fr");
+    assert_eq!(progress["languageList"][1]["translatedCount"], 1);
+    assert_eq!(progress["languageList"][1]["translatedWordCount"], 2);
+    assert_eq!(progress["contributorIdList"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn lists_the_progress_of_each_project_shown() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let game_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let manual_id: Uuid = alice.post_ok("/project", json!({ "name": "Manual" })).await;
+    let empty_id: Uuid = alice.post_ok("/project", json!({ "name": "Empty" })).await;
+    for title in ["Intro", "Outro"] {
+        let unit_id = common::add_unit(&alice, game_id, title, &["Hello", "Bye"]).await;
+        common::add_commit(&alice, unit_id, "de", &["Hallo", ""]).await;
+    }
+    common::add_unit(&alice, manual_id, "Setup", &["Plug it in"]).await;
+    let response = alice
+        .post("/project/archive", json!({ "id": manual_id }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    let progress_of = |project_list: &Value, id: Uuid| {
+        project_list
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|t| t["id"] == json!(id))
+            .map(|t| t["progress"].clone())
+    };
+
+    let project_list: Value = alice.get_ok("/project").await;
+    let progress = progress_of(&project_list, game_id).unwrap();
+    assert_eq!(progress["unitCount"], 2);
+    assert_eq!(progress["lineCount"], 4);
+    assert_eq!(progress["languageList"][0]["translatedCount"], 2);
+    assert_eq!(
+        progress_of(&project_list, empty_id).unwrap()["unitCount"],
+        0
+    );
+    assert_eq!(progress_of(&project_list, manual_id), None);
+
+    let project_list: Value = alice.get_ok("/project?include-archived=true").await;
+    let progress = progress_of(&project_list, manual_id).unwrap();
+    assert_eq!(progress["unitCount"], 1);
+    assert_eq!(progress["wordCount"], 3);
+}
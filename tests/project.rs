@@ -0,0 +1,205 @@
+//! Project lifecycle and metadata.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::Api;
+
+#[tokio::test]
+async fn renames_and_archives_projects() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    create_user(database.repo.clone(), "bob", "bobpass12345", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+    let bob = Api::sign_in(&app, "bob", "bobpass12345").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+
+    // Only owners rename and archive.
+    let response = bob
+        .post(
+            "/project/rename",
+            json!({ "id": project_id, "name": "Mine" }),
+        )
+        .await;
+    assert_eq!(response.status(), 401);
+    let response = bob
+        .post("/project/archive", json!({ "id": project_id }))
+        .await;
+    assert_eq!(response.status(), 401);
+
+    let response = alice
+        .post(
+            "/project/rename",
+            json!({ "id": project_id, "name": "Sequel" }),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+    let response = alice
+        .post("/project/archive", json!({ "id": project_id }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    // Archived projects are hidden by default and read-only.
+    let project_list: Vec<Value> = alice.get_ok("/project").await;
+    assert!(project_list.is_empty());
+    let project_list: Vec<Value> = alice.get_ok("/project?include-archived=true").await;
+    assert_eq!(project_list.len(), 1);
+    assert_eq!(project_list[0]["name"], "Sequel");
+    assert_eq!(project_list[0]["isArchived"], true);
+
+    let response = alice
+        .post(
+            "/unit",
+            json!({ "projectId": project_id, "title": "Intro", "sourceList": [] }),
+        )
+        .await;
+    assert_eq!(response.status(), 409);
+    let response = alice
+        .post(
+            "/project/rename",
+            json!({ "id": project_id, "name": "Prequel" }),
+        )
+        .await;
+    assert_eq!(response.status(), 409);
+
+    let response = alice
+        .post("/project/unarchive", json!({ "id": project_id }))
+        .await;
+    assert_eq!(response.status(), 200);
+    common::add_unit(&alice, project_id, "Intro", &["Hello"]).await;
+}
+
+#[tokio::test]
+async fn deletes_projects_with_everything_in_them() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let other_id: Uuid = alice.post_ok("/project", json!({ "name": "Other" })).await;
+    let unit_id = common::add_unit(&alice, project_id, "Intro", &["Hello", "Bye"]).await;
+    let other_unit_id = common::add_unit(&alice, other_id, "Intro", &["Hello"]).await;
+    common::add_commit(&alice, unit_id, "de", &["Hallo", ""]).await;
+    common::add_commit(&alice, unit_id, "de", &["Hallo", "Tschüss"]).await;
+    common::add_commit(&alice, other_unit_id, "de", &["Hallo"]).await;
+    alice
+        .post_ok::<Value>("/auth/invite", json!({ "projectId": project_id }))
+        .await;
+
+    let response = alice
+        .post("/project/delete", json!({ "id": project_id }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    assert!(database.repo.get_project_by_id(project_id).is_err());
+    assert!(database.repo.get_unit_by_id(unit_id).is_err());
+    assert!(database
+        .repo
+        .get_commit_by_unit_id(unit_id, None)
+        .unwrap()
+        .is_empty());
+
+    // The deletion is logged with what it took along.
+    let event_list = database
+        .repo
+        .get_audit_event(
+            mts_server::repo::AuditEventFilter {
+                kind: Some(String::from("project.delete")),
+                ..Default::default()
+            },
+            0,
+            None,
+        )
+        .unwrap();
+    assert_eq!(
+        event_list[0].detail,
+        json!({ "projectId": project_id, "name": "Game", "unitCount": 1, "commitCount": 2 })
+    );
+
+    // Other projects are left alone.
+    assert_eq!(
+        database
+            .repo
+            .get_commit_by_unit_id(other_unit_id, None)
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn normalizes_and_validates_metadata() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice
+        .post_ok(
+            "/project",
+            json!({
+                "name": "Game",
+                "sourceLanguage": "EN-us",
+                "targetLanguageList": ["zh-hant-tw", "DE"],
+                "description": "A game",
+            }),
+        )
+        .await;
+    let project: Value = alice
+        .get_ok(&format!("/project/by-id?id={}", project_id))
+        .await;
+    assert_eq!(project["sourceLanguage"], "en-US");
+    assert_eq!(project["targetLanguageList"], json!(["zh-Hant-TW", "de"]));
+    assert_eq!(project["description"], "A game");
+    assert_eq!(project["settings"], json!({}));
+
+    for metadata in [
+        json!({ "targetLanguageList": ["de", "DE"] }),
+        json!({ "targetLanguageList": ["en_US"] }),
+        json!({ "sourceLanguage": "" }),
+        json!({ "qaProfile": "" }),
+        json!({ "settings": [] }),
+    ] {
+        let mut request = json!({ "id": project_id });
+        request
+            .as_object_mut()
+            .unwrap()
+            .extend(metadata.as_object().unwrap().clone());
+        let response = alice.post("/project/update", request).await;
+        assert_eq!(response.status(), 400, "{}", metadata);
+    }
+
+    // Missing fields stay, null clears them.
+    let project: Value = alice
+        .post_ok(
+            "/project/update",
+            json!({ "id": project_id, "sourceLanguage": null, "qaProfile": "ui" }),
+        )
+        .await;
+    assert_eq!(project["sourceLanguage"], Value::Null);
+    assert_eq!(project["qaProfile"], "ui");
+    assert_eq!(project["targetLanguageList"], json!(["zh-Hant-TW", "de"]));
+    assert_eq!(project["description"], "A game");
+
+    // Archived projects keep their metadata.
+    let response = alice
+        .post("/project/archive", json!({ "id": project_id }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let response = alice
+        .post(
+            "/project/update",
+            json!({ "id": project_id, "description": "A sequel" }),
+        )
+        .await;
+    assert_eq!(response.status(), 409);
+}
@@ -0,0 +1,202 @@
+//! Filling repetitions of translated lines on commit.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::Api;
+
+async fn head_content_list(api: &Api, unit_id: Uuid) -> Vec<String> {
+    let record_list: Vec<Value> = api
+        .get_ok(&format!(
+            "/commit/head/record?unit-id={}&language=de",
+            unit_id
+        ))
+        .await;
+
+    record_list
+        .iter()
+        .map(|t| String::from(t["content"].as_str().unwrap()))
+        .collect::<Vec<_>>()
+}
+
+#[tokio::test]
+async fn fills_repetitions_in_the_unit() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let unit_id = common::add_unit(
+        &alice,
+        project_id,
+        "Intro",
+        &["Yes", "No", "Yes", "No", "Yes", "Maybe"],
+    )
+    .await;
+
+    // Lines translated two ways in the commit are not filled, nor are lines
+    // already translated.
+    let result: Value = alice
+        .post_ok(
+            "/commit",
+            json!({
+                "unitId": unit_id,
+                "language": "de",
+                "recordList": [
+                    { "sq": 1, "content": "Ja" },
+                    { "sq": 2, "content": "Nein" },
+                    { "sq": 4, "content": "Nö" },
+                    { "sq": 5, "content": "Jawohl" },
+                ],
+                "propagate": {},
+            }),
+        )
+        .await;
+    assert_eq!(result["propagatedList"], json!(null));
+    assert_eq!(
+        head_content_list(&alice, unit_id).await,
+        vec!["Ja", "Nein", "Nö", "Jawohl"]
+    );
+
+    let result: Value = alice
+        .post_ok(
+            "/commit",
+            json!({
+                "unitId": unit_id,
+                "language": "de",
+                "recordList": [
+                    { "sq": 1, "content": "Ja" },
+                    { "sq": 2, "content": "Nein" },
+                    { "sq": 3, "content": "" },
+                    { "sq": 4, "content": "Nö" },
+                    { "sq": 5, "content": "" },
+                    { "sq": 6, "content": "Vielleicht" },
+                ],
+                "propagate": { "scope": "unit" },
+            }),
+        )
+        .await;
+    // Only lines the commit changes are copied.
+    assert_eq!(result["propagatedList"], json!(null));
+
+    let result: Value = alice
+        .post_ok(
+            "/commit",
+            json!({
+                "unitId": unit_id,
+                "language": "de",
+                "recordList": [
+                    { "sq": 1, "content": "Ja!" },
+                    { "sq": 2, "content": "Nein" },
+                    { "sq": 3, "content": "" },
+                    { "sq": 4, "content": "Nö" },
+                    { "sq": 5, "content": "" },
+                    { "sq": 6, "content": "Vielleicht" },
+                ],
+                "propagate": { "scope": "unit" },
+            }),
+        )
+        .await;
+    let propagated = &result["propagatedList"][0];
+    assert_eq!(propagated["unitId"], json!(unit_id));
+    assert_eq!(propagated["sqList"], json!([3, 5]));
+    assert_eq!(
+        head_content_list(&alice, unit_id).await,
+        vec!["Ja!", "Nein", "Ja!", "Nö", "Ja!", "Vielleicht"]
+    );
+
+    // The filled commit follows the one it was filled from.
+    let commit: Value = alice
+        .get_ok(&format!(
+            "/commit/by-id?id={}",
+            propagated["commitId"].as_str().unwrap()
+        ))
+        .await;
+    assert_eq!(commit["propagatedFromId"], result["id"]);
+}
+
+#[tokio::test]
+async fn fills_repetitions_across_the_project() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let add_unit = |title: &'static str, source_list: Value| {
+        let alice = &alice;
+        async move {
+            alice
+                .post_ok::<Uuid>(
+                    "/unit",
+                    json!({ "projectId": project_id, "title": title, "sourceList": source_list }),
+                )
+                .await
+        }
+    };
+    let intro_id = add_unit(
+        "Intro",
+        json!([{ "sq": 1, "content": "Hi", "meta": { "speaker": "Mira" } }]),
+    )
+    .await;
+    let outro_id = add_unit(
+        "Outro",
+        json!([
+            { "sq": 1, "content": "Hi", "meta": { "speaker": "Mira" } },
+            { "sq": 2, "content": "Hi", "meta": { "speaker": "Tom" } },
+        ]),
+    )
+    .await;
+    let credits_id = add_unit(
+        "Credits",
+        json!([{ "sq": 1, "content": "Hi", "meta": { "speaker": "Mira" } }]),
+    )
+    .await;
+    common::add_commit(&alice, credits_id, "de", &["Servus"]).await;
+
+    let result: Value = alice
+        .post_ok(
+            "/commit",
+            json!({
+                "unitId": intro_id,
+                "language": "de",
+                "recordList": [{ "sq": 1, "content": "Hallo" }],
+                "propagate": { "scope": "project", "matchKeyList": ["speaker"] },
+            }),
+        )
+        .await;
+    assert_eq!(
+        result["propagatedList"],
+        json!([{
+            "unitId": outro_id,
+            "commitId": result["propagatedList"][0]["commitId"],
+            "sqList": [1],
+        }])
+    );
+    assert_eq!(head_content_list(&alice, outro_id).await, vec!["Hallo"]);
+    assert_eq!(head_content_list(&alice, credits_id).await, vec!["Servus"]);
+
+    // Without match keys, the other speaker is filled too.
+    let result: Value = alice
+        .post_ok(
+            "/commit",
+            json!({
+                "unitId": intro_id,
+                "language": "de",
+                "recordList": [{ "sq": 1, "content": "Hallo!" }],
+                "propagate": { "scope": "project" },
+            }),
+        )
+        .await;
+    assert_eq!(result["propagatedList"][0]["sqList"], json!([2]));
+    assert_eq!(
+        head_content_list(&alice, outro_id).await,
+        vec!["Hallo", "Hallo!"]
+    );
+}
@@ -0,0 +1,154 @@
+//! Find and replace over the latest translations of a project.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::Api;
+
+async fn head_content_list(api: &Api, unit_id: Uuid, language: &str) -> Vec<String> {
+    let record_list: Vec<Value> = api
+        .get_ok(&format!(
+            "/commit/head/record?unit-id={}&language={}",
+            unit_id, language
+        ))
+        .await;
+
+    record_list
+        .iter()
+        .map(|t| String::from(t["content"].as_str().unwrap()))
+        .collect::<Vec<_>>()
+}
+
+#[tokio::test]
+async fn replaces_in_every_unit_after_a_dry_run() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let intro_id = common::add_unit(&alice, project_id, "Intro", &["Hello", "Bye"]).await;
+    let outro_id = common::add_unit(&alice, project_id, "Outro", &["Hello"]).await;
+    let credits_id = common::add_unit(&alice, project_id, "Credits", &["Thanks"]).await;
+    common::add_commit(&alice, intro_id, "de", &["Der Drache", "Tschüss"]).await;
+    common::add_commit(&alice, outro_id, "de", &["Ein drache, zwei Drachen"]).await;
+    common::add_commit(&alice, credits_id, "de", &["Danke"]).await;
+    common::add_commit(&alice, outro_id, "fr", &["Un drache"]).await;
+
+    let request = json!({
+        "projectId": project_id,
+        "language": "de",
+        "pattern": "drache",
+        "replacement": "Lindwurm",
+        "dryRun": true,
+    });
+    let result: Value = alice.post_ok("/replace", request.clone()).await;
+    assert_eq!(
+        result["changeList"],
+        json!([
+            {
+                "unitId": intro_id,
+                "unitTitle": "Intro",
+                "sq": 1,
+                "before": "Der Drache",
+                "after": "Der Lindwurm",
+            },
+            {
+                "unitId": outro_id,
+                "unitTitle": "Outro",
+                "sq": 1,
+                "before": "Ein drache, zwei Drachen",
+                "after": "Ein Lindwurm, zwei Lindwurmn",
+            },
+        ])
+    );
+    assert_eq!(result["commitList"], json!([]));
+    assert_eq!(
+        head_content_list(&alice, intro_id, "de").await,
+        vec!["Der Drache", "Tschüss"]
+    );
+
+    // The same changes are committed, one commit per changed unit.
+    let mut request = request;
+    request["dryRun"] = json!(false);
+    let result: Value = alice.post_ok("/replace", request).await;
+    assert_eq!(result["changeList"].as_array().unwrap().len(), 2);
+    let unit_id_list = result["commitList"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["unitId"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(unit_id_list, vec![json!(intro_id), json!(outro_id)]);
+
+    assert_eq!(
+        head_content_list(&alice, intro_id, "de").await,
+        vec!["Der Lindwurm", "Tschüss"]
+    );
+    assert_eq!(
+        head_content_list(&alice, outro_id, "de").await,
+        vec!["Ein Lindwurm, zwei Lindwurmn"]
+    );
+    assert_eq!(
+        head_content_list(&alice, credits_id, "de").await,
+        vec!["Danke"]
+    );
+    assert_eq!(
+        head_content_list(&alice, outro_id, "fr").await,
+        vec!["Un drache"]
+    );
+}
+
+#[tokio::test]
+async fn replaces_with_patterns() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let unit_id = common::add_unit(&alice, project_id, "Intro", &["Gold", "Price"]).await;
+    common::add_commit(&alice, unit_id, "de", &["10 Gold", "Preis: 5 gold"]).await;
+
+    let replace = |pattern: &str, replacement: &str, mode: &str, case_sensitive: bool| {
+        alice.post(
+            "/replace",
+            json!({
+                "projectId": project_id,
+                "language": "de",
+                "pattern": pattern,
+                "replacement": replacement,
+                "mode": mode,
+                "caseSensitive": case_sensitive,
+                "dryRun": true,
+            }),
+        )
+    };
+    let after_list = |result: Value| {
+        result["changeList"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| String::from(t["after"].as_str().unwrap()))
+            .collect::<Vec<_>>()
+    };
+
+    // Groups are expanded in regular expressions only.
+    let response = replace(r"(\d+) Gold", "$1 G", "regex", true).await;
+    assert_eq!(after_list(response.json().await.unwrap()), vec!["10 G"]);
+    let response = replace("gold", "$1", "literal", false).await;
+    assert_eq!(
+        after_list(response.json().await.unwrap()),
+        vec!["10 $1", "Preis: 5 $1"]
+    );
+
+    for (pattern, mode) in [("", "literal"), ("(", "regex")] {
+        let response = replace(pattern, "x", mode, false).await;
+        assert_eq!(response.status(), 400, "{}", pattern);
+    }
+}
@@ -0,0 +1,118 @@
+//! The contributor report of the translation work on a project.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::Api;
+
+#[tokio::test]
+async fn reports_the_work_of_each_editor() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    let alice_id = create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let (bob_id, bob) = common::join(&app, &alice, project_id, "bob").await;
+    let unit_id = common::add_unit(
+        &alice,
+        project_id,
+        "Intro",
+        &["Hello world", "Goodbye", "See you soon"],
+    )
+    .await;
+
+    common::add_commit(&alice, unit_id, "de", &["Hallo Welt", "", ""]).await;
+    common::add_commit(&bob, unit_id, "de", &["Hallo Welt!", "Tschüss", ""]).await;
+    common::add_commit(&alice, unit_id, "de", &["Hallo Welt!", "", ""]).await;
+
+    let report_list: Value = alice
+        .get_ok(&format!("/report/contributor?project-id={}", project_id))
+        .await;
+    assert_eq!(
+        report_list,
+        json!([
+            {
+                "editorId": alice_id,
+                "editorName": "alice",
+                "commitCount": 2,
+                "unitCount": 1,
+                "changedLineCount": 2,
+                "newLineCount": 1,
+                "editedLineCount": 0,
+                "newWordCount": 2,
+                "editedWordCount": 0,
+            },
+            {
+                "editorId": bob_id,
+                "editorName": "bob",
+                "commitCount": 1,
+                "unitCount": 1,
+                "changedLineCount": 2,
+                "newLineCount": 1,
+                "editedLineCount": 1,
+                "newWordCount": 1,
+                "editedWordCount": 2,
+            },
+        ])
+    );
+
+    // The same rows in CSV, narrowed to one editor.
+    let response = alice
+        .get(&format!(
+            "/report/contributor?project-id={}&editor-id={}&format=csv",
+            project_id, bob_id
+        ))
+        .await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/csv; charset=utf-8"
+    );
+    assert_eq!(
+        response.text().await.unwrap(),
+        format!(
+            "editor_id,editor_name,commit_count,unit_count,changed_line_count,\
+            new_line_count,edited_line_count,new_word_count,edited_word_count\r\n\
+            {},bob,1,1,2,1,1,1,2\r\n",
+            bob_id
+        )
+    );
+
+    let report_list: Value = alice
+        .get_ok(&format!(
+            "/report/contributor?project-id={}&since=2100-01-01T00:00:00Z",
+            project_id
+        ))
+        .await;
+    assert_eq!(report_list, json!([]));
+}
+
+#[tokio::test]
+async fn limits_the_report_to_owners() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let (_, bob) = common::join(&app, &alice, project_id, "bob").await;
+
+    let response = bob
+        .get(&format!("/report/contributor?project-id={}", project_id))
+        .await;
+    assert_eq!(response.status(), 401);
+
+    let response = alice
+        .get(&format!(
+            "/report/contributor?project-id={}&since=2000-01-02T00:00:00Z&until=2000-01-01T00:00:00Z",
+            project_id
+        ))
+        .await;
+    assert_eq!(response.status(), 400);
+}
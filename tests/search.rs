@@ -0,0 +1,167 @@
+//! Searching sources, meta and translations across projects.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::Api;
+
+/// The unit title, line, field and language of each hit.
+fn hit_key_list(result: &Value) -> Vec<(String, i64, String, Option<String>)> {
+    result["hitList"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| {
+            (
+                String::from(t["unitTitle"].as_str().unwrap()),
+                t["sq"].as_i64().unwrap(),
+                String::from(t["field"].as_str().unwrap()),
+                t["language"].as_str().map(String::from),
+            )
+        })
+        .collect::<Vec<_>>()
+}
+
+fn key(
+    title: &str,
+    sq: i64,
+    field: &str,
+    language: Option<&str>,
+) -> (String, i64, String, Option<String>) {
+    (
+        String::from(title),
+        sq,
+        String::from(field),
+        language.map(String::from),
+    )
+}
+
+#[tokio::test]
+async fn searches_each_field_with_each_mode() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let unit_id: Uuid = alice
+        .post_ok(
+            "/unit",
+            json!({
+                "projectId": project_id,
+                "title": "Intro",
+                "sourceList": [
+                    { "sq": 1, "content": "The dragon wakes", "meta": { "speaker": "Narrator" } },
+                    { "sq": 2, "content": "Run from the Dragon!" },
+                ],
+            }),
+        )
+        .await;
+    common::add_commit(&alice, unit_id, "de", &["Der Drache erwacht", "Flieh!"]).await;
+    common::add_commit(&alice, unit_id, "fr", &["Le dragon se réveille", ""]).await;
+
+    let search = |query: &str| {
+        let alice = &alice;
+        let query = String::from(query);
+        async move { alice.get_ok::<Value>(&format!("/search?{}", query)).await }
+    };
+
+    let result = search("q=dragon").await;
+    assert_eq!(
+        hit_key_list(&result),
+        vec![
+            key("Intro", 1, "source", None),
+            key("Intro", 1, "translation", Some("fr")),
+            key("Intro", 2, "source", None),
+        ]
+    );
+    assert_eq!(result["hasMore"], false);
+    assert_eq!(
+        result["hitList"][0]["snippet"],
+        json!([
+            { "text": "The ", "isMatch": false },
+            { "text": "dragon", "isMatch": true },
+            { "text": " wakes", "isMatch": false },
+        ])
+    );
+
+    let result = search("q=Dragon&mode=substring&case-sensitive=true&field-list=source").await;
+    assert_eq!(hit_key_list(&result), vec![key("Intro", 2, "source", None)]);
+
+    let result = search("q=dra.he&mode=regex&language=de").await;
+    assert_eq!(
+        hit_key_list(&result),
+        vec![key("Intro", 1, "translation", Some("de"))]
+    );
+
+    // Expressions follow the syntax of the highlighter, not the database.
+    let result = search("q=%5Cbdragon%5Cb&mode=regex&field-list=source").await;
+    assert_eq!(
+        hit_key_list(&result),
+        vec![
+            key("Intro", 1, "source", None),
+            key("Intro", 2, "source", None),
+        ]
+    );
+    let result = search("q=%5Cbdrag%5Cb&mode=regex").await;
+    assert_eq!(hit_key_list(&result), vec![]);
+    let result = search("q=%28%3FP%3Cverb%3Ewakes%29&mode=regex&field-list=source").await;
+    assert_eq!(hit_key_list(&result), vec![key("Intro", 1, "source", None)]);
+    assert_eq!(
+        result["hitList"][0]["snippet"][1],
+        json!({ "text": "wakes", "isMatch": true })
+    );
+
+    // Pages are cut after matching.
+    let result = search("q=dragon&mode=regex&limit=1&offset=1").await;
+    assert_eq!(
+        hit_key_list(&result),
+        vec![key("Intro", 1, "translation", Some("fr"))]
+    );
+    assert_eq!(result["hasMore"], true);
+
+    let result = search("q=narrator&field-list=meta").await;
+    assert_eq!(hit_key_list(&result), vec![key("Intro", 1, "meta", None)]);
+
+    // One more hit than the limit tells there is a next page.
+    let result = search("q=dragon&limit=2").await;
+    assert_eq!(result["hitList"].as_array().unwrap().len(), 2);
+    assert_eq!(result["hasMore"], true);
+    let result = search("q=dragon&limit=2&offset=2").await;
+    assert_eq!(hit_key_list(&result), vec![key("Intro", 2, "source", None)]);
+    assert_eq!(result["hasMore"], false);
+
+    for query in ["q=%20", "q=(&mode=regex", "q=dragon&field-list=title"] {
+        let response = alice.get(&format!("/search?{}", query)).await;
+        assert_eq!(response.status(), 400, "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn searches_only_projects_of_the_member() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    create_user(database.repo.clone(), "bob", "bobpass12345", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+    let bob = Api::sign_in(&app, "bob", "bobpass12345").await;
+
+    let game_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let manual_id: Uuid = bob.post_ok("/project", json!({ "name": "Manual" })).await;
+    common::add_unit(&alice, game_id, "Intro", &["A dragon"]).await;
+    common::add_unit(&bob, manual_id, "Setup", &["No dragon here"]).await;
+
+    let result: Value = alice.get_ok("/search?q=dragon").await;
+    assert_eq!(hit_key_list(&result), vec![key("Intro", 1, "source", None)]);
+    assert_eq!(result["hitList"][0]["projectId"], json!(game_id));
+
+    let response = alice
+        .get(&format!("/search?q=dragon&project-id={}", manual_id))
+        .await;
+    assert_eq!(response.status(), 401);
+}
@@ -0,0 +1,151 @@
+//! Source updates carrying translations over to the new version.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::Api;
+
+fn source_list(line_list: &[&str]) -> Value {
+    json!(line_list
+        .iter()
+        .enumerate()
+        .map(|(i, t)| json!({ "sq": i + 1, "content": t }))
+        .collect::<Vec<_>>())
+}
+
+async fn head_record_list(api: &Api, unit_id: Uuid) -> Vec<(String, bool)> {
+    let record_list: Vec<Value> = api
+        .get_ok(&format!(
+            "/commit/head/record?unit-id={}&language=de",
+            unit_id
+        ))
+        .await;
+
+    record_list
+        .into_iter()
+        .map(|t| {
+            (
+                String::from(t["content"].as_str().unwrap()),
+                t["needsRetranslation"].as_bool().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>()
+}
+
+#[tokio::test]
+async fn carries_translations_over() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let unit_id = common::add_unit(
+        &alice,
+        project_id,
+        "Intro",
+        &["Good morning, everyone", "Bye", "Removed"],
+    )
+    .await;
+    common::add_commit(
+        &alice,
+        unit_id,
+        "de",
+        &["Guten Morgen, alle", "Tschüss", "Weg"],
+    )
+    .await;
+
+    let result: Value = alice
+        .post_ok(
+            "/unit/source",
+            json!({
+                "unitId": unit_id,
+                "sourceVersion": 1,
+                "sourceList": source_list(&["New", "Good morning, everyone!", "Bye"]),
+            }),
+        )
+        .await;
+    assert_eq!(result["sourceVersion"], 2);
+    assert_eq!(result["unchangedCount"], 1);
+    assert_eq!(result["editedCount"], 1);
+    assert_eq!(result["addedCount"], 1);
+    assert_eq!(result["removedCount"], 1);
+
+    assert_eq!(
+        head_record_list(&alice, unit_id).await,
+        vec![
+            (String::from("Guten Morgen, alle"), true),
+            (String::from("Tschüss"), false),
+        ]
+    );
+
+    // A stale version is refused.
+    let response = alice
+        .post(
+            "/unit/source",
+            json!({ "unitId": unit_id, "sourceVersion": 1, "sourceList": [] }),
+        )
+        .await;
+    assert_eq!(response.status(), 409);
+
+    // Sending every line again keeps the flag, editing the line clears it.
+    common::add_commit(
+        &alice,
+        unit_id,
+        "de",
+        &["Neu", "Guten Morgen, alle", "Tschüss"],
+    )
+    .await;
+    assert_eq!(
+        head_record_list(&alice, unit_id).await,
+        vec![
+            (String::from("Neu"), false),
+            (String::from("Guten Morgen, alle"), true),
+            (String::from("Tschüss"), false),
+        ]
+    );
+    common::add_commit(
+        &alice,
+        unit_id,
+        "de",
+        &["Neu", "Guten Morgen, alle!", "Tschüss"],
+    )
+    .await;
+    assert_eq!(
+        head_record_list(&alice, unit_id).await,
+        vec![
+            (String::from("Neu"), false),
+            (String::from("Guten Morgen, alle!"), false),
+            (String::from("Tschüss"), false),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn lets_only_owners_update_sources() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    create_user(database.repo.clone(), "bob", "bobpass12345", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+    let bob = Api::sign_in(&app, "bob", "bobpass12345").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let unit_id = common::add_unit(&alice, project_id, "Intro", &["Hello"]).await;
+
+    let response = bob
+        .post(
+            "/unit/source",
+            json!({ "unitId": unit_id, "sourceList": source_list(&["Mine"]) }),
+        )
+        .await;
+    assert_eq!(response.status(), 401);
+
+    let source_list: Vec<Value> = alice.get_ok(&format!("/unit/source?id={}", unit_id)).await;
+    assert_eq!(source_list[0]["content"], "Hello");
+}
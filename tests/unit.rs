@@ -0,0 +1,195 @@
+//! Renaming, ordering, moving and deleting units.
+
+mod common;
+
+use mts_server::auth::service::create_user;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::Api;
+
+async fn title_list(api: &Api, project_id: Uuid) -> Vec<String> {
+    let unit_list: Vec<Value> = api
+        .get_ok(&format!("/unit?project-id={}", project_id))
+        .await;
+
+    unit_list
+        .into_iter()
+        .map(|t| String::from(t["title"].as_str().unwrap()))
+        .collect::<Vec<_>>()
+}
+
+#[tokio::test]
+async fn renames_and_reorders_units() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    create_user(database.repo.clone(), "bob", "bobpass12345", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+    let bob = Api::sign_in(&app, "bob", "bobpass12345").await;
+
+    let project_id: Uuid = alice.post_ok("/project", json!({ "name": "Game" })).await;
+    let a_id = common::add_unit(&alice, project_id, "A", &[]).await;
+    let b_id = common::add_unit(&alice, project_id, "B", &[]).await;
+    let c_id = common::add_unit(&alice, project_id, "C", &[]).await;
+    assert_eq!(title_list(&alice, project_id).await, vec!["A", "B", "C"]);
+
+    // Only members add and rename units.
+    let response = bob
+        .post(
+            "/unit",
+            json!({ "projectId": project_id, "title": "Mine", "sourceList": [] }),
+        )
+        .await;
+    assert_eq!(response.status(), 401);
+    let response = bob
+        .post("/unit/rename", json!({ "id": a_id, "title": "Mine" }))
+        .await;
+    assert_eq!(response.status(), 401);
+    let response = alice
+        .post("/unit/rename", json!({ "id": a_id, "title": "Prologue" }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    let response = alice
+        .post(
+            "/unit/reorder",
+            json!({ "projectId": project_id, "unitIdList": [c_id, a_id, b_id] }),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        title_list(&alice, project_id).await,
+        vec!["C", "Prologue", "B"]
+    );
+
+    // The list must hold every unit once.
+    for unit_id_list in [json!([c_id, a_id]), json!([c_id, a_id, b_id, b_id])] {
+        let response = alice
+            .post(
+                "/unit/reorder",
+                json!({ "projectId": project_id, "unitIdList": unit_id_list }),
+            )
+            .await;
+        assert_eq!(response.status(), 409);
+    }
+}
+
+#[tokio::test]
+async fn moves_units_between_projects() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    create_user(database.repo.clone(), "bob", "bobpass12345", false).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+    let bob = Api::sign_in(&app, "bob", "bobpass12345").await;
+
+    let project_id: Uuid = alice
+        .post_ok(
+            "/project",
+            json!({ "name": "Game", "targetLanguageList": ["de", "fr"] }),
+        )
+        .await;
+    let other_id: Uuid = alice
+        .post_ok(
+            "/project",
+            json!({ "name": "Sequel", "targetLanguageList": ["de"] }),
+        )
+        .await;
+    let bob_project_id: Uuid = bob.post_ok("/project", json!({ "name": "Bob's" })).await;
+    common::add_unit(&alice, other_id, "Existing", &[]).await;
+    let unit_id = common::add_unit(&alice, project_id, "Intro", &["Hello"]).await;
+    common::add_commit(&alice, unit_id, "de", &["Hallo"]).await;
+    common::add_commit(&alice, unit_id, "fr", &["Bonjour"]).await;
+
+    // Owners of both projects only.
+    let response = alice
+        .post(
+            "/unit/move",
+            json!({ "id": unit_id, "projectId": bob_project_id }),
+        )
+        .await;
+    assert_eq!(response.status(), 401);
+
+    // The French translation has no place in the other project.
+    let response = alice
+        .post(
+            "/unit/move",
+            json!({ "id": unit_id, "projectId": other_id }),
+        )
+        .await;
+    assert_eq!(response.status(), 409);
+    assert_eq!(
+        database.repo.get_unit_by_id(unit_id).unwrap().project_id,
+        project_id
+    );
+
+    let response = alice
+        .post(
+            "/project/update",
+            json!({ "id": other_id, "targetLanguageList": ["de", "fr"] }),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+    let response = alice
+        .post(
+            "/unit/move",
+            json!({ "id": unit_id, "projectId": other_id }),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+
+    assert_eq!(
+        title_list(&alice, other_id).await,
+        vec!["Existing", "Intro"]
+    );
+    assert!(title_list(&alice, project_id).await.is_empty());
+    common::add_commit(&alice, unit_id, "fr", &["Salut"]).await;
+}
+
+#[tokio::test]
+async fn deletes_units() {
+    let Some((database, app)) = common::setup().await else {
+        return;
+    };
+    create_user(database.repo.clone(), "alice", "alicepass1234", false).unwrap();
+    create_user(database.repo.clone(), "admin", "adminpass1234", true).unwrap();
+    let alice = Api::sign_in(&app, "alice", "alicepass1234").await;
+    let admin = Api::sign_in(&app, "admin", "adminpass1234").await;
+
+    let project_id: Uuid = admin.post_ok("/project", json!({ "name": "Game" })).await;
+    let invitation: Value = admin
+        .post_ok(
+            "/auth/invite",
+            json!({ "projectId": project_id, "role": "translator" }),
+        )
+        .await;
+    let response = common::client()
+        .post(format!("{}/api/auth/accept-invite", app))
+        .json(&json!({ "token": invitation["token"], "name": "carol", "pass": "carolpass1234" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let carol = Api::sign_in(&app, "carol", "carolpass1234").await;
+
+    let unit_id = common::add_unit(&admin, project_id, "Intro", &["Hello"]).await;
+    common::add_commit(&admin, unit_id, "de", &["Hallo"]).await;
+
+    // Translators and outsiders cannot delete.
+    for api in [&alice, &carol] {
+        let response = api.post("/unit/delete", json!({ "id": unit_id })).await;
+        assert_eq!(response.status(), 401);
+    }
+
+    let response = admin.post("/unit/delete", json!({ "id": unit_id })).await;
+    assert_eq!(response.status(), 200);
+    assert!(database.repo.get_unit_by_id(unit_id).is_err());
+    assert!(database
+        .repo
+        .get_commit_by_unit_id(unit_id, None)
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,129 @@
+//! User management by admins and the bootstrap of the first admin.
+
+mod common;
+
+use mts_server::auth::service::{create_initial_admin, create_user};
+use reqwest::header;
+use serde_json::{json, Value};
+
+use common::{client, TestDatabase};
+
+#[tokio::test]
+async fn creates_the_initial_admin_once() {
+    let Some(database) = TestDatabase::create() else {
+        return;
+    };
+    let app = common::spawn(mts_server::build_app(common::app_state(
+        database.repo.clone(),
+    )))
+    .await;
+    let client = client();
+
+    assert!(create_initial_admin(database.repo.clone(), "admin", "adminpass1234").unwrap());
+    assert!(!create_initial_admin(database.repo.clone(), "admin", "adminpass1234").unwrap());
+
+    // Renaming the admin does not bring back an account named admin.
+    let token = common::sign_in(&client, &app, "admin", "adminpass1234").await;
+    let admin_id = common::claim_id(&client, &app, &token).await.unwrap();
+    let response = client
+        .post(format!("{}/api/auth/user/rename", app))
+        .header(header::COOKIE, &token)
+        .json(&json!({ "id": admin_id, "name": "root" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    assert!(!create_initial_admin(database.repo.clone(), "admin", "adminpass1234").unwrap());
+    assert!(database
+        .repo
+        .get_user_by_name(String::from("admin"))
+        .is_err());
+}
+
+#[tokio::test]
+async fn manages_users() {
+    let Some(database) = TestDatabase::create() else {
+        return;
+    };
+    let app = common::spawn(mts_server::build_app(common::app_state(
+        database.repo.clone(),
+    )))
+    .await;
+    let client = client();
+
+    let admin_id = create_user(database.repo.clone(), "admin", "adminpass1234", true).unwrap();
+    let user_id = create_user(database.repo.clone(), "bob", "bobpass12345", false).unwrap();
+    let admin_token = common::sign_in(&client, &app, "admin", "adminpass1234").await;
+    let user_token = common::sign_in(&client, &app, "bob", "bobpass12345").await;
+
+    let post = |token: &str, path: &str, body: Value| {
+        client
+            .post(format!("{}/api/auth/user/{}", app, path))
+            .header(header::COOKIE, token)
+            .json(&body)
+            .send()
+    };
+
+    // Only admins manage users.
+    let response = post(
+        &user_token,
+        "set-admin",
+        json!({ "id": user_id, "isAdmin": true }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 401);
+
+    let response = post(
+        &admin_token,
+        "rename",
+        json!({ "id": user_id, "name": "robert" }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let user_list: Value = client
+        .get(format!("{}/api/auth/user/list?search=rob", app))
+        .header(header::COOKIE, &admin_token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        user_list,
+        json!([{ "id": user_id, "name": "robert", "isAdmin": false, "isActive": true }])
+    );
+
+    // Admins cannot lock themselves out.
+    let response = post(
+        &admin_token,
+        "set-admin",
+        json!({ "id": admin_id, "isAdmin": false }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 409);
+    let response = post(
+        &admin_token,
+        "set-active",
+        json!({ "id": admin_id, "isActive": false }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 409);
+
+    // Deactivating a user ends their sessions.
+    let response = post(
+        &admin_token,
+        "set-active",
+        json!({ "id": user_id, "isActive": false }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(common::claim_id(&client, &app, &user_token).await, None);
+}